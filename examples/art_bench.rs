@@ -0,0 +1,93 @@
+//! Compare `Art`, dispatching on the types of its nodes by `map_enum` and `map_enum_mut` of enum `ointer`s,
+//! against `BTreeMap` on byte string keys, for times of insertion, lookup, iteration and prefix scans.
+//!
+//! Run with `cargo run --release --example art_bench`.
+
+mod common;
+
+use common::lcg;
+use ointer::art::Art;
+use std::{collections::BTreeMap, hint::black_box, time::Instant};
+
+/// Keys of decimal numbers, sharing prefixes densely, and of words over a few letters, sharing them sparsely.
+fn keys(n: usize) -> Vec<Vec<u8>> {
+    lcg(1)
+        .take(n)
+        .enumerate()
+        .map(|(i, x)| match i % 2 {
+            0 => format!("{}", x >> 40).into_bytes(),
+            _ => (0..8 + (x >> 61))
+                .map(|j| b"etaoinsh"[(x >> (3 * j)) as usize & 7])
+                .collect(),
+        })
+        .collect()
+}
+
+fn time(name: &str, f: impl FnOnce()) {
+    let t = Instant::now();
+    f();
+    println!("{name:>24}: {:?}", t.elapsed());
+}
+
+fn main() {
+    const N: usize = 500_000;
+    let keys = keys(N);
+    let (mut art, mut btree) = (Art::new(), BTreeMap::new());
+    time("Art insert", || {
+        keys.iter().for_each(|k| {
+            art.insert(k, k.len());
+        })
+    });
+    time("BTreeMap insert", || {
+        keys.iter().for_each(|k| {
+            btree.insert(k.clone(), k.len());
+        })
+    });
+    println!("Art nodes (4, 16, 48, 256, leaf): {:?}", art.node_counts());
+    time("Art get", || {
+        keys.iter().for_each(|k| {
+            black_box(art.get(k));
+        })
+    });
+    time("BTreeMap get", || {
+        keys.iter().for_each(|k| {
+            black_box(btree.get(k));
+        })
+    });
+    time("Art get_mut", || {
+        keys.iter().for_each(|k| *art.get_mut(k).unwrap() += 1)
+    });
+    time("Art iter", || {
+        black_box(art.iter().map(|(_, v)| v).sum::<usize>());
+    });
+    time("BTreeMap iter", || {
+        black_box(btree.values().sum::<usize>());
+    });
+    time("Art prefix", || {
+        for p in ["1", "42", "et", "ta"] {
+            black_box(art.prefix(p).count());
+        }
+    });
+    time("BTreeMap prefix", || {
+        for p in ["1", "42", "et", "ta"] {
+            let p = p.as_bytes();
+            black_box(
+                btree
+                    .range(p.to_vec()..)
+                    .take_while(|(k, _)| k.starts_with(p))
+                    .count(),
+            );
+        }
+    });
+    time("Art remove", || {
+        keys.iter().for_each(|k| {
+            art.remove(k);
+        })
+    });
+    time("BTreeMap remove", || {
+        keys.iter().for_each(|k| {
+            btree.remove(k);
+        })
+    });
+    assert!(art.is_empty() && btree.is_empty());
+}
@@ -0,0 +1,12 @@
+//! Helpers shared by the benchmarks.
+
+/// Iterate over the pseudorandom words of a linear congruential generator seeded with `seed`.
+pub fn lcg(seed: u64) -> impl Iterator<Item = u64> {
+    core::iter::successors(Some(seed), |x| {
+        Some(
+            x.wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407),
+        )
+    })
+    .skip(1)
+}
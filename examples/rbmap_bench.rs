@@ -0,0 +1,143 @@
+//! Compare `RbMap`, with colours in the stolen bits of `ORc`s, against a persistent left-leaning red-black tree
+//! with an `Rc` and a `bool` per node, for node sizes and times of building and copying on insertion.
+//!
+//! Run with `cargo run --release --example rbmap_bench`.
+
+mod common;
+
+use common::lcg;
+use ointer::rbmap::RbMap;
+use std::{hint::black_box, rc::Rc, time::Instant};
+
+#[derive(Clone)]
+struct Node {
+    key: u64,
+    value: u64,
+    red: bool,
+    left: Option<Rc<Node>>,
+    right: Option<Rc<Node>>,
+}
+
+fn is_red(link: &Option<Rc<Node>>) -> bool {
+    link.as_ref().is_some_and(|n| n.red)
+}
+
+fn rotate_left(mut h: Rc<Node>) -> Rc<Node> {
+    let mut x = Rc::make_mut(&mut h).right.take().unwrap();
+    let (hn, xn) = (Rc::make_mut(&mut h), Rc::make_mut(&mut x));
+    hn.right = xn.left.take();
+    (xn.red, hn.red) = (hn.red, true);
+    xn.left = Some(h);
+    x
+}
+
+fn rotate_right(mut h: Rc<Node>) -> Rc<Node> {
+    let mut x = Rc::make_mut(&mut h).left.take().unwrap();
+    let (hn, xn) = (Rc::make_mut(&mut h), Rc::make_mut(&mut x));
+    hn.left = xn.right.take();
+    (xn.red, hn.red) = (hn.red, true);
+    xn.right = Some(h);
+    x
+}
+
+fn flip_colors(h: &mut Rc<Node>) {
+    let n = Rc::make_mut(h);
+    n.red = !n.red;
+    for c in [&mut n.left, &mut n.right] {
+        let c = Rc::make_mut(c.as_mut().unwrap());
+        c.red = !c.red;
+    }
+}
+
+fn insert(h: Option<Rc<Node>>, key: u64, value: u64) -> Rc<Node> {
+    let Some(mut h) = h else {
+        return Rc::new(Node {
+            key,
+            value,
+            red: true,
+            left: None,
+            right: None,
+        });
+    };
+    let n = Rc::make_mut(&mut h);
+    match key.cmp(&n.key) {
+        std::cmp::Ordering::Less => n.left = Some(insert(n.left.take(), key, value)),
+        std::cmp::Ordering::Greater => n.right = Some(insert(n.right.take(), key, value)),
+        std::cmp::Ordering::Equal => n.value = value,
+    }
+    if is_red(&h.right) && !is_red(&h.left) {
+        h = rotate_left(h);
+    }
+    if is_red(&h.left) && h.left.as_ref().is_some_and(|l| is_red(&l.left)) {
+        h = rotate_right(h);
+    }
+    if is_red(&h.left) && is_red(&h.right) {
+        flip_colors(&mut h);
+    }
+    h
+}
+
+#[derive(Clone, Default)]
+struct PlainMap {
+    root: Option<Rc<Node>>,
+}
+
+impl PlainMap {
+    fn insert(&mut self, key: u64, value: u64) {
+        let mut root = insert(self.root.take(), key, value);
+        Rc::make_mut(&mut root).red = false;
+        self.root = Some(root);
+    }
+}
+
+fn keys(n: usize) -> impl Iterator<Item = u64> {
+    lcg(1).take(n).map(|x| x >> 16)
+}
+
+fn time(name: &str, f: impl FnOnce()) {
+    let t = Instant::now();
+    f();
+    println!("{name:>36}: {:?}", t.elapsed());
+}
+
+fn main() {
+    const N: usize = 200_000;
+    println!(
+        "node size: {} bytes with ORc, {} bytes with Rc and bool",
+        RbMap::<u64, u64>::NODE_SIZE,
+        size_of::<Node>()
+    );
+    time("RbMap insert", || {
+        let mut m = RbMap::new();
+        keys(N).for_each(|k| {
+            m.insert(k, k);
+        });
+        black_box(m);
+    });
+    time("Rc and bool insert", || {
+        let mut m = PlainMap::default();
+        keys(N).for_each(|k| m.insert(k, k));
+        black_box(m);
+    });
+    // Keeping every version copies a path per insertion.
+    time("RbMap insert keeping versions", || {
+        let mut m = RbMap::new();
+        let versions: Vec<_> = keys(N / 4)
+            .map(|k| {
+                m.insert(k, k);
+                m.clone()
+            })
+            .collect();
+        black_box(versions);
+    });
+    time("Rc and bool insert keeping versions", || {
+        let mut m = PlainMap::default();
+        let versions: Vec<_> = keys(N / 4)
+            .map(|k| {
+                m.insert(k, k);
+                m.clone()
+            })
+            .collect();
+        black_box(versions);
+    });
+}
@@ -0,0 +1,515 @@
+//! This module defines a stable `Allocator` trait, pointers `BoxIn`/`RcIn`/`ArcIn` allocated by it, and `ointer`s
+//! that wrap them, named `BBoxIn/BRcIn/BArcIn`(byte stolen) and `OBoxIn/ORcIn/OArcIn`(orientable, with 1 bit
+//! stolen), with weak counterparts `BRcWeakIn/BArcWeakIn` and `ORcWeakIn/OArcWeakIn`, suffixed by `In` like their
+//! pointers, apart from the `ointer`s of `std` pointers.
+//!
+//! The allocator is stored in the allocation, next to the value, so that these `ointer`s stay the size of a
+//! `usize` and keep access to their allocator through tag operations.
+//! ```
+//! use ointer::allocator::{Allocator, Global, OBoxIn};
+//! let mut o = OBoxIn::new_in(1, Global);
+//! o.flip();
+//! *o += 1;
+//! assert_eq!((*o, o.o()), (2, true));
+//! let _: &Global = o.allocator();
+//! ```
+
+use crate::ointer::*;
+use core::{
+    alloc::Layout,
+    cell::Cell,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+    sync::atomic::{fence, AtomicUsize, Ordering},
+};
+
+/// Allocators usable by the `ointer`s of this module, a stable subset of `core::alloc::Allocator`.
+///
+/// # Safety
+/// `allocate` must return a block fitting `layout`, which stays valid until passed to `deallocate`
+/// with the same layout, on this allocator or a clone of it.
+pub unsafe trait Allocator {
+    /// Allocate a block fitting `layout`, or return `None` on failure.
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>>;
+    /// Deallocate block `ptr` allocated by `allocate` with `layout`.
+    ///
+    /// # Safety
+    /// `ptr` must have been returned by `allocate` with `layout` and not deallocated yet.
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+}
+
+/// The global allocator.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct Global;
+
+unsafe impl Allocator for Global {
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+        if layout.size() == 0 {
+            return NonNull::new(layout.align() as *mut u8);
+        }
+        NonNull::new(unsafe { std::alloc::alloc(layout) })
+    }
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            std::alloc::dealloc(ptr.as_ptr(), layout);
+        }
+    }
+}
+
+unsafe impl<A: Allocator + ?Sized> Allocator for &A {
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+        (**self).allocate(layout)
+    }
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        (**self).deallocate(ptr, layout)
+    }
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+unsafe impl Allocator for crate::arena::LowArena {
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+        NonNull::new(unsafe { core::alloc::GlobalAlloc::alloc(self, layout) })
+    }
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        core::alloc::GlobalAlloc::dealloc(self, ptr.as_ptr(), layout)
+    }
+}
+
+/// Allocate an uninitialized `I` by `alloc`.
+fn allocate_in<I, A: Allocator>(alloc: &A) -> NonNull<I> {
+    let layout = Layout::new::<I>();
+    match alloc.allocate(layout) {
+        Some(p) => p.cast(),
+        None => std::alloc::handle_alloc_error(layout),
+    }
+}
+
+/// Take the allocator out of the allocation `p` and deallocate it.
+///
+/// # Safety
+/// `alloc` must point into the allocation `p` of `I`, whose other fields are already dropped.
+unsafe fn deallocate_in<I, A: Allocator>(p: NonNull<I>, alloc: *const A) {
+    let alloc = alloc.read();
+    alloc.deallocate(p.cast(), Layout::new::<I>());
+}
+
+/// Abort if reference count `n` went above `isize::MAX`, like `std`, as leaked pointers could make it wrap around.
+fn check_count(n: usize) -> usize {
+    if n > isize::MAX as usize {
+        std::process::abort();
+    }
+    n
+}
+
+#[repr(C)]
+struct BoxInner<T, A> {
+    alloc: A,
+    value: T,
+}
+
+/// `Box` allocated by allocator `A`, stored next to the value.
+pub struct BoxIn<T, A: Allocator = Global>(NonNull<BoxInner<T, A>>, PhantomData<BoxInner<T, A>>);
+
+unsafe impl<T: Send, A: Allocator + Send> Send for BoxIn<T, A> {}
+unsafe impl<T: Sync, A: Allocator + Sync> Sync for BoxIn<T, A> {}
+
+impl<T, A: Allocator> BoxIn<T, A> {
+    /// Allocate `x` by `alloc`.
+    pub fn new_in(x: T, alloc: A) -> Self {
+        let p = allocate_in::<BoxInner<T, A>, A>(&alloc);
+        unsafe { p.as_ptr().write(BoxInner { alloc, value: x }) };
+        Self(p, PhantomData)
+    }
+    /// Get the allocator.
+    pub fn allocator(&self) -> &A {
+        unsafe { &(*self.0.as_ptr()).alloc }
+    }
+}
+
+impl<T> BoxIn<T> {
+    /// Allocate `x` by the global allocator.
+    pub fn new(x: T) -> Self {
+        Self::new_in(x, Global)
+    }
+}
+
+impl<T: Default, A: Allocator + Default> Default for BoxIn<T, A> {
+    fn default() -> Self {
+        Self::new_in(T::default(), A::default())
+    }
+}
+
+impl<T: Clone, A: Allocator + Clone> Clone for BoxIn<T, A> {
+    fn clone(&self) -> Self {
+        Self::new_in(T::clone(self), self.allocator().clone())
+    }
+}
+
+impl<T, A: Allocator> Drop for BoxIn<T, A> {
+    fn drop(&mut self) {
+        let p = self.0.as_ptr();
+        unsafe {
+            core::ptr::drop_in_place(&mut (*p).value);
+            deallocate_in(self.0, &(*p).alloc);
+        }
+    }
+}
+
+impl<T, A: Allocator> Deref for BoxIn<T, A> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &(*self.0.as_ptr()).value }
+    }
+}
+
+impl<T, A: Allocator> DerefMut for BoxIn<T, A> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut (*self.0.as_ptr()).value }
+    }
+}
+
+#[repr(C)]
+struct RcInner<T, A> {
+    strong: Cell<usize>,
+    weak: Cell<usize>,
+    alloc: A,
+    value: T,
+}
+
+/// `Rc` allocated by allocator `A`, stored next to the value.
+pub struct RcIn<T, A: Allocator = Global>(NonNull<RcInner<T, A>>, PhantomData<RcInner<T, A>>);
+
+impl<T, A: Allocator> RcIn<T, A> {
+    /// Allocate `x` by `alloc`.
+    pub fn new_in(x: T, alloc: A) -> Self {
+        let p = allocate_in::<RcInner<T, A>, A>(&alloc);
+        let (strong, weak) = (Cell::new(1), Cell::new(1));
+        unsafe {
+            p.as_ptr().write(RcInner {
+                strong,
+                weak,
+                alloc,
+                value: x,
+            })
+        };
+        Self(p, PhantomData)
+    }
+    /// Get the allocator.
+    pub fn allocator(&self) -> &A {
+        unsafe { &(*self.0.as_ptr()).alloc }
+    }
+    /// Get the number of `RcIn`s pointing to this allocation.
+    pub fn strong_count(&self) -> usize {
+        unsafe { (*self.0.as_ptr()).strong.get() }
+    }
+    /// Get the number of `RcWeakIn`s pointing to this allocation.
+    pub fn weak_count(&self) -> usize {
+        unsafe { (*self.0.as_ptr()).weak.get() - 1 }
+    }
+    /// Make a `RcWeakIn` pointing to this allocation.
+    pub fn downgrade(&self) -> RcWeakIn<T, A> {
+        let weak = unsafe { &(*self.0.as_ptr()).weak };
+        weak.set(check_count(weak.get() + 1));
+        RcWeakIn(self.0, PhantomData)
+    }
+}
+
+impl<T> RcIn<T> {
+    /// Allocate `x` by the global allocator.
+    pub fn new(x: T) -> Self {
+        Self::new_in(x, Global)
+    }
+}
+
+impl<T: Default, A: Allocator + Default> Default for RcIn<T, A> {
+    fn default() -> Self {
+        Self::new_in(T::default(), A::default())
+    }
+}
+
+impl<T, A: Allocator> Clone for RcIn<T, A> {
+    fn clone(&self) -> Self {
+        let strong = unsafe { &(*self.0.as_ptr()).strong };
+        strong.set(check_count(strong.get() + 1));
+        Self(self.0, PhantomData)
+    }
+}
+
+impl<T, A: Allocator> Drop for RcIn<T, A> {
+    fn drop(&mut self) {
+        let p = self.0.as_ptr();
+        unsafe {
+            let strong = (*p).strong.get() - 1;
+            (*p).strong.set(strong);
+            if strong == 0 {
+                core::ptr::drop_in_place(&mut (*p).value);
+                drop(RcWeakIn(self.0, PhantomData));
+            }
+        }
+    }
+}
+
+impl<T, A: Allocator> Deref for RcIn<T, A> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &(*self.0.as_ptr()).value }
+    }
+}
+
+/// Weak pointer to the allocation of a [`RcIn`], which does not keep its value alive.
+pub struct RcWeakIn<T, A: Allocator = Global>(NonNull<RcInner<T, A>>, PhantomData<RcInner<T, A>>);
+
+impl<T, A: Allocator> RcWeakIn<T, A> {
+    /// Get a `RcIn` to the value, or `None` if it has been dropped.
+    pub fn upgrade(&self) -> Option<RcIn<T, A>> {
+        let strong = unsafe { &(*self.0.as_ptr()).strong };
+        match strong.get() {
+            0 => None,
+            n => {
+                strong.set(check_count(n + 1));
+                Some(RcIn(self.0, PhantomData))
+            }
+        }
+    }
+}
+
+impl<T, A: Allocator> Clone for RcWeakIn<T, A> {
+    fn clone(&self) -> Self {
+        let weak = unsafe { &(*self.0.as_ptr()).weak };
+        weak.set(check_count(weak.get() + 1));
+        Self(self.0, PhantomData)
+    }
+}
+
+impl<T, A: Allocator> Drop for RcWeakIn<T, A> {
+    fn drop(&mut self) {
+        let p = self.0.as_ptr();
+        unsafe {
+            let weak = (*p).weak.get() - 1;
+            (*p).weak.set(weak);
+            if weak == 0 {
+                deallocate_in(self.0, &(*p).alloc);
+            }
+        }
+    }
+}
+
+#[repr(C)]
+struct ArcInner<T, A> {
+    strong: AtomicUsize,
+    weak: AtomicUsize,
+    alloc: A,
+    value: T,
+}
+
+/// `Arc` allocated by allocator `A`, stored next to the value.
+pub struct ArcIn<T, A: Allocator = Global>(NonNull<ArcInner<T, A>>, PhantomData<ArcInner<T, A>>);
+
+unsafe impl<T: Send + Sync, A: Allocator + Send + Sync> Send for ArcIn<T, A> {}
+unsafe impl<T: Send + Sync, A: Allocator + Send + Sync> Sync for ArcIn<T, A> {}
+
+impl<T, A: Allocator> ArcIn<T, A> {
+    /// Allocate `x` by `alloc`.
+    pub fn new_in(x: T, alloc: A) -> Self {
+        let p = allocate_in::<ArcInner<T, A>, A>(&alloc);
+        let (strong, weak) = (AtomicUsize::new(1), AtomicUsize::new(1));
+        unsafe {
+            p.as_ptr().write(ArcInner {
+                strong,
+                weak,
+                alloc,
+                value: x,
+            })
+        };
+        Self(p, PhantomData)
+    }
+    /// Get the allocator.
+    pub fn allocator(&self) -> &A {
+        unsafe { &(*self.0.as_ptr()).alloc }
+    }
+    /// Get the number of `ArcIn`s pointing to this allocation.
+    pub fn strong_count(&self) -> usize {
+        unsafe { (*self.0.as_ptr()).strong.load(Ordering::Acquire) }
+    }
+    /// Get the number of `ArcWeakIn`s pointing to this allocation.
+    pub fn weak_count(&self) -> usize {
+        unsafe { (*self.0.as_ptr()).weak.load(Ordering::Acquire) - 1 }
+    }
+    /// Make an `ArcWeakIn` pointing to this allocation.
+    pub fn downgrade(&self) -> ArcWeakIn<T, A> {
+        check_count(unsafe { (*self.0.as_ptr()).weak.fetch_add(1, Ordering::Relaxed) });
+        ArcWeakIn(self.0, PhantomData)
+    }
+}
+
+impl<T> ArcIn<T> {
+    /// Allocate `x` by the global allocator.
+    pub fn new(x: T) -> Self {
+        Self::new_in(x, Global)
+    }
+}
+
+impl<T: Default, A: Allocator + Default> Default for ArcIn<T, A> {
+    fn default() -> Self {
+        Self::new_in(T::default(), A::default())
+    }
+}
+
+impl<T, A: Allocator> Clone for ArcIn<T, A> {
+    fn clone(&self) -> Self {
+        check_count(unsafe { (*self.0.as_ptr()).strong.fetch_add(1, Ordering::Relaxed) });
+        Self(self.0, PhantomData)
+    }
+}
+
+impl<T, A: Allocator> Drop for ArcIn<T, A> {
+    fn drop(&mut self) {
+        let p = self.0.as_ptr();
+        unsafe {
+            if (*p).strong.fetch_sub(1, Ordering::Release) == 1 {
+                fence(Ordering::Acquire);
+                core::ptr::drop_in_place(&mut (*p).value);
+                drop(ArcWeakIn(self.0, PhantomData));
+            }
+        }
+    }
+}
+
+impl<T, A: Allocator> Deref for ArcIn<T, A> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &(*self.0.as_ptr()).value }
+    }
+}
+
+/// Weak pointer to the allocation of an [`ArcIn`], which does not keep its value alive.
+pub struct ArcWeakIn<T, A: Allocator = Global>(
+    NonNull<ArcInner<T, A>>,
+    PhantomData<ArcInner<T, A>>,
+);
+
+unsafe impl<T: Send + Sync, A: Allocator + Send + Sync> Send for ArcWeakIn<T, A> {}
+unsafe impl<T: Send + Sync, A: Allocator + Send + Sync> Sync for ArcWeakIn<T, A> {}
+
+impl<T, A: Allocator> ArcWeakIn<T, A> {
+    /// Get an `ArcIn` to the value, or `None` if it has been dropped.
+    pub fn upgrade(&self) -> Option<ArcIn<T, A>> {
+        let strong = unsafe { &(*self.0.as_ptr()).strong };
+        let mut n = strong.load(Ordering::Relaxed);
+        loop {
+            if n == 0 {
+                return None;
+            }
+            check_count(n);
+            match strong.compare_exchange_weak(n, n + 1, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => return Some(ArcIn(self.0, PhantomData)),
+                Err(m) => n = m,
+            }
+        }
+    }
+}
+
+impl<T, A: Allocator> Clone for ArcWeakIn<T, A> {
+    fn clone(&self) -> Self {
+        check_count(unsafe { (*self.0.as_ptr()).weak.fetch_add(1, Ordering::Relaxed) });
+        Self(self.0, PhantomData)
+    }
+}
+
+impl<T, A: Allocator> Drop for ArcWeakIn<T, A> {
+    fn drop(&mut self) {
+        let p = self.0.as_ptr();
+        unsafe {
+            if (*p).weak.fetch_sub(1, Ordering::Release) == 1 {
+                fence(Ordering::Acquire);
+                deallocate_in(self.0, &(*p).alloc);
+            }
+        }
+    }
+}
+
+macro_rules! impl_pointer_in {
+    ($($pointer:ident),*) => {
+        $(
+            impl<T: core::fmt::Debug, A: Allocator> core::fmt::Debug for $pointer<T, A> {
+                fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+                    (**self).fmt(f)
+                }
+            }
+
+            impl<T: core::hash::Hash, A: Allocator> core::hash::Hash for $pointer<T, A> {
+                fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+                    (**self).hash(state)
+                }
+            }
+
+            impl<T: PartialEq, A: Allocator> PartialEq for $pointer<T, A> {
+                fn eq(&self, rhs: &Self) -> bool {
+                    **self == **rhs
+                }
+            }
+
+            impl<T: Eq, A: Allocator> Eq for $pointer<T, A> {}
+
+            impl<T: PartialOrd, A: Allocator> PartialOrd for $pointer<T, A> {
+                fn partial_cmp(&self, rhs: &Self) -> Option<core::cmp::Ordering> {
+                    (**self).partial_cmp(&**rhs)
+                }
+            }
+
+            impl<T: Ord, A: Allocator> Ord for $pointer<T, A> {
+                fn cmp(&self, rhs: &Self) -> core::cmp::Ordering {
+                    (**self).cmp(&**rhs)
+                }
+            }
+        )*
+    };
+}
+
+impl_pointer_in!(BoxIn, RcIn, ArcIn);
+
+macro_rules! impl_weak_in {
+    ($($pointer:ident),*) => {
+        $(
+            impl<T, A: Allocator> core::fmt::Debug for $pointer<T, A> {
+                fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+                    f.write_str("(Weak)")
+                }
+            }
+        )*
+    };
+}
+
+impl_weak_in!(RcWeakIn, ArcWeakIn);
+
+// The pointers of this module point to the start of their allocations, aligned for the inner structs.
+macro_rules! impl_stealable_pointer_in {
+    ($($pointer:ident, $inner:ident);*) => {
+        $(
+            unsafe impl<T, A: Allocator> StealablePointer for $pointer<T, A> {
+                const FREE_HIGH_BITS: usize = FREE_ADDRESS_BITS;
+                const FREE_LOW_BITS: usize =
+                    core::mem::align_of::<$inner<T, A>>().trailing_zeros() as usize;
+            }
+        )*
+    };
+}
+
+impl_stealable_pointer_in! {
+    BoxIn, BoxInner;
+    RcIn, RcInner;
+    RcWeakIn, RcInner;
+    ArcIn, ArcInner;
+    ArcWeakIn, ArcInner
+}
+
+define_ointer_strong!(OBoxIn, BoxIn<A: Allocator = Global>, 1);
+define_ointer_strong!(BBoxIn, BoxIn<A: Allocator = Global>, 8);
+define_shared_ointer!(ORcIn, RcIn<A: Allocator = Global>, ORcWeakIn, RcWeakIn, 1);
+define_shared_ointer!(BRcIn, RcIn<A: Allocator = Global>, BRcWeakIn, RcWeakIn, 8);
+define_shared_ointer!(OArcIn, ArcIn<A: Allocator = Global>, OArcWeakIn, ArcWeakIn, 1);
+define_shared_ointer!(BArcIn, ArcIn<A: Allocator = Global>, BArcWeakIn, ArcWeakIn, 8);
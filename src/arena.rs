@@ -0,0 +1,261 @@
+//! This module defines `LowArena`, an allocator handing out addresses below `2^LowArena::ADDRESS_BITS` from a
+//! virtual region reserved by `mmap`, and `ArenaBox`, a `Box` like `ointer` allocated there, with up to
+//! `usize::BITS - LowArena::ADDRESS_BITS` high bits stolen.
+//!
+//! ```
+//! use ointer::{arena::ArenaBox, Ointer};
+//! let mut o = ArenaBox::<_, 24>::new(String::from("low"));
+//! o.set_usize(0xabcdef);
+//! o.push('!');
+//! assert_eq!((o.as_str(), o.get_usize()), ("low!", 0xabcdef));
+//! ```
+
+use crate::ointer::*;
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    ffi::{c_int, c_void},
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+};
+use std::sync::{Mutex, OnceLock};
+
+extern "C" {
+    fn mmap(
+        addr: *mut c_void,
+        len: usize,
+        prot: c_int,
+        flags: c_int,
+        fd: c_int,
+        off: i64,
+    ) -> *mut c_void;
+    fn munmap(addr: *mut c_void, len: usize) -> c_int;
+}
+
+const PROT_READ: c_int = 0x1;
+const PROT_WRITE: c_int = 0x2;
+const MAP_PRIVATE: c_int = 0x2;
+const MAP_ANONYMOUS: c_int = 0x20;
+const MAP_NORESERVE: c_int = 0x4000;
+const MAP_FIXED_NOREPLACE: c_int = 0x100000;
+
+/// Size of the reserved region, which is only backed by memory once touched.
+const REGION_SIZE: usize = 1 << 30;
+
+/// Hints tried in turn to reserve the region below `2^LowArena::ADDRESS_BITS`.
+const REGION_HINTS: [usize; 3] = [0x8000_0000, 0x4000_0000, 0xc000_0000];
+
+/// Bump pointer and free lists of the reserved region, one per power of two size class.
+struct Region {
+    next: usize,
+    end: usize,
+    free: [usize; usize::BITS as usize],
+}
+
+fn region() -> Option<&'static Mutex<Region>> {
+    static REGION: OnceLock<Option<Mutex<Region>>> = OnceLock::new();
+    REGION
+        .get_or_init(|| {
+            REGION_HINTS.iter().find_map(|&hint| {
+                let p = unsafe {
+                    mmap(
+                        hint as *mut c_void,
+                        REGION_SIZE,
+                        PROT_READ | PROT_WRITE,
+                        MAP_PRIVATE | MAP_ANONYMOUS | MAP_NORESERVE | MAP_FIXED_NOREPLACE,
+                        -1,
+                        0,
+                    )
+                } as usize;
+                if p == !0 {
+                    None
+                } else if p + REGION_SIZE > 1 << LowArena::ADDRESS_BITS {
+                    // Older kernels take the address as a mere hint.
+                    unsafe { munmap(p as *mut c_void, REGION_SIZE) };
+                    None
+                } else {
+                    Some(Mutex::new(Region {
+                        next: p,
+                        end: p + REGION_SIZE,
+                        free: [0; usize::BITS as usize],
+                    }))
+                }
+            })
+        })
+        .as_ref()
+}
+
+/// Allocator handing out addresses below `2^ADDRESS_BITS` only, so that their high bits can all be stolen.
+///
+/// Blocks are rounded up to a power of two aligned to itself, and freed blocks are kept for reuse.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct LowArena;
+
+impl LowArena {
+    /// Number of low bits that addresses handed out by `LowArena` may use.
+    pub const ADDRESS_BITS: usize = 32;
+    /// Number of high bits left free by addresses handed out by `LowArena`.
+    pub const FREE_HIGH_BITS: usize = usize::BITS as usize - Self::ADDRESS_BITS;
+
+    /// Whether the region could be reserved.
+    pub fn is_available() -> bool {
+        region().is_some()
+    }
+}
+
+unsafe impl GlobalAlloc for LowArena {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let class = layout
+            .size()
+            .max(layout.align())
+            .max(16)
+            .next_power_of_two();
+        let index = class.trailing_zeros() as usize;
+        let Some(region) = region() else {
+            return core::ptr::null_mut();
+        };
+        let mut r = region.lock().unwrap();
+        let p = r.free[index];
+        if p != 0 {
+            r.free[index] = *(p as *const usize);
+            return p as *mut u8;
+        }
+        let p = (r.next + class - 1) & !(class - 1);
+        if p + class > r.end {
+            return core::ptr::null_mut();
+        }
+        r.next = p + class;
+        p as *mut u8
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let class = layout
+            .size()
+            .max(layout.align())
+            .max(16)
+            .next_power_of_two();
+        let index = class.trailing_zeros() as usize;
+        if let Some(region) = region() {
+            let mut r = region.lock().unwrap();
+            *(ptr as *mut usize) = r.free[index];
+            r.free[index] = ptr as usize;
+        }
+    }
+}
+
+/// `Box` like `ointer` allocated by [`LowArena`], with high `N` bits stolen, `N` up to
+/// `LowArena::FREE_HIGH_BITS`, which is checked at compile time.
+/// ```compile_fail
+/// use ointer::arena::ArenaBox;
+/// let _ = ArenaBox::<_, 33>::new(0);
+/// ```
+#[repr(transparent)]
+pub struct ArenaBox<T, const N: usize>(NonNull<T>, PhantomData<T>);
+
+unsafe impl<T: Send, const N: usize> Send for ArenaBox<T, N> {}
+unsafe impl<T: Sync, const N: usize> Sync for ArenaBox<T, N> {}
+
+unsafe impl<T, const N: usize> Ointer<N> for ArenaBox<T, N> {
+    type Pointer = NonNull<T>;
+    const OFFSET: usize = {
+        assert!(
+            N <= LowArena::FREE_HIGH_BITS,
+            "No enough free high bits in arena addresses"
+        );
+        0
+    };
+}
+
+impl<T, const N: usize> ArenaBox<T, N> {
+    /// Allocate `x` in the arena, with high `N` bits all `0`.
+    pub fn new(x: T) -> Self {
+        let layout = Layout::new::<T>();
+        let p = unsafe { LowArena.alloc(layout) } as *mut T;
+        let Some(p) = NonNull::new(p) else {
+            std::alloc::handle_alloc_error(layout)
+        };
+        unsafe { p.as_ptr().write(x) };
+        let mut o = Self(p, PhantomData);
+        o.steal();
+        o
+    }
+    /// Move the value out of the arena.
+    pub fn into_inner(mut self) -> T {
+        self.restore_ptr();
+        let p = core::mem::ManuallyDrop::new(self).0.as_ptr();
+        unsafe {
+            let x = p.read();
+            LowArena.dealloc(p as *mut u8, Layout::new::<T>());
+            x
+        }
+    }
+}
+
+impl<T: Default, const N: usize> Default for ArenaBox<T, N> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for ArenaBox<T, N> {
+    fn clone(&self) -> Self {
+        let mut o = Self::new(T::clone(self));
+        o.set_usize(self.get_usize());
+        o
+    }
+}
+
+impl<T: core::fmt::Debug, const N: usize> core::fmt::Debug for ArenaBox<T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+        (self.get_usize(), &**self).fmt(f)
+    }
+}
+
+impl<T, const N: usize> Drop for ArenaBox<T, N> {
+    fn drop(&mut self) {
+        self.restore_ptr();
+        unsafe {
+            core::ptr::drop_in_place(self.0.as_ptr());
+            LowArena.dealloc(self.0.as_ptr() as *mut u8, Layout::new::<T>());
+        }
+    }
+}
+
+impl<T: core::hash::Hash, const N: usize> core::hash::Hash for ArenaBox<T, N> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        (self.get_usize(), &**self).hash(state)
+    }
+}
+
+impl<T: PartialEq, const N: usize> PartialEq for ArenaBox<T, N> {
+    fn eq(&self, rhs: &Self) -> bool {
+        (self.get_usize(), &**self).eq(&(rhs.get_usize(), &**rhs))
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for ArenaBox<T, N> {}
+
+impl<T: PartialOrd, const N: usize> PartialOrd for ArenaBox<T, N> {
+    fn partial_cmp(&self, rhs: &Self) -> Option<core::cmp::Ordering> {
+        (self.get_usize(), &**self).partial_cmp(&(rhs.get_usize(), &**rhs))
+    }
+}
+
+impl<T: Ord, const N: usize> Ord for ArenaBox<T, N> {
+    fn cmp(&self, rhs: &Self) -> core::cmp::Ordering {
+        (self.get_usize(), &**self).cmp(&(rhs.get_usize(), &**rhs))
+    }
+}
+
+impl<T, const N: usize> Deref for ArenaBox<T, N> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*(self.get_ptr_as_usize() as *const T) }
+    }
+}
+
+impl<T, const N: usize> DerefMut for ArenaBox<T, N> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *(self.get_ptr_as_usize() as *mut T) }
+    }
+}
@@ -0,0 +1,684 @@
+//! This module defines `Art`, an adaptive radix tree mapping byte strings to values, whose children are enum
+//! `ointer`s over `Box`es with the type of node, `Node4`, `Node16`, `Node48`, `Node256` or leaf, in the stolen
+//! bits, dispatched by `map_enum_ref` and `map_enum_ref_mut`.
+//!
+//! Inner nodes grow to the next type when full and shrink back when sparse, and store the bytes shared by all
+//! keys below them as a compressed path, while a key ending at an inner node is kept in it, so that keys may be
+//! prefixes of one another. Entries are iterated in lexicographic order of keys, also when scanning a prefix.
+//! ```
+//! use ointer::art::Art;
+//! let mut t: Art<usize> = ["romane", "romanus", "romulus", "rubens", "ruber"].iter().map(|s| (s, s.len())).collect();
+//! assert_eq!(t.insert("rom", 3), None);
+//! assert_eq!(t.remove("rubens"), Some(6));
+//! let keys: Vec<_> = t.prefix("rom").map(|(k, _)| String::from_utf8_lossy(k).into_owned()).collect();
+//! assert_eq!(keys, ["rom", "romane", "romanus", "romulus"]);
+//! assert_eq!((t.get("ruber"), t.get("rub"), t.len()), (Some(&5), None, 5));
+//! ```
+
+use crate::ointer::*;
+use core::mem::ManuallyDrop;
+
+/// Stored key and value.
+struct Leaf<V> {
+    key: Box<[u8]>,
+    value: V,
+}
+
+/// Compressed path and value of the key ending at an inner node.
+struct Header<V> {
+    prefix: Vec<u8>,
+    end: Option<Box<Leaf<V>>>,
+}
+
+impl<V> Default for Header<V> {
+    fn default() -> Self {
+        Self {
+            prefix: Vec::new(),
+            end: None,
+        }
+    }
+}
+
+/// Inner node with up to `C` children in order of bytes.
+struct Sorted<V, const C: usize> {
+    header: Header<V>,
+    len: u8,
+    keys: [u8; C],
+    children: [Option<Child<V>>; C],
+}
+
+type Node4<V> = Sorted<V, 4>;
+type Node16<V> = Sorted<V, 16>;
+
+/// Inner node with up to 48 children, indexed by bytes.
+struct Node48<V> {
+    header: Header<V>,
+    len: u8,
+    index: [u8; 256],
+    children: [Option<Child<V>>; 48],
+}
+
+/// Inner node with a child for each byte.
+struct Node256<V> {
+    header: Header<V>,
+    len: u16,
+    children: [Option<Child<V>>; 256],
+}
+
+crate::define_enum_ointers! {
+    enum Child<V> {
+        Node4(Box<Node4<V>>) = 0,
+        Node16(Box<Node16<V>>) = 1,
+        Node48(Box<Node48<V>>) = 2,
+        Node256(Box<Node256<V>>) = 3,
+        Leaf(Box<Leaf<V>>) = 4,
+    }
+}
+
+/// Operations common to inner nodes.
+trait Inner<V> {
+    fn header(&self) -> &Header<V>;
+    fn header_mut(&mut self) -> &mut Header<V>;
+    fn len(&self) -> usize;
+    fn capacity(&self) -> usize;
+    fn child(&self, b: u8) -> Option<&Child<V>>;
+    fn child_mut(&mut self, b: u8) -> Option<&mut Option<Child<V>>>;
+    /// Add child `c` at byte `b`, not present yet, with room for it.
+    fn add(&mut self, b: u8, c: Child<V>);
+    /// Remove the entry at byte `b`, present even if its child was taken, returning the child.
+    fn remove(&mut self, b: u8) -> Option<Child<V>>;
+    /// Get the first child at a byte from `from` on.
+    fn next(&self, from: usize) -> Option<(u8, &Child<V>)>;
+    /// Take the header and the children out of the node.
+    fn drain(&mut self) -> (Header<V>, Vec<(u8, Child<V>)>) {
+        let mut bytes = Vec::new();
+        while let Some((b, _)) = self.next(bytes.last().map_or(0, |&b| b as usize + 1)) {
+            bytes.push(b);
+        }
+        let children = bytes
+            .into_iter()
+            .filter_map(|b| Some((b, self.remove(b)?)))
+            .collect();
+        (core::mem::take(self.header_mut()), children)
+    }
+}
+
+impl<V, const C: usize> Inner<V> for Sorted<V, C> {
+    fn header(&self) -> &Header<V> {
+        &self.header
+    }
+    fn header_mut(&mut self) -> &mut Header<V> {
+        &mut self.header
+    }
+    fn len(&self) -> usize {
+        self.len as usize
+    }
+    fn capacity(&self) -> usize {
+        C
+    }
+    fn child(&self, b: u8) -> Option<&Child<V>> {
+        let i = self.keys[..self.len()].binary_search(&b).ok()?;
+        self.children[i].as_ref()
+    }
+    fn child_mut(&mut self, b: u8) -> Option<&mut Option<Child<V>>> {
+        let i = self.keys[..self.len()].binary_search(&b).ok()?;
+        Some(&mut self.children[i])
+    }
+    fn add(&mut self, b: u8, c: Child<V>) {
+        let (n, i) = (
+            self.len(),
+            self.keys[..self.len()].partition_point(|&k| k < b),
+        );
+        self.keys.copy_within(i..n, i + 1);
+        self.children[i..=n].rotate_right(1);
+        (self.keys[i], self.children[i]) = (b, Some(c));
+        self.len += 1;
+    }
+    fn remove(&mut self, b: u8) -> Option<Child<V>> {
+        let (n, i) = (self.len(), self.keys[..self.len()].binary_search(&b).ok()?);
+        self.keys.copy_within(i + 1..n, i);
+        self.children[i..n].rotate_left(1);
+        self.len -= 1;
+        self.children[n - 1].take()
+    }
+    fn next(&self, from: usize) -> Option<(u8, &Child<V>)> {
+        let i = self.keys[..self.len()].partition_point(|&k| (k as usize) < from);
+        Some((*self.keys[..self.len()].get(i)?, self.children[i].as_ref()?))
+    }
+}
+
+impl<V> Inner<V> for Node48<V> {
+    fn header(&self) -> &Header<V> {
+        &self.header
+    }
+    fn header_mut(&mut self) -> &mut Header<V> {
+        &mut self.header
+    }
+    fn len(&self) -> usize {
+        self.len as usize
+    }
+    fn capacity(&self) -> usize {
+        48
+    }
+    fn child(&self, b: u8) -> Option<&Child<V>> {
+        let i = self.index[b as usize].checked_sub(1)?;
+        self.children[i as usize].as_ref()
+    }
+    fn child_mut(&mut self, b: u8) -> Option<&mut Option<Child<V>>> {
+        let i = self.index[b as usize].checked_sub(1)?;
+        Some(&mut self.children[i as usize])
+    }
+    fn add(&mut self, b: u8, c: Child<V>) {
+        let i = self.children.iter().position(Option::is_none).unwrap();
+        (self.index[b as usize], self.children[i]) = (i as u8 + 1, Some(c));
+        self.len += 1;
+    }
+    fn remove(&mut self, b: u8) -> Option<Child<V>> {
+        let i = core::mem::take(&mut self.index[b as usize]).checked_sub(1)?;
+        self.len -= 1;
+        self.children[i as usize].take()
+    }
+    fn next(&self, from: usize) -> Option<(u8, &Child<V>)> {
+        let b = (from..256).find(|&b| self.index[b] != 0)?;
+        Some((b as u8, self.children[self.index[b] as usize - 1].as_ref()?))
+    }
+}
+
+impl<V> Inner<V> for Node256<V> {
+    fn header(&self) -> &Header<V> {
+        &self.header
+    }
+    fn header_mut(&mut self) -> &mut Header<V> {
+        &mut self.header
+    }
+    fn len(&self) -> usize {
+        self.len as usize
+    }
+    fn capacity(&self) -> usize {
+        256
+    }
+    fn child(&self, b: u8) -> Option<&Child<V>> {
+        self.children[b as usize].as_ref()
+    }
+    fn child_mut(&mut self, b: u8) -> Option<&mut Option<Child<V>>> {
+        let c = &mut self.children[b as usize];
+        c.is_some().then_some(c)
+    }
+    fn add(&mut self, b: u8, c: Child<V>) {
+        self.children[b as usize] = Some(c);
+        self.len += 1;
+    }
+    fn remove(&mut self, b: u8) -> Option<Child<V>> {
+        self.len -= 1;
+        self.children[b as usize].take()
+    }
+    fn next(&self, from: usize) -> Option<(u8, &Child<V>)> {
+        let b = (from..256).find(|&b| self.children[b].is_some())?;
+        Some((b as u8, self.children[b].as_ref()?))
+    }
+}
+
+enum NodeRef<'a, V> {
+    Inner(&'a dyn Inner<V>),
+    Leaf(&'a Leaf<V>),
+}
+
+enum NodeMut<'a, V> {
+    Inner(&'a mut dyn Inner<V>),
+    Leaf(&'a mut Leaf<V>),
+}
+
+impl<V> Child<V> {
+    fn leaf(key: &[u8], value: V) -> Self {
+        Self::new_leaf(Box::new(Leaf {
+            key: key.into(),
+            value,
+        }))
+    }
+    /// Make the smallest inner node holding `children`.
+    fn inner(header: Header<V>, children: Vec<(u8, Child<V>)>) -> Self {
+        fn sorted<V, const C: usize>(header: Header<V>) -> Box<Sorted<V, C>> {
+            let children = [const { None }; C];
+            Box::new(Sorted {
+                header,
+                len: 0,
+                keys: [0; C],
+                children,
+            })
+        }
+        let mut c = match children.len() {
+            0..=4 => Self::new_node4(sorted(header)),
+            5..=16 => Self::new_node16(sorted(header)),
+            17..=48 => Self::new_node48(Box::new(Node48 {
+                header,
+                len: 0,
+                index: [0; 256],
+                children: [const { None }; 48],
+            })),
+            _ => Self::new_node256(Box::new(Node256 {
+                header,
+                len: 0,
+                children: [const { None }; 256],
+            })),
+        };
+        let NodeMut::Inner(n) = c.get_mut() else {
+            unreachable!()
+        };
+        children.into_iter().for_each(|(b, c)| n.add(b, c));
+        c
+    }
+    fn get(&self) -> NodeRef<'_, V> {
+        self.map_enum_ref(
+            |n| NodeRef::Inner(n),
+            |n| NodeRef::Inner(n),
+            |n| NodeRef::Inner(n),
+            |n| NodeRef::Inner(n),
+            NodeRef::Leaf,
+        )
+    }
+    fn get_mut(&mut self) -> NodeMut<'_, V> {
+        self.map_enum_ref_mut(
+            |n| NodeMut::Inner(n),
+            |n| NodeMut::Inner(n),
+            |n| NodeMut::Inner(n),
+            |n| NodeMut::Inner(n),
+            NodeMut::Leaf,
+        )
+    }
+    fn into_leaf(self) -> Box<Leaf<V>> {
+        let c = ManuallyDrop::new(self);
+        c.map_enum(
+            |_| unreachable!(),
+            |_| unreachable!(),
+            |_| unreachable!(),
+            |_| unreachable!(),
+            |p| unsafe { core::ptr::read(p) },
+        )
+    }
+}
+
+/// Get the length of the common prefix of `a` and `b`.
+fn common(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+/// Make a node with compressed path `key[depth..depth + p]` holding `old`, after its byte or as the key ending
+/// there, and a leaf for `key`.
+fn split<V>(key: &[u8], depth: usize, p: usize, value: V, old: (Option<u8>, Child<V>)) -> Child<V> {
+    let d = depth + p;
+    let mut header = Header {
+        prefix: key[depth..d].to_vec(),
+        end: None,
+    };
+    let mut children = Vec::with_capacity(2);
+    for (b, c) in [old, (key.get(d).copied(), Child::leaf(key, value))] {
+        match b {
+            Some(b) => children.push((b, c)),
+            None => header.end = Some(c.into_leaf()),
+        }
+    }
+    children.sort_by_key(|&(b, _)| b);
+    Child::inner(header, children)
+}
+
+/// Get the slot of the child at byte `b` of the inner node in `slot`, present.
+fn child_slot<V>(slot: &mut Option<Child<V>>, b: u8) -> &mut Option<Child<V>> {
+    match slot.as_mut().unwrap().get_mut() {
+        NodeMut::Inner(n) => n.child_mut(b).unwrap(),
+        NodeMut::Leaf(_) => unreachable!(),
+    }
+}
+
+/// Insert `value` at `key` below the child in `slot`.
+fn insert<V>(mut slot: &mut Option<Child<V>>, key: &[u8], value: V) -> Option<V> {
+    let mut depth = 0;
+    loop {
+        let n = match slot.as_mut().unwrap().get_mut() {
+            NodeMut::Leaf(l) if *l.key == *key => {
+                return Some(core::mem::replace(&mut l.value, value))
+            }
+            NodeMut::Leaf(l) => {
+                let p = common(&l.key[depth..], &key[depth..]);
+                let b = l.key.get(depth + p).copied();
+                let old = slot.take().unwrap();
+                *slot = Some(split(key, depth, p, value, (b, old)));
+                return None;
+            }
+            NodeMut::Inner(n) => n,
+        };
+        let prefix = &n.header().prefix;
+        let p = common(prefix, &key[depth..]);
+        if p < prefix.len() {
+            let b = prefix[p];
+            n.header_mut().prefix.drain(..=p);
+            let old = slot.take().unwrap();
+            *slot = Some(split(key, depth, p, value, (Some(b), old)));
+            return None;
+        }
+        depth += p;
+        let Some(&b) = key.get(depth) else {
+            let end = &mut n.header_mut().end;
+            return match end {
+                Some(l) => Some(core::mem::replace(&mut l.value, value)),
+                None => {
+                    *end = Some(Child::leaf(key, value).into_leaf());
+                    None
+                }
+            };
+        };
+        if n.child(b).is_none() {
+            if n.len() < n.capacity() {
+                n.add(b, Child::leaf(key, value));
+            } else {
+                let (header, mut children) = n.drain();
+                let i = children.partition_point(|&(k, _)| k < b);
+                children.insert(i, (b, Child::leaf(key, value)));
+                *slot = Some(Child::inner(header, children));
+            }
+            return None;
+        }
+        slot = child_slot(slot, b);
+        depth += 1;
+    }
+}
+
+/// Remove `key` below the child in `slot`, shrinking the node it is removed from and compressing paths.
+///
+/// Inner nodes hold two entries at least, so only that node may shrink.
+fn remove<V>(mut slot: &mut Option<Child<V>>, key: &[u8]) -> Option<V> {
+    if let NodeRef::Leaf(l) = slot.as_ref()?.get() {
+        return (*l.key == *key).then(|| slot.take().unwrap().into_leaf().value);
+    }
+    let mut depth = 0;
+    loop {
+        let NodeMut::Inner(n) = slot.as_mut().unwrap().get_mut() else {
+            unreachable!()
+        };
+        if !key[depth..].starts_with(&n.header().prefix) {
+            return None;
+        }
+        depth += n.header().prefix.len();
+        let Some(&b) = key.get(depth) else {
+            let value = n.header_mut().end.take()?.value;
+            shrink(slot);
+            return Some(value);
+        };
+        match n.child(b)?.get() {
+            NodeRef::Leaf(l) if *l.key == *key => {
+                let value = n.remove(b).unwrap().into_leaf().value;
+                shrink(slot);
+                return Some(value);
+            }
+            NodeRef::Leaf(_) => return None,
+            NodeRef::Inner(_) => {}
+        }
+        slot = child_slot(slot, b);
+        depth += 1;
+    }
+}
+
+/// Shrink the inner node in `slot` after removing an entry from it: replace it by the key ending at it or its
+/// only child when left with one entry, or by a smaller node when sparse.
+fn shrink<V>(slot: &mut Option<Child<V>>) {
+    let NodeMut::Inner(n) = slot.as_mut().unwrap().get_mut() else {
+        unreachable!()
+    };
+    let smaller = match n.capacity() {
+        256 => 48,
+        48 => 16,
+        16 => 4,
+        _ => 0,
+    };
+    match (n.len(), &n.header().end) {
+        (0, _) => *slot = n.header_mut().end.take().map(Child::new_leaf),
+        (1, None) => {
+            let (header, mut children) = n.drain();
+            let (b, mut c) = children.pop().unwrap();
+            if let NodeMut::Inner(m) = c.get_mut() {
+                let prefix = &mut m.header_mut().prefix;
+                prefix.splice(..0, header.prefix.into_iter().chain([b]));
+            }
+            *slot = Some(c);
+        }
+        (len, _) if len <= smaller * 3 / 4 => {
+            let (header, children) = n.drain();
+            *slot = Some(Child::inner(header, children));
+        }
+        _ => {}
+    }
+}
+
+/// Adaptive radix tree mapping byte strings to values of type `V`.
+pub struct Art<V> {
+    root: Option<Child<V>>,
+    len: usize,
+}
+
+impl<V> Drop for Art<V> {
+    fn drop(&mut self) {
+        // Drain inner nodes on an explicit stack, as dropping them recursively would overflow the call stack
+        // below long compressed paths.
+        let mut stack: Vec<_> = self.root.take().into_iter().collect();
+        while let Some(mut c) = stack.pop() {
+            if let NodeMut::Inner(n) = c.get_mut() {
+                stack.extend(n.drain().1.into_iter().map(|(_, d)| d));
+            }
+        }
+    }
+}
+
+impl<V> Default for Art<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Art<V> {
+    /// Make an empty tree.
+    pub const fn new() -> Self {
+        Self { root: None, len: 0 }
+    }
+    /// Get the number of entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// Whether there is no entry.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Get the value of `key`.
+    pub fn get(&self, key: impl AsRef<[u8]>) -> Option<&V> {
+        let key = key.as_ref();
+        let (mut c, mut depth) = (self.root.as_ref()?, 0);
+        loop {
+            let n = match c.get() {
+                NodeRef::Leaf(l) => return (*l.key == *key).then_some(&l.value),
+                NodeRef::Inner(n) => n,
+            };
+            if !key[depth..].starts_with(&n.header().prefix) {
+                return None;
+            }
+            depth += n.header().prefix.len();
+            match key.get(depth) {
+                None => return n.header().end.as_ref().map(|l| &l.value),
+                Some(&b) => c = n.child(b)?,
+            }
+            depth += 1;
+        }
+    }
+    /// Get the value of `key` mutably.
+    pub fn get_mut(&mut self, key: impl AsRef<[u8]>) -> Option<&mut V> {
+        let key = key.as_ref();
+        let (mut c, mut depth) = (self.root.as_mut()?, 0);
+        loop {
+            let n = match c.get_mut() {
+                NodeMut::Leaf(l) => return (*l.key == *key).then_some(&mut l.value),
+                NodeMut::Inner(n) => n,
+            };
+            if !key[depth..].starts_with(&n.header().prefix) {
+                return None;
+            }
+            depth += n.header().prefix.len();
+            match key.get(depth) {
+                None => return n.header_mut().end.as_mut().map(|l| &mut l.value),
+                Some(&b) => c = n.child_mut(b)?.as_mut()?,
+            }
+            depth += 1;
+        }
+    }
+    /// Whether there is an entry for `key`.
+    pub fn contains_key(&self, key: impl AsRef<[u8]>) -> bool {
+        self.get(key).is_some()
+    }
+    /// Insert `value` at `key`, returning the value replaced.
+    pub fn insert(&mut self, key: impl AsRef<[u8]>, value: V) -> Option<V> {
+        let key = key.as_ref();
+        if self.root.is_none() {
+            self.root = Some(Child::leaf(key, value));
+            self.len += 1;
+            return None;
+        }
+        let old = insert(&mut self.root, key, value);
+        self.len += usize::from(old.is_none());
+        old
+    }
+    /// Remove the entry of `key`, returning its value.
+    pub fn remove(&mut self, key: impl AsRef<[u8]>) -> Option<V> {
+        let value = remove(&mut self.root, key.as_ref())?;
+        self.len -= 1;
+        Some(value)
+    }
+    /// Iterate over entries in lexicographic order of keys.
+    pub fn iter(&self) -> Iter<'_, V> {
+        let mut it = Iter {
+            stack: Vec::new(),
+            leaf: None,
+        };
+        if let Some(c) = &self.root {
+            it.visit(c);
+        }
+        it
+    }
+    /// Iterate over entries with keys starting with `prefix`, in lexicographic order of keys.
+    pub fn prefix(&self, prefix: impl AsRef<[u8]>) -> Iter<'_, V> {
+        let prefix = prefix.as_ref();
+        let mut it = Iter {
+            stack: Vec::new(),
+            leaf: None,
+        };
+        let (mut c, mut depth) = match &self.root {
+            Some(c) => (c, 0),
+            None => return it,
+        };
+        loop {
+            let n = match c.get() {
+                NodeRef::Leaf(l) if l.key.starts_with(prefix) => break,
+                NodeRef::Leaf(_) => return it,
+                NodeRef::Inner(n) => n,
+            };
+            let (rest, path) = (&prefix[depth..], &n.header().prefix[..]);
+            if common(rest, path) < rest.len().min(path.len()) {
+                return it;
+            }
+            // Keys below an inner node whose path covers the rest of the prefix all start with it.
+            let Some(&b) = rest.get(path.len()) else {
+                break;
+            };
+            match n.child(b) {
+                Some(d) => c = d,
+                None => return it,
+            }
+            depth += path.len() + 1;
+        }
+        it.visit(c);
+        it
+    }
+    /// Count the nodes of each type, `Node4`, `Node16`, `Node48`, `Node256` and leaf, including keys ending at
+    /// inner nodes as leaves.
+    pub fn node_counts(&self) -> [usize; 5] {
+        let mut counts = [0; 5];
+        let mut stack: Vec<_> = self.root.iter().collect();
+        while let Some(c) = stack.pop() {
+            counts[c.get_usize()] += 1;
+            if let NodeRef::Inner(n) = c.get() {
+                counts[4] += usize::from(n.header().end.is_some());
+                let mut from = 0;
+                while let Some((b, d)) = n.next(from) {
+                    stack.push(d);
+                    from = b as usize + 1;
+                }
+            }
+        }
+        counts
+    }
+}
+
+impl<K: AsRef<[u8]>, V> FromIterator<(K, V)> for Art<V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut t = Self::new();
+        t.extend(iter);
+        t
+    }
+}
+
+impl<K: AsRef<[u8]>, V> Extend<(K, V)> for Art<V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<V: core::fmt::Debug> core::fmt::Debug for Art<V> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, V> IntoIterator for &'a Art<V> {
+    type Item = (&'a [u8], &'a V);
+    type IntoIter = Iter<'a, V>;
+    fn into_iter(self) -> Iter<'a, V> {
+        self.iter()
+    }
+}
+
+/// Iterator over entries of a tree in lexicographic order of keys.
+pub struct Iter<'a, V> {
+    /// Inner nodes on the path, with the next byte to look for a child from.
+    stack: Vec<(&'a dyn Inner<V>, usize)>,
+    leaf: Option<&'a Leaf<V>>,
+}
+
+impl<'a, V> Iter<'a, V> {
+    fn visit(&mut self, c: &'a Child<V>) {
+        match c.get() {
+            NodeRef::Leaf(l) => self.leaf = Some(l),
+            NodeRef::Inner(n) => {
+                self.stack.push((n, 0));
+                self.leaf = n.header().end.as_deref();
+            }
+        }
+    }
+}
+
+impl<'a, V> Iterator for Iter<'a, V> {
+    type Item = (&'a [u8], &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(l) = self.leaf.take() {
+                return Some((&l.key, &l.value));
+            }
+            let (n, from) = self.stack.last_mut()?;
+            match n.next(*from) {
+                Some((b, c)) => {
+                    *from = b as usize + 1;
+                    self.visit(c);
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,369 @@
+//! This module defines `CMap`, a combinatorial map of dimension `D` up to 3, where each dart keeps a link to
+//! its image by each involution it is sewn along, as an `OIndex` whose 2 stolen bits tell the index `i` of
+//! the involution `β_i`, `β_0` being the inverse of `β_1`.
+//!
+//! An `i`-cell is an orbit of darts: faces are orbits of `β_1`, edges of 2-maps are orbits of `β_2`, and
+//! vertices are orbits of compositions `β_j ∘ β_k` with `j < k`.
+//! ```
+//! use ointer::cmap::CMap2;
+//! // Two triangles sharing the edge between vertices 0 and 2.
+//! let mut m = CMap2::new();
+//! let a = m.add_face([0, 1, 2]);
+//! let b = m.add_face([0, 2, 3]);
+//! assert_eq!((m.beta(1, a), m.beta(0, a), m.beta(2, a)), (Some(a + 1), Some(a + 2), None));
+//! m.sew(2, a + 2, b).unwrap();
+//! assert_eq!((m.beta(2, b), *m.data(a + 2), *m.data(b)), (Some(a + 2), 2, 0));
+//! assert_eq!((m.num_cells(0), m.num_cells(1), m.num_cells(2)), (4, 5, 2));
+//! m.unsew(2, b).unwrap();
+//! assert_eq!(m.num_cells(1), 6);
+//! ```
+
+use crate::{
+    mesh::{Mesh, MeshError},
+    oindex::*,
+    ointer::*,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Errors of sewing or unsewing darts of a [`CMap`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CMapError {
+    /// A dart, second, already sewn along the involution, first.
+    NotFree(usize, usize),
+    /// A dart, second, not sewn along the involution, first.
+    Free(usize, usize),
+    /// Orbits of darts to be sewn along the involution which do not match.
+    OrbitMismatch(usize),
+}
+
+impl core::fmt::Display for CMapError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotFree(i, d) => write!(f, "dart {d} not {i}-free"),
+            Self::Free(i, d) => write!(f, "dart {d} {i}-free"),
+            Self::OrbitMismatch(i) => write!(f, "orbits not {i}-sewable"),
+        }
+    }
+}
+
+impl std::error::Error for CMapError {}
+
+/// Link of a dart to another one, with the index of the involution in the stolen bits.
+pub type Link = OIndex<(), 2>;
+
+struct Dart<T> {
+    links: Vec<Link>,
+    data: T,
+}
+
+/// Combinatorial map of dimension `D`, from 1 to 3, with data `T` on darts.
+pub struct CMap<const D: usize, T = ()> {
+    darts: OArena<Dart<T>, 0>,
+}
+
+/// Combinatorial map of dimension 2.
+pub type CMap2<T = ()> = CMap<2, T>;
+/// Combinatorial map of dimension 3.
+pub type CMap3<T = ()> = CMap<3, T>;
+
+impl<const D: usize, T> Default for CMap<D, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const D: usize, T> CMap<D, T> {
+    const VALID: () = assert!(D >= 1 && D <= 3, "Dimension out of 1..=3");
+
+    /// Make an empty map.
+    pub const fn new() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let _ = Self::VALID;
+        Self {
+            darts: OArena::new(),
+        }
+    }
+    fn dart(&self, d: usize) -> &Dart<T> {
+        &self.darts[OIndex::new(d)]
+    }
+    fn dart_mut(&mut self, d: usize) -> &mut Dart<T> {
+        &mut self.darts[OIndex::new(d)]
+    }
+    /// Add a dart free along all involutions, holding `data`, and return its index.
+    pub fn add_dart(&mut self, data: T) -> usize {
+        let d = self.darts.insert(Dart {
+            links: Vec::new(),
+            data,
+        });
+        d.index()
+    }
+    /// Add darts holding `data` in order, linked by `β_1` into a loop, and return the first one.
+    ///
+    /// # Panics
+    /// Panics if `data` is empty.
+    pub fn add_face(&mut self, data: impl IntoIterator<Item = T>) -> usize {
+        let darts: Vec<_> = data.into_iter().map(|x| self.add_dart(x)).collect();
+        assert!(!darts.is_empty(), "Empty face");
+        for (i, &d) in darts.iter().enumerate() {
+            self.pair(1, d, darts[(i + 1) % darts.len()]);
+        }
+        darts[0]
+    }
+    /// Remove dart `d`, unlinking it from its images, and return its data.
+    pub fn remove_dart(&mut self, d: usize) -> T {
+        for i in 0..=D {
+            if let Some(e) = self.beta(i, d) {
+                self.set_beta(Self::inverse(i), e, None);
+            }
+        }
+        self.darts.remove(OIndex::new(d)).unwrap().data
+    }
+    /// Whether dart `d` is in the map.
+    pub fn contains(&self, d: usize) -> bool {
+        self.darts.contains(OIndex::new(d))
+    }
+    /// Get the number of darts.
+    pub fn num_darts(&self) -> usize {
+        self.darts.len()
+    }
+    /// Iterate over the darts.
+    pub fn darts(&self) -> impl Iterator<Item = usize> + '_ {
+        self.darts.iter().map(|(d, _)| d.index())
+    }
+    /// Get the data of dart `d`.
+    pub fn data(&self, d: usize) -> &T {
+        &self.dart(d).data
+    }
+    /// Get the data of dart `d` mutably.
+    pub fn data_mut(&mut self, d: usize) -> &mut T {
+        &mut self.dart_mut(d).data
+    }
+    /// Get the links of dart `d`, one per involution it is sewn along.
+    pub fn links(&self, d: usize) -> &[Link] {
+        &self.dart(d).links
+    }
+    /// Get the image of dart `d` by `β_i`, or `None` if `d` is `i`-free.
+    pub fn beta(&self, i: usize, d: usize) -> Option<usize> {
+        assert!(i <= D, "Involution out of the dimension");
+        self.dart(d)
+            .links
+            .iter()
+            .find(|l| l.get_usize() == i)
+            .map(Link::index)
+    }
+    /// Whether dart `d` is `i`-free.
+    pub fn is_free(&self, i: usize, d: usize) -> bool {
+        self.beta(i, d).is_none()
+    }
+    fn inverse(i: usize) -> usize {
+        match i {
+            0 => 1,
+            1 => 0,
+            _ => i,
+        }
+    }
+    fn set_beta(&mut self, i: usize, d: usize, e: Option<usize>) {
+        let links = &mut self.dart_mut(d).links;
+        links.retain(|l| l.get_usize() != i);
+        if let Some(e) = e {
+            let mut l = Link::new(e);
+            l.set_usize(i);
+            links.push(l);
+        }
+    }
+    /// Link `a` to `b` by `β_i`, and `b` to `a` by its inverse.
+    fn pair(&mut self, i: usize, a: usize, b: usize) {
+        self.set_beta(i, a, Some(b));
+        self.set_beta(Self::inverse(i), b, Some(a));
+    }
+    /// Get the pairs of darts to be linked by `β_i` along with `a` and `b`, walking in parallel the orbits of
+    /// `β_j` for `j` at least 1 and 2 apart from `i`, or `None` if they do not match one to one.
+    fn sewing_pairs(&self, i: usize, a: usize, b: usize) -> Option<Vec<(usize, usize)>> {
+        let mut pairs = vec![(a, b)];
+        let (mut xs, mut ys) = (HashMap::from([(a, b)]), HashMap::from([(b, a)]));
+        let mut k = 0;
+        while let Some(&(x, y)) = pairs.get(k) {
+            k += 1;
+            for j in (1..=D).filter(|&j| j.abs_diff(i) >= 2) {
+                // Sewing reverses orientation, so `β_1` on one side goes along `β_0` on the other.
+                let next = match (i, j) {
+                    (1, _) => (self.beta(j, y), self.beta(j, x)),
+                    (_, 1) => (self.beta(1, x), self.beta(0, y)),
+                    _ => (self.beta(j, x), self.beta(j, y)),
+                };
+                match next {
+                    (Some(x), Some(y)) => match (xs.get(&x), ys.get(&y)) {
+                        (None, None) => {
+                            xs.insert(x, y);
+                            ys.insert(y, x);
+                            pairs.push((x, y));
+                        }
+                        (Some(&z), Some(&w)) if (z, w) == (y, x) => {}
+                        _ => return None,
+                    },
+                    (None, None) => {}
+                    _ => return None,
+                }
+            }
+        }
+        Some(pairs)
+    }
+    /// Sew `a` to `b` by `β_i`, along with the darts of their orbits to be sewn for the map to stay valid.
+    pub fn sew(&mut self, i: usize, a: usize, b: usize) -> Result<(), CMapError> {
+        assert!((1..=D).contains(&i), "Involution out of the dimension");
+        let pairs = self
+            .sewing_pairs(i, a, b)
+            .ok_or(CMapError::OrbitMismatch(i))?;
+        for &(x, y) in &pairs {
+            for (j, d) in [(i, x), (Self::inverse(i), y)] {
+                if !self.is_free(j, d) {
+                    return Err(CMapError::NotFree(j, d));
+                }
+            }
+        }
+        for (x, y) in pairs {
+            self.pair(i, x, y);
+        }
+        Ok(())
+    }
+    /// Unsew `d` from its image by `β_i`, along with the darts of their orbits sewn with them.
+    pub fn unsew(&mut self, i: usize, d: usize) -> Result<(), CMapError> {
+        assert!((1..=D).contains(&i), "Involution out of the dimension");
+        let e = self.beta(i, d).ok_or(CMapError::Free(i, d))?;
+        let pairs = self
+            .sewing_pairs(i, d, e)
+            .ok_or(CMapError::OrbitMismatch(i))?;
+        for (x, y) in pairs {
+            self.set_beta(i, x, None);
+            self.set_beta(Self::inverse(i), y, None);
+        }
+        Ok(())
+    }
+    /// Get the orbit of dart `d` under the involutions `β_i` for `i` in `generators`, `β_1` going along with
+    /// its inverse `β_0`, in breadth first order.
+    pub fn orbit(&self, d: usize, generators: &[usize]) -> Vec<usize> {
+        self.closure(d, |x| {
+            generators
+                .iter()
+                .flat_map(|&i| [Some(i), (i < 2).then(|| Self::inverse(i))])
+                .flatten()
+                .filter_map(|i| self.beta(i, x))
+                .collect()
+        })
+    }
+    fn closure(&self, d: usize, steps: impl Fn(usize) -> Vec<usize>) -> Vec<usize> {
+        let mut orbit = vec![d];
+        let mut seen = HashSet::from([d]);
+        let mut queue = VecDeque::from([d]);
+        while let Some(x) = queue.pop_front() {
+            for y in steps(x) {
+                if seen.insert(y) {
+                    orbit.push(y);
+                    queue.push_back(y);
+                }
+            }
+        }
+        orbit
+    }
+    /// Get the `i`-cell of dart `d`: its vertex for `0`, or its orbit under `β_j` for all `j` but `i` otherwise.
+    pub fn cell(&self, i: usize, d: usize) -> Vec<usize> {
+        assert!(i <= D, "Cell out of the dimension");
+        if i > 0 {
+            let generators: Vec<_> = (1..=D).filter(|&j| j != i).collect();
+            return self.orbit(d, &generators);
+        }
+        self.closure(d, |x| {
+            let mut steps = Vec::new();
+            for k in 2..=D {
+                for j in 1..k {
+                    let back = if j == 1 { 0 } else { j };
+                    let forward = self.beta(k, x).and_then(|y| self.beta(j, y));
+                    let backward = self.beta(back, x).and_then(|y| self.beta(k, y));
+                    steps.extend(forward.into_iter().chain(backward));
+                }
+            }
+            steps
+        })
+    }
+    /// Get the `i`-cells, each starting from its smallest dart.
+    pub fn cells(&self, i: usize) -> Vec<Vec<usize>> {
+        let mut seen = HashSet::new();
+        let mut cells = Vec::new();
+        for d in self.darts() {
+            if !seen.contains(&d) {
+                let c = self.cell(i, d);
+                seen.extend(c.iter().copied());
+                cells.push(c);
+            }
+        }
+        cells
+    }
+    /// Get the number of `i`-cells.
+    pub fn num_cells(&self, i: usize) -> usize {
+        self.cells(i).len()
+    }
+    /// Get the alternating sum of the numbers of cells.
+    pub fn euler_characteristic(&self) -> isize {
+        (0..=D)
+            .map(|i| self.num_cells(i) as isize * if i % 2 == 0 { 1 } else { -1 })
+            .sum()
+    }
+    /// Check that `β_0` is the inverse of `β_1`, that other `β_i` are involutions, and that `β_i ∘ β_j` are
+    /// involutions when `i + 2 <= j`.
+    pub fn is_valid(&self) -> bool {
+        let beta = |i, d| self.beta(i, d);
+        self.darts().all(|d| {
+            (0..=D).all(|i| beta(i, d).is_none_or(|e| beta(Self::inverse(i), e) == Some(d)))
+                && (1..=D).all(|i| {
+                    (i + 2..=D).all(|j| {
+                        let f = |x| beta(j, x).and_then(|y| beta(i, y));
+                        f(d).is_none_or(|e| {
+                            let back = beta(Self::inverse(i), e).and_then(|y| beta(j, y));
+                            back == Some(d)
+                        })
+                    })
+                })
+        })
+    }
+}
+
+impl CMap<2, usize> {
+    /// Make a 2-map with a dart per half-edge of a face of mesh `m`, holding its origin, linked by `β_1` to
+    /// the next one, and by `β_2` to its twin unless on the boundary.
+    pub fn from_mesh<V>(m: &Mesh<V>) -> Self {
+        let mut c = Self::new();
+        let mut darts = HashMap::new();
+        for h in m.half_edges().filter(|h| !h.is_boundary()) {
+            darts.insert(h.id(), c.add_dart(h.origin()));
+        }
+        for h in m.half_edges().filter(|h| !h.is_boundary()) {
+            c.set_beta(1, darts[&h.id()], Some(darts[&h.next().id()]));
+            c.set_beta(0, darts[&h.next().id()], Some(darts[&h.id()]));
+            if let Some(&t) = darts.get(&h.twin().id()) {
+                c.set_beta(2, darts[&h.id()], Some(t));
+            }
+        }
+        c
+    }
+    /// Get the vertices of the face of dart `d`, in order from the origin of `d`, or `None` if it is not closed.
+    pub fn face_vertices(&self, d: usize) -> Option<Vec<usize>> {
+        let mut v = vec![*self.data(d)];
+        let mut x = self.beta(1, d)?;
+        while x != d {
+            v.push(*self.data(x));
+            x = self.beta(1, x)?;
+        }
+        Some(v)
+    }
+    /// Make a half-edge mesh with vertex data `vertices`, of the faces of the map, indexed by the darts holding
+    /// the origins of their half-edges.
+    pub fn to_mesh<V>(&self, vertices: impl IntoIterator<Item = V>) -> Result<Mesh<V>, MeshError> {
+        let faces = self.cells(2);
+        let polygons = faces
+            .iter()
+            .enumerate()
+            .map(|(f, c)| self.face_vertices(c[0]).ok_or(MeshError::InvalidFace(f)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Mesh::from_polygons(vertices, &polygons)
+    }
+}
@@ -0,0 +1,408 @@
+//! This module defines `Heap`, a region allocator handing out blocks at offsets from its base, and
+//! `CompressedOinter`, a 32-bit `Box` like handle into a `Heap`, with high `N` bits stolen.
+//!
+//! A handle stores the offset of its value, shifted right by [`Heap::GRANULE_BITS`], in its low `32 - N` bits, so a
+//! heap of up to `2^(32 - N + Heap::GRANULE_BITS)` bytes is addressable by 4-byte references.
+//! ```
+//! use ointer::compressed::{CompressedOinter, Heap};
+//! let heap = Heap::new(1 << 20);
+//! let mut c = heap.alloc::<_, 4>(String::from("compressed"));
+//! c.set_usize(0xa);
+//! unsafe { heap.get_mut(&mut c) }.push('!');
+//! assert_eq!((unsafe { heap.get(&c) }.as_str(), c.get_usize()), ("compressed!", 0xa));
+//! assert_eq!(core::mem::size_of::<CompressedOinter<String, 4>>(), 4);
+//! assert_eq!(unsafe { heap.free(c) }, "compressed!");
+//! ```
+
+use crate::ointer::*;
+use core::{alloc::Layout, cell::Cell, marker::PhantomData, ptr::NonNull};
+use std::sync::Mutex;
+
+/// Bump offset and free lists of a heap, one per power of two size class, linked by offsets.
+struct Region {
+    next: usize,
+    free: [usize; usize::BITS as usize],
+}
+
+/// Region allocator for [`CompressedOinter`]s, owning a contiguous block of memory.
+///
+/// Blocks are rounded up to a power of two aligned to itself, and freed blocks are kept for reuse.
+/// Values still allocated when the heap is dropped are leaked, without running their destructors.
+pub struct Heap {
+    base: NonNull<u8>,
+    capacity: usize,
+    region: Mutex<Region>,
+}
+
+unsafe impl Send for Heap {}
+unsafe impl Sync for Heap {}
+
+thread_local! {
+    static BASE: Cell<usize> = const { Cell::new(0) };
+}
+
+impl Heap {
+    /// Number of low bits of offsets dropped by handles, as all blocks are aligned to `2^GRANULE_BITS`.
+    pub const GRANULE_BITS: usize = 3;
+    /// Alignment of the base, the largest alignment supported.
+    pub const MAX_ALIGN: usize = 4096;
+
+    /// Reserve a heap of `capacity` bytes.
+    pub fn new(capacity: usize) -> Self {
+        let layout = Self::layout(capacity);
+        let Some(base) = NonNull::new(unsafe { std::alloc::alloc(layout) }) else {
+            std::alloc::handle_alloc_error(layout)
+        };
+        Self {
+            base,
+            capacity: layout.size(),
+            region: Mutex::new(Region {
+                // Offset 0 is never handed out, so that it marks empty free lists.
+                next: 1 << Self::GRANULE_BITS,
+                free: [0; usize::BITS as usize],
+            }),
+        }
+    }
+    fn layout(capacity: usize) -> Layout {
+        Layout::from_size_align(capacity.max(Self::MAX_ALIGN), Self::MAX_ALIGN).unwrap()
+    }
+    fn class<T>() -> usize {
+        let layout = Layout::new::<T>();
+        assert!(
+            layout.align() <= Self::MAX_ALIGN,
+            "Alignment exceeds Heap::MAX_ALIGN"
+        );
+        layout
+            .size()
+            .max(layout.align())
+            .max(1 << Self::GRANULE_BITS)
+            .next_power_of_two()
+    }
+    /// Get the base address.
+    pub fn base(&self) -> NonNull<u8> {
+        self.base
+    }
+    /// Get the capacity in bytes.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    /// Get the number of bytes handed out so far, including freed blocks kept for reuse.
+    pub fn used(&self) -> usize {
+        self.region.lock().unwrap().next
+    }
+    /// Allocate `x` in the heap, with high `N` bits all `0`.
+    ///
+    /// # Aborts
+    /// Aborts through [`std::alloc::handle_alloc_error`] if the heap is full, or if the offset of the block
+    /// does not fit in `32 - N` bits, see [`Heap::try_alloc`].
+    pub fn alloc<T, const N: usize>(&self, x: T) -> CompressedOinter<T, N> {
+        match self.try_alloc(x) {
+            Ok(c) => c,
+            Err(_) => std::alloc::handle_alloc_error(Layout::new::<T>()),
+        }
+    }
+    /// Allocate `x` in the heap, with high `N` bits all `0`, or give it back if the heap is full, or if the
+    /// offset of the block does not fit in `32 - N` bits, which is never the case when the capacity does.
+    pub fn try_alloc<T, const N: usize>(&self, x: T) -> Result<CompressedOinter<T, N>, T> {
+        let class = Self::class::<T>();
+        let index = class.trailing_zeros() as usize;
+        let whole = CompressedOinter::<T, N>::fits(self.capacity - 1);
+        let fits = |p| whole || CompressedOinter::<T, N>::fits(p);
+        let offset = {
+            let mut r = self.region.lock().unwrap();
+            match r.free[index] {
+                0 => {
+                    let p = (r.next + class - 1) & !(class - 1);
+                    if p + class > self.capacity || !fits(p) {
+                        return Err(x);
+                    }
+                    r.next = p + class;
+                    p
+                }
+                p if fits(p) => {
+                    r.free[index] = unsafe { *(self.base.as_ptr().add(p) as *const usize) };
+                    p
+                }
+                _ => return Err(x),
+            }
+        };
+        let c = CompressedOinter::from_offset(offset);
+        unsafe { (self.base.as_ptr().add(offset) as *mut T).write(x) };
+        Ok(c)
+    }
+    /// Move the value of `c` out of the heap and free its block.
+    ///
+    /// # Safety
+    /// `c` must have been allocated by this heap.
+    pub unsafe fn free<T, const N: usize>(&self, c: CompressedOinter<T, N>) -> T {
+        let p = self.ptr(&c);
+        let x = unsafe { p.read() };
+        let index = Self::class::<T>().trailing_zeros() as usize;
+        let mut r = self.region.lock().unwrap();
+        unsafe { *(p as *mut usize) = r.free[index] };
+        r.free[index] = c.offset();
+        x
+    }
+    /// Get a reference to the value of `c`.
+    ///
+    /// # Safety
+    /// `c` must have been allocated by this heap.
+    pub unsafe fn get<'a, T, const N: usize>(&'a self, c: &'a CompressedOinter<T, N>) -> &'a T {
+        &*self.ptr(c)
+    }
+    /// Get a mutable reference to the value of `c`.
+    ///
+    /// # Safety
+    /// `c` must have been allocated by this heap.
+    pub unsafe fn get_mut<'a, T, const N: usize>(
+        &'a self,
+        c: &'a mut CompressedOinter<T, N>,
+    ) -> &'a mut T {
+        &mut *self.ptr(c)
+    }
+    fn ptr<T, const N: usize>(&self, c: &CompressedOinter<T, N>) -> *mut T {
+        let offset = c.offset();
+        assert!(
+            offset != 0 && offset < self.capacity,
+            "Handle out of the heap"
+        );
+        unsafe { self.base.as_ptr().add(offset) as *mut T }
+    }
+    /// Move the value of an `ointer` like `OBox` or `Tagged<Box<T>, N>` into the heap, keeping its high `N` bits.
+    ///
+    /// # Aborts
+    /// Aborts like [`Heap::alloc`] if the heap is full.
+    pub fn compress<T, const N: usize, O: Ointer<N, Pointer = Box<T>>>(
+        &self,
+        mut o: O,
+    ) -> CompressedOinter<T, N> {
+        let u = o.get_usize();
+        o.restore_ptr();
+        let o = core::mem::ManuallyDrop::new(o);
+        let b = unsafe { core::ptr::read(&*o as *const O as *const Box<T>) };
+        let mut c = self.alloc(*b);
+        c.set_usize(u);
+        c
+    }
+    /// Move the value of `c` out of the heap into an `ointer` like `OBox` or `Tagged<Box<T>, N>`, keeping
+    /// its high `N` bits.
+    ///
+    /// # Safety
+    /// `c` must have been allocated by this heap.
+    pub unsafe fn decompress<T, const N: usize, O: Ointer<N, Pointer = Box<T>> + From<Box<T>>>(
+        &self,
+        c: CompressedOinter<T, N>,
+    ) -> O {
+        let u = c.get_usize();
+        let mut o = O::from(Box::new(self.free(c)));
+        o.set_usize(u);
+        o
+    }
+    /// Make this heap the base of the current thread, through which [`CompressedOinter::get_entered`]
+    /// resolves handles, until the returned guard is dropped.
+    pub fn enter(&self) -> HeapGuard<'_> {
+        let prev = BASE.with(|b| b.replace(self.base.as_ptr() as usize));
+        HeapGuard {
+            heap: self,
+            prev,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl Drop for Heap {
+    fn drop(&mut self) {
+        unsafe { std::alloc::dealloc(self.base.as_ptr(), Self::layout(self.capacity)) };
+    }
+}
+
+impl core::fmt::Debug for Heap {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+        (self.base, self.capacity, self.used()).fmt(f)
+    }
+}
+
+/// Guard returned by [`Heap::enter`], restoring the previous base of the current thread on drop, so it
+/// stays on that thread.
+///
+/// Values of handles borrowed through the guard cannot outlive it, unlike through
+/// [`CompressedOinter::get_entered`].
+/// ```compile_fail
+/// use ointer::compressed::Heap;
+/// let heap = Heap::new(1 << 16);
+/// let c = heap.alloc::<_, 0>(1u8);
+/// let guard = heap.enter();
+/// let x = unsafe { guard.get(&c) };
+/// drop(guard);
+/// assert_eq!(*x, 1);
+/// ```
+pub struct HeapGuard<'a> {
+    heap: &'a Heap,
+    prev: usize,
+    _marker: PhantomData<*const ()>,
+}
+
+impl HeapGuard<'_> {
+    /// Get the entered heap.
+    pub fn heap(&self) -> &Heap {
+        self.heap
+    }
+    /// Get a reference to the value of `c`, borrowed no longer than the guard.
+    ///
+    /// # Safety
+    /// `c` must have been allocated by the entered heap.
+    pub unsafe fn get<'b, T, const N: usize>(&'b self, c: &'b CompressedOinter<T, N>) -> &'b T {
+        self.heap.get(c)
+    }
+    /// Get a mutable reference to the value of `c`, borrowed no longer than the guard, which it borrows
+    /// exclusively.
+    ///
+    /// # Safety
+    /// `c` must have been allocated by the entered heap.
+    pub unsafe fn get_mut<'b, T, const N: usize>(
+        &'b mut self,
+        c: &'b mut CompressedOinter<T, N>,
+    ) -> &'b mut T {
+        self.heap.get_mut(c)
+    }
+}
+
+impl Drop for HeapGuard<'_> {
+    fn drop(&mut self) {
+        BASE.with(|b| b.set(self.prev));
+    }
+}
+
+/// 32-bit `Box` like handle into a [`Heap`], with high `N` bits stolen, `N` below `32`, which is
+/// checked at compile time.
+///
+/// The handle owns its value but not its heap, nor does it know which heap that is: it resolves
+/// through [`Heap::get`], or through the heap entered by the current thread and its [`HeapGuard`], all
+/// unsafe, and its value is leaked unless moved out by [`Heap::free`].
+/// ```
+/// use ointer::{compressed::{CompressedOinter, Heap}, OBox};
+/// let heap = Heap::new(1 << 16);
+/// let mut o = OBox::new(vec![1, 2]);
+/// o.flip();
+/// let mut c: CompressedOinter<_, 1> = heap.compress(o);
+/// let guard = heap.enter();
+/// unsafe { c.get_entered_mut() }.push(3);
+/// assert_eq!((unsafe { c.get_entered() }.len(), c.o()), (3, true));
+/// drop(guard);
+/// let o: OBox<_> = unsafe { heap.decompress(c) };
+/// assert_eq!((&o[..], o.o()), (&[1, 2, 3][..], true));
+/// ```
+/// ```compile_fail
+/// use ointer::compressed::Heap;
+/// let _ = Heap::new(1 << 16).alloc::<_, 32>(0);
+/// ```
+#[must_use = "the value is leaked unless moved out by `Heap::free`"]
+#[repr(transparent)]
+pub struct CompressedOinter<T, const N: usize>(u32, PhantomData<T>);
+
+unsafe impl<T: Send, const N: usize> Send for CompressedOinter<T, N> {}
+unsafe impl<T: Sync, const N: usize> Sync for CompressedOinter<T, N> {}
+
+impl<T, const N: usize> CompressedOinter<T, N> {
+    const LOW_BITS: u32 = {
+        assert!(N < 32, "No enough bits in compressed handles");
+        32 - N as u32
+    };
+    const LOW_MASK: u32 = u32::MAX >> N;
+
+    fn fits(offset: usize) -> bool {
+        (offset >> Heap::GRANULE_BITS) >> Self::LOW_BITS == 0
+    }
+    fn from_offset(offset: usize) -> Self {
+        let u = offset >> Heap::GRANULE_BITS;
+        assert!(
+            u >> Self::LOW_BITS == 0,
+            "Offset overflows the compressed handle"
+        );
+        Self(u as u32, PhantomData)
+    }
+    /// Get the offset of the value from the base of its heap.
+    pub fn offset(&self) -> usize {
+        ((self.0 & Self::LOW_MASK) as usize) << Heap::GRANULE_BITS
+    }
+    /// Get high `N` bits as bool.
+    pub fn get_bool(&self) -> bool {
+        self.get_usize() != 0
+    }
+    /// Get high `N` bits as isize.
+    pub fn get_isize(&self) -> isize {
+        if N == 0 {
+            0
+        } else {
+            (self.0 as i32 >> Self::LOW_BITS) as isize
+        }
+    }
+    /// Get high `N` bits as usize.
+    pub fn get_usize(&self) -> usize {
+        (u64::from(self.0) >> Self::LOW_BITS) as usize
+    }
+    /// Set high `N` bits from bool.
+    pub fn set_bool(&mut self, b: bool) {
+        self.set_usize(usize::from(b));
+    }
+    /// Set high `N` bits from isize.
+    pub fn set_isize(&mut self, i: isize) {
+        if N == 0 {
+            assert!(i == 0, "Value out of high bits");
+        } else {
+            let m = 1 << (N - 1);
+            assert!(-m <= i && i < m, "Value out of high bits");
+        }
+        self.set_usize(i as usize & ((1 << N) - 1));
+    }
+    /// Set high `N` bits from usize.
+    pub fn set_usize(&mut self, u: usize) {
+        assert!(u >> N == 0, "Value out of high bits");
+        self.0 = (self.0 & Self::LOW_MASK) | ((u as u64) << Self::LOW_BITS) as u32;
+    }
+}
+
+impl<T, const N: usize> core::fmt::Debug for CompressedOinter<T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+        (self.get_usize(), self.offset()).fmt(f)
+    }
+}
+
+impl<T, const N: usize> CompressedOinter<T, N> {
+    fn entered_ptr(&self) -> *mut T {
+        let base = BASE.with(|b| b.get());
+        assert!(base != 0, "No heap entered by this thread");
+        (base + self.offset()) as *mut T
+    }
+    /// Get a reference to the value through the heap entered by the current thread.
+    ///
+    /// # Safety
+    /// The entered heap must have allocated this handle, and the reference must not outlive its guard.
+    ///
+    /// # Panics
+    /// Panics if no heap is entered by the current thread.
+    pub unsafe fn get_entered(&self) -> &T {
+        &*self.entered_ptr()
+    }
+    /// Get a mutable reference to the value through the heap entered by the current thread.
+    ///
+    /// # Safety
+    /// The entered heap must have allocated this handle, and the reference must not outlive its guard.
+    ///
+    /// # Panics
+    /// Panics if no heap is entered by the current thread.
+    pub unsafe fn get_entered_mut(&mut self) -> &mut T {
+        &mut *self.entered_ptr()
+    }
+}
+
+impl<T> CompressedOinter<T, 1> {
+    /// Get first bit and cast as bool.
+    pub fn o(&self) -> bool {
+        self.get_bool()
+    }
+    /// Flip first bit.
+    pub fn flip(&mut self) {
+        self.set_bool(!self.o());
+    }
+}
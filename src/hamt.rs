@@ -0,0 +1,419 @@
+//! This module defines `HamtMap`, a persistent hash array mapped trie, whose children are enum `ointer`s over
+//! `Rc`s with the kind of node, inner, leaf or collision, in the stolen byte, so that each slot of a node is a
+//! single word, and `ArcHamtMap`, the same over `Arc`s to be `Send` and `Sync`.
+//!
+//! Each level consumes 5 bits of the 64-bit hash of a key, with inner nodes storing only their present
+//! children after a 32-bit bitmap, and keys of equal hashes sharing a collision node.
+//!
+//! Cloning a map is `O(1)`. Modifying a map copies the nodes on the path to the change only if they are shared,
+//! checked as by `Rc::make_mut`, so that batches of changes to an unshared map, such as `extend`, are done in
+//! place, like with transients.
+//! ```
+//! use ointer::hamt::HamtMap;
+//! let mut a: HamtMap<String, usize> = ["x", "y", "z"].iter().map(|s| (s.to_string(), s.len())).collect();
+//! let b = a.clone();
+//! assert_eq!(a.insert("y".into(), 2), Some(1));
+//! assert_eq!(a.remove("x"), Some(1));
+//! assert_eq!((a.get("y"), b.get("y"), a.get("x")), (Some(&2), Some(&1), None));
+//! assert_eq!((a.len(), b.len(), b.iter().map(|(_, v)| v).sum::<usize>()), (2, 3, 3));
+//! ```
+
+use core::{borrow::Borrow, hash::Hash, hash::Hasher};
+use std::{hash::DefaultHasher, rc::Rc, sync::Arc};
+
+/// Bits of hash consumed by each level.
+const BITS: u32 = 5;
+
+fn hash_of<K: Hash + ?Sized>(key: &K) -> u64 {
+    let mut h = DefaultHasher::new();
+    key.hash(&mut h);
+    h.finish()
+}
+
+/// Get the bit of the slot of `hash` in the bitmap of an inner node at `shift`.
+fn slot(hash: u64, shift: u32) -> u32 {
+    1 << ((hash >> shift) & ((1 << BITS) - 1))
+}
+
+/// Get the position in the children of an inner node with `bitmap`, of the child at slot `bit`.
+fn position(bitmap: u32, bit: u32) -> usize {
+    (bitmap & (bit - 1)).count_ones() as usize
+}
+
+macro_rules! define_hamt_map {
+    ($(#[$attr:meta])* $map:ident, $iter:ident, $pointer:ident) => {
+        #[derive(Clone)]
+        struct InnerNode<K, V> {
+            bitmap: u32,
+            children: Vec<Child<K, V>>,
+        }
+
+        #[derive(Clone)]
+        struct LeafNode<K, V> {
+            hash: u64,
+            key: K,
+            value: V,
+        }
+
+        #[derive(Clone)]
+        struct CollisionNode<K, V> {
+            hash: u64,
+            entries: Vec<(K, V)>,
+        }
+
+        crate::define_enum_ointers! {
+            enum Child<K, V>: 8 {
+                Inner($pointer<InnerNode<K, V>>) = 0,
+                Leaf($pointer<LeafNode<K, V>>) = 1,
+                Collision($pointer<CollisionNode<K, V>>) = 2,
+            }
+        }
+
+        enum NodeRef<'a, K, V> {
+            Inner(&'a InnerNode<K, V>),
+            Leaf(&'a LeafNode<K, V>),
+            Collision(&'a CollisionNode<K, V>),
+        }
+
+        enum NodeMut<'a, K, V> {
+            Inner(&'a mut InnerNode<K, V>),
+            Leaf(&'a mut LeafNode<K, V>),
+            Collision(&'a mut CollisionNode<K, V>),
+        }
+
+        impl<K, V> Child<K, V> {
+            fn leaf(hash: u64, key: K, value: V) -> Self {
+                Self::new_leaf($pointer::new(LeafNode { hash, key, value }))
+            }
+            fn get(&self) -> NodeRef<'_, K, V> {
+                self.map_enum_ref(NodeRef::Inner, NodeRef::Leaf, NodeRef::Collision)
+            }
+            /// Get the hash of a leaf or collision node.
+            fn hash(&self) -> u64 {
+                match self.get() {
+                    NodeRef::Inner(_) => unreachable!(),
+                    NodeRef::Leaf(l) => l.hash,
+                    NodeRef::Collision(c) => c.hash,
+                }
+            }
+        }
+
+        impl<K: Clone, V: Clone> Child<K, V> {
+            /// Get the node mutably, copying it first if shared.
+            fn get_mut(&mut self) -> NodeMut<'_, K, V> {
+                self.map_enum_ref_mut(NodeMut::Inner, NodeMut::Leaf, NodeMut::Collision)
+            }
+        }
+
+        /// Make an inner node holding leaf or collision nodes `a` and `b` of different hashes.
+        fn merge<K, V>(a: Child<K, V>, b: Child<K, V>, shift: u32) -> Child<K, V> {
+            let (x, y) = (slot(a.hash(), shift), slot(b.hash(), shift));
+            let (bitmap, children) = match x.cmp(&y) {
+                core::cmp::Ordering::Equal => (x, vec![merge(a, b, shift + BITS)]),
+                core::cmp::Ordering::Less => (x | y, vec![a, b]),
+                core::cmp::Ordering::Greater => (x | y, vec![b, a]),
+            };
+            Child::new_inner($pointer::new(InnerNode { bitmap, children }))
+        }
+
+        fn get<'a, K: Borrow<Q>, V, Q: Eq + ?Sized>(
+            mut child: &'a Child<K, V>,
+            hash: u64,
+            key: &Q,
+        ) -> Option<&'a V> {
+            let mut shift = 0;
+            loop {
+                match child.get() {
+                    NodeRef::Inner(n) => {
+                        let bit = slot(hash, shift);
+                        if n.bitmap & bit == 0 {
+                            return None;
+                        }
+                        child = &n.children[position(n.bitmap, bit)];
+                        shift += BITS;
+                    }
+                    NodeRef::Leaf(l) => {
+                        return (l.hash == hash && l.key.borrow() == key).then_some(&l.value)
+                    }
+                    NodeRef::Collision(c) => {
+                        let mut entries = c.entries.iter().filter(|_| c.hash == hash);
+                        return entries.find(|(k, _)| k.borrow() == key).map(|(_, v)| v);
+                    }
+                }
+            }
+        }
+
+        fn insert<K: Eq + Clone, V: Clone>(
+            child: &mut Child<K, V>,
+            shift: u32,
+            hash: u64,
+            key: K,
+            value: V,
+        ) -> Option<V> {
+            let split = match child.get() {
+                NodeRef::Inner(_) => false,
+                NodeRef::Leaf(l) => l.hash != hash,
+                NodeRef::Collision(c) => c.hash != hash,
+            };
+            if split {
+                *child = merge(child.clone(), Child::leaf(hash, key, value), shift);
+                return None;
+            }
+            match child.get_mut() {
+                NodeMut::Inner(n) => {
+                    let bit = slot(hash, shift);
+                    let i = position(n.bitmap, bit);
+                    if n.bitmap & bit != 0 {
+                        return insert(&mut n.children[i], shift + BITS, hash, key, value);
+                    }
+                    n.bitmap |= bit;
+                    n.children.insert(i, Child::leaf(hash, key, value));
+                    None
+                }
+                NodeMut::Leaf(l) if l.key == key => Some(core::mem::replace(&mut l.value, value)),
+                NodeMut::Leaf(l) => {
+                    let entries = vec![(l.key.clone(), l.value.clone()), (key, value)];
+                    *child = Child::new_collision($pointer::new(CollisionNode { hash, entries }));
+                    None
+                }
+                NodeMut::Collision(c) => match c.entries.iter_mut().find(|(k, _)| *k == key) {
+                    Some((_, v)) => Some(core::mem::replace(v, value)),
+                    None => {
+                        c.entries.push((key, value));
+                        None
+                    }
+                },
+            }
+        }
+
+        /// Remove `key` below `child`, returning its value and whether `child` is left empty.
+        fn remove<K: Borrow<Q> + Clone, V: Clone, Q: Eq + ?Sized>(
+            child: &mut Child<K, V>,
+            shift: u32,
+            hash: u64,
+            key: &Q,
+        ) -> Option<(V, bool)> {
+            match child.get() {
+                NodeRef::Inner(n) if n.bitmap & slot(hash, shift) == 0 => return None,
+                NodeRef::Leaf(l) if l.hash == hash && l.key.borrow() == key => return Some((l.value.clone(), true)),
+                NodeRef::Leaf(_) => return None,
+                NodeRef::Collision(c) if c.hash != hash || !c.entries.iter().any(|(k, _)| k.borrow() == key) => {
+                    return None
+                }
+                _ => {}
+            }
+            let (value, collapse) = match child.get_mut() {
+                NodeMut::Inner(n) => {
+                    let bit = slot(hash, shift);
+                    let i = position(n.bitmap, bit);
+                    let (value, empty) = remove(&mut n.children[i], shift + BITS, hash, key)?;
+                    if empty {
+                        n.bitmap &= !bit;
+                        n.children.remove(i);
+                    }
+                    // A single leaf or collision node below replaces its parent.
+                    match &n.children[..] {
+                        [] => return Some((value, true)),
+                        [c] if !matches!(c.get(), NodeRef::Inner(_)) => (value, Some(c.clone())),
+                        _ => (value, None),
+                    }
+                }
+                NodeMut::Collision(c) => {
+                    let i = c.entries.iter().position(|(k, _)| k.borrow() == key).unwrap();
+                    let (_, value) = c.entries.swap_remove(i);
+                    match &c.entries[..] {
+                        [(k, v)] => (value, Some(Child::leaf(c.hash, k.clone(), v.clone()))),
+                        _ => (value, None),
+                    }
+                }
+                NodeMut::Leaf(_) => unreachable!(),
+            };
+            if let Some(c) = collapse {
+                *child = c;
+            }
+            Some((value, false))
+        }
+
+        $(#[$attr])*
+        pub struct $map<K, V> {
+            root: Option<Child<K, V>>,
+            len: usize,
+        }
+
+        impl<K, V> Clone for $map<K, V> {
+            fn clone(&self) -> Self {
+                Self {
+                    root: self.root.clone(),
+                    len: self.len,
+                }
+            }
+        }
+
+        impl<K, V> Default for $map<K, V> {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl<K, V> $map<K, V> {
+            /// Make an empty map.
+            pub const fn new() -> Self {
+                Self { root: None, len: 0 }
+            }
+            /// Get the number of entries.
+            pub fn len(&self) -> usize {
+                self.len
+            }
+            /// Whether there is no entry.
+            pub fn is_empty(&self) -> bool {
+                self.len == 0
+            }
+            /// Iterate over entries in an unspecified order.
+            pub fn iter(&self) -> $iter<'_, K, V> {
+                $iter {
+                    stack: self.root.iter().map(core::slice::from_ref).map(<[_]>::iter).collect(),
+                    entries: [].iter(),
+                }
+            }
+        }
+
+        impl<K: Hash + Eq, V> $map<K, V> {
+            /// Get the value of `key`.
+            pub fn get<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<&V>
+            where
+                K: Borrow<Q>,
+            {
+                get(self.root.as_ref()?, hash_of(key), key)
+            }
+            /// Whether there is an entry for `key`.
+            pub fn contains_key<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> bool
+            where
+                K: Borrow<Q>,
+            {
+                self.get(key).is_some()
+            }
+        }
+
+        impl<K: Hash + Eq + Clone, V: Clone> $map<K, V> {
+            /// Insert `value` at `key`, returning the value replaced, copying the nodes on the path to it if shared.
+            pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+                let hash = hash_of(&key);
+                let old = match &mut self.root {
+                    Some(root) => insert(root, 0, hash, key, value),
+                    None => {
+                        self.root = Some(Child::leaf(hash, key, value));
+                        None
+                    }
+                };
+                self.len += usize::from(old.is_none());
+                old
+            }
+            /// Remove the entry of `key`, returning its value, copying the nodes on the path to it if shared.
+            pub fn remove<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<V>
+            where
+                K: Borrow<Q>,
+            {
+                let (value, empty) = remove(self.root.as_mut()?, 0, hash_of(key), key)?;
+                if empty {
+                    self.root = None;
+                }
+                self.len -= 1;
+                Some(value)
+            }
+            /// Make a copy of the map with `value` at `key`.
+            pub fn update(&self, key: K, value: V) -> Self {
+                let mut m = self.clone();
+                m.insert(key, value);
+                m
+            }
+            /// Make a copy of the map without the entry of `key`.
+            pub fn without<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Self
+            where
+                K: Borrow<Q>,
+            {
+                let mut m = self.clone();
+                m.remove(key);
+                m
+            }
+        }
+
+        impl<K: Hash + Eq + Clone, V: Clone> Extend<(K, V)> for $map<K, V> {
+            fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+                for (k, v) in iter {
+                    self.insert(k, v);
+                }
+            }
+        }
+
+        impl<K: Hash + Eq + Clone, V: Clone> FromIterator<(K, V)> for $map<K, V> {
+            fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+                let mut m = Self::new();
+                m.extend(iter);
+                m
+            }
+        }
+
+        impl<K: core::fmt::Debug, V: core::fmt::Debug> core::fmt::Debug for $map<K, V> {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+                f.debug_map().entries(self.iter()).finish()
+            }
+        }
+
+        impl<'a, K, V> IntoIterator for &'a $map<K, V> {
+            type Item = (&'a K, &'a V);
+            type IntoIter = $iter<'a, K, V>;
+            fn into_iter(self) -> $iter<'a, K, V> {
+                self.iter()
+            }
+        }
+
+        /// Iterator over entries of a map.
+        pub struct $iter<'a, K, V> {
+            stack: Vec<core::slice::Iter<'a, Child<K, V>>>,
+            entries: core::slice::Iter<'a, (K, V)>,
+        }
+
+        impl<'a, K, V> Iterator for $iter<'a, K, V> {
+            type Item = (&'a K, &'a V);
+            fn next(&mut self) -> Option<Self::Item> {
+                loop {
+                    if let Some((k, v)) = self.entries.next() {
+                        return Some((k, v));
+                    }
+                    let Some(c) = self.stack.last_mut()?.next() else {
+                        self.stack.pop();
+                        continue;
+                    };
+                    match c.get() {
+                        NodeRef::Inner(n) => self.stack.push(n.children.iter()),
+                        NodeRef::Leaf(l) => return Some((&l.key, &l.value)),
+                        NodeRef::Collision(c) => self.entries = c.entries.iter(),
+                    }
+                }
+            }
+        }
+    };
+}
+
+mod rc_map {
+    use super::*;
+    define_hamt_map!(
+        /// Persistent hash map over `Rc`s.
+        HamtMap,
+        Iter,
+        Rc
+    );
+}
+
+mod arc_map {
+    use super::*;
+    define_hamt_map!(
+        /// Persistent hash map over `Arc`s, `Send` and `Sync` when keys and values are.
+        ArcHamtMap,
+        ArcIter,
+        Arc
+    );
+}
+
+pub use arc_map::{ArcHamtMap, ArcIter};
+pub use rc_map::{HamtMap, Iter};
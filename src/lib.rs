@@ -1,11 +1,37 @@
 //! This crate provides a set of traits and macros to enable the creation of custom pointers in Rust, allowing for the storage of extra information in the high bits of a pointer. This extra information can be of various types, and the crate provides utilities for working with these custom pointers efficiently. The crate also offers convenient macros for defining custom `ointer`s and `enum ointers` and managing them.
 
+#[doc(hidden)]
+pub use paste;
+
 mod ointer;
 pub use ointer::*;
 pub mod boxed;
 pub use boxed::*;
+pub mod allocator;
+pub mod art;
+pub mod cmap;
+pub mod compressed;
+pub mod hamt;
+pub mod mesh;
+pub mod meshio;
+pub mod nanbox;
+pub mod ograph;
+pub mod oindex;
+pub mod orient;
+pub mod ovalue;
+pub mod quadedge;
+pub use oindex::*;
+pub mod rbmap;
 pub mod rc;
+pub mod simplex;
 pub mod sync;
+pub mod tagged;
+pub use tagged::*;
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub mod arena;
 
 /// Type alias for `boxed::OBox`
 pub type Ox<T> = OBox<T>;
@@ -25,6 +51,58 @@ mod tests {
     use super::{rc::*, sync::*, *};
     use std::{mem::size_of, pin::Pin, rc::Rc, sync::*};
 
+    // Define a box whose allocation starts with a header word for the stolen bits.
+    #[repr(C)]
+    struct Headed<T>(usize, T);
+    pub struct HeaderBox<T>(Box<Headed<T>>);
+    impl<T> HeaderBox<T> {
+        pub fn new(x: T) -> Self {
+            Self(Box::new(Headed(0, x)))
+        }
+    }
+    impl<T> std::ops::Deref for HeaderBox<T> {
+        type Target = T;
+        fn deref(&self) -> &T {
+            &self.0 .1
+        }
+    }
+    impl<T> std::ops::DerefMut for HeaderBox<T> {
+        fn deref_mut(&mut self) -> &mut T {
+            &mut self.0 .1
+        }
+    }
+    impl<T: Clone> Clone for HeaderBox<T> {
+        fn clone(&self) -> Self {
+            Self::new((**self).clone())
+        }
+    }
+    impl<T: PartialEq> PartialEq for HeaderBox<T> {
+        fn eq(&self, rhs: &Self) -> bool {
+            **self == **rhs
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for HeaderBox<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            (**self).fmt(f)
+        }
+    }
+    unsafe impl<T> StealablePointer for HeaderBox<T> {
+        const FREE_HIGH_BITS: usize = FREE_ADDRESS_BITS;
+        const FREE_LOW_BITS: usize = std::mem::align_of::<Headed<T>>().trailing_zeros() as usize;
+        const TAG_HEADER: bool = true;
+    }
+
+    // Iterate over the pseudorandom words of a linear congruential generator seeded with `seed`.
+    fn lcg(seed: u64) -> impl Iterator<Item = u64> {
+        core::iter::successors(Some(seed), |x| {
+            Some(
+                x.wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407),
+            )
+        })
+        .skip(1)
+    }
+
     // Define a test function.
     #[test]
     fn test() {
@@ -32,13 +110,13 @@ mod tests {
             // Test custom ointers (OBox).
             let mut o = OBox::new(1);
             assert_eq!(*o, 1);
-            assert_eq!(o.get::<bool>(), false);
+            assert!(!o.get::<bool>());
             assert_eq!(*o, 1);
             *o = i32::default();
             assert_eq!(*o, i32::default());
             o.set_bool(true);
             let b = o.get_bool();
-            assert_eq!(b, true);
+            assert!(b);
             o.set_mut(false);
             assert_eq!(o, Pin::into_inner(OBox::pin(Default::default())));
         }
@@ -46,7 +124,7 @@ mod tests {
             // Test custom strong ointers (BArc).
             let mut o = BArc::new(1);
             assert_eq!(*o, 1);
-            assert_eq!(o.get::<bool>(), false);
+            assert!(!o.get::<bool>());
 
             // Define a small enum for testing.
             #[derive(Clone, Copy, PartialEq, Debug)]
@@ -109,6 +187,1552 @@ mod tests {
             assert_eq!(size_of::<Option<MyEnumOinters>>(), size_of::<usize>());
         }
 
+        {
+            // Define enum ointers with unit and inline scalar variants.
+            define_enum_ointers! {
+                enum MyValue: 8 {
+                    Nil = 0,
+                    Int(i32) = 1,
+                    Char(char) = 2,
+                    Float(f32) = 3,
+                    Shared(Rc<i32>) = 4,
+                }
+            }
+            assert_eq!(size_of::<Option<MyValue>>(), size_of::<usize>());
+
+            // Zero payloads with a zero discriminant are still valid.
+            let nil = MyValue::new_nil();
+            assert_eq!(nil.get_usize(), 0);
+            assert!(nil.map_enum(|_| true, |_| false, |_| false, |_| false, |_| false));
+            let int = MyValue::new(1, 0i32);
+            assert_eq!(
+                int.map_enum(|_| None, |i| Some(*i), |_| None, |_| None, |_| None),
+                Some(0)
+            );
+
+            // Inline payloads round trip through the low bits.
+            for i in [i32::MIN, -1, 0, 1, i32::MAX] {
+                let mut v = MyValue::new_int(i);
+                assert_eq!(v.clone().map_enum(|_| 0, |i| *i, |_| 0, |_| 0, |_| 0), i);
+                v.map_enum_mut(|_| (), |i| *i = i.wrapping_add(1), |_| (), |_| (), |_| ());
+                assert_eq!(
+                    v.map_enum(|_| 0, |i| *i, |_| 0, |_| 0, |_| 0),
+                    i.wrapping_add(1)
+                );
+            }
+            let c = MyValue::new_char('\u{10FFFF}');
+            assert_eq!(
+                c.map_enum(|_| ' ', |_| ' ', |c| *c, |_| ' ', |_| ' '),
+                '\u{10FFFF}'
+            );
+            let f = MyValue::new_float(-0.0);
+            assert!(f
+                .map_enum(|_| 0.0, |_| 0.0, |_| 0.0, |f| *f, |_| 0.0)
+                .is_sign_negative());
+
+            // Pointer payloads are still dropped and cloned.
+            let r = Rc::new(7);
+            let mut s = MyValue::new_shared(r.clone());
+            let t = s.clone();
+            assert_eq!(Rc::strong_count(&r), 3);
+            s.set_mut(0, ());
+            drop(t);
+            assert_eq!(Rc::strong_count(&r), 1);
+        }
+
+        {
+            // Derive standard traits for enum ointers variant-wise.
+            define_enum_ointers! {
+                #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+                enum MyKey: 4 {
+                    #[default]
+                    Int(i16) = 3,
+                    Nil = 1,
+                    Name(Arc<String>) = 2,
+                }
+            }
+            let name = |s: &str| MyKey::new_name(Arc::new(s.to_string()));
+            assert_eq!(MyKey::default(), MyKey::new_int(0));
+            assert_eq!(name("a"), name("a"));
+            assert_ne!(name("a"), name("b"));
+            assert_ne!(MyKey::new_int(0), MyKey::new_nil());
+            assert_eq!(format!("{:?}", name("a")), "Name(\"a\")");
+            assert_eq!(format!("{:?}", MyKey::new_nil()), "Nil");
+
+            // Discriminants are compared first, then payloads.
+            let mut keys = vec![MyKey::new_int(-1), name("b"), MyKey::new_nil(), name("a")];
+            keys.sort();
+            assert_eq!(
+                keys,
+                [MyKey::new_nil(), name("a"), name("b"), MyKey::new_int(-1)]
+            );
+            let set: std::collections::HashSet<_> = keys.iter().chain(&keys).cloned().collect();
+            assert_eq!(set.len(), 4);
+
+            // The first form takes a trailing derive list.
+            define_enum_ointers!(
+                MyEnumKeys {
+                    Box<u8> = 1,
+                    i32 = 2
+                },
+                8,
+                derive(Debug, PartialEq, Default)
+            );
+            assert_eq!(MyEnumKeys::new(2, 5), MyEnumKeys::new(2, 5));
+            assert_eq!(MyEnumKeys::default(), MyEnumKeys::new(1, Box::new(0u8)));
+            assert_ne!(MyEnumKeys::new(1, Box::new(5u8)), MyEnumKeys::new(2, 5));
+            assert_eq!(format!("{:?}", MyEnumKeys::new(1, Box::new(5u8))), "V1(5)");
+        }
+
+        {
+            // Borrow the targets of enum ointer payloads for as long as the enum ointers.
+            define_enum_ointers! {
+                enum MyTree {
+                    Leaf(Box<u32>),
+                    Node(Rc<Vec<u32>>),
+                }
+            }
+            fn last(t: &MyTree) -> &u32 {
+                t.map_enum_ref(|l| l, |n| n.last().unwrap())
+            }
+            let mut t = MyTree::new_node(Rc::new(vec![1, 2]));
+            let u = t.clone();
+            let n = t.map_enum_ref_mut(|_| panic!(), |n| n);
+            n.push(3);
+            assert_eq!((last(&t), last(&u)), (&3, &2));
+            let mut l = MyTree::new_leaf(Box::new(5));
+            *l.map_enum_ref_mut(|l| l, |_| panic!()) += 1;
+            assert_eq!((last(&l), l.get_usize()), (&6, MyTree::LEAF));
+
+            // Plain ointers copy shared targets too, keeping their stolen bits.
+            let mut o = ORc::new(vec![1]);
+            o.flip();
+            let p = o.clone();
+            o.make_mut().push(2);
+            assert_eq!((o.o(), &o[..], &p[..]), (true, &[1, 2][..], &[1][..]));
+        }
+
+        {
+            // Define generic enum ointers with the first form.
+            #[derive(Clone)]
+            struct Leaf<K, V>(K, V);
+            struct Inner<K, V>(Vec<(K, V)>);
+            define_enum_ointers!(
+                Node<K, V> {
+                    Box<Leaf<K, V>> = 1,
+                    Arc<Inner<K, V>> = 2
+                },
+                8
+            );
+            let leaf = Node::new_v1(Box::new(Leaf(1, "a")));
+            let inner = Node::new_v2(Arc::new(Inner(vec![(2, "b")])));
+            let key = |n: &Node<i32, &str>| n.map_enum(|l| l.0, |i| i.0[0].0);
+            assert_eq!(key(&leaf), 1);
+            assert_eq!(key(&inner.clone()), 2);
+            assert_eq!(size_of::<Option<Node<u8, u8>>>(), size_of::<usize>());
+
+            // Define enum ointers borrowing non-'static data with the enum form.
+            define_enum_ointers! {
+                #[derive(Debug, PartialEq)]
+                enum MyRef<'a, T>: 2 where T: PartialEq + core::fmt::Debug {
+                    Shared(&'a T) = 1,
+                    Unique(&'a mut T) = 2,
+                    Missing = 3,
+                }
+            }
+            let (x, mut y) = (String::from("x"), String::from("y"));
+            assert_eq!(MyRef::new_shared(&x), MyRef::new_shared(&String::from("x")));
+            let mut r = MyRef::new_unique(&mut y);
+            r.map_enum_mut(|_| (), |s| s.push('z'), |_| ());
+            assert_eq!(format!("{:?}", r), "Unique(\"yz\")");
+            drop(r);
+            assert_eq!(y, "yz");
+            assert_ne!(MyRef::<String>::new_missing(), MyRef::new_shared(&x));
+        }
+
+        {
+            // Number discriminants and bits automatically.
+            define_enum_ointers! {
+                enum MyAuto {
+                    First,
+                    Second(Box<u8>),
+                    Third(u32) = 9,
+                    Fourth(Rc<u8>),
+                }
+            }
+            assert_eq!(
+                [MyAuto::FIRST, MyAuto::SECOND, MyAuto::THIRD, MyAuto::FOURTH],
+                [0, 1, 9, 10]
+            );
+            assert_eq!(MyAuto::BITS, 4);
+            let mut e = MyAuto::new(MyAuto::FOURTH, Rc::new(4u8));
+            assert_eq!(e.get_usize(), MyAuto::FOURTH);
+            assert_eq!(e.map_enum(|_| 0, |_| 0, |_| 0, |r| **r), 4);
+            e.set_mut(MyAuto::THIRD, u32::MAX);
+            assert_eq!(e.map_enum(|_| 0, |_| 0, |u| *u, |_| 0), u32::MAX);
+            assert_eq!(MyAuto::new_first().get_usize(), 0);
+        }
+
+        {
+            // Pair strong enum ointers with weak ones.
+            define_enum_ointers! {
+                #[weak(MyWeakChild)]
+                #[derive(Debug, PartialEq)]
+                enum MyChild<T> {
+                    Empty,
+                    Local(Rc<T>) = 2,
+                    Shared(Arc<T>),
+                }
+            }
+            let (a, b) = (Rc::new(1u8), Arc::new(2u8));
+            let (ca, cb) = (
+                MyChild::new_local(a.clone()),
+                MyChild::new_shared(b.clone()),
+            );
+            let (wa, wb) = (ca.downgrade(), cb.downgrade());
+            assert_eq!(MyWeakChild::<u8>::BITS, MyChild::<u8>::BITS);
+            assert_eq!(wa.get_usize(), MyChild::<u8>::LOCAL);
+            assert_eq!((Rc::weak_count(&a), Arc::weak_count(&b)), (1, 1));
+            assert_eq!(wa.upgrade(), Some(ca.clone()));
+            assert_eq!(Rc::strong_count(&a), 2);
+            drop((ca, a));
+            assert!(wa.upgrade().is_none());
+            let wb2 = wb.clone();
+            drop(wb);
+            assert_eq!(wb2.upgrade(), Some(cb));
+            drop(wb2);
+            assert_eq!(Arc::weak_count(&b), 0);
+            let e = MyChild::<u8>::new_empty().downgrade().upgrade().unwrap();
+            assert_eq!(e, MyChild::new_empty());
+        }
+
+        {
+            // Nest ointers with non-overlapping stolen bits.
+            define_enum_ointers! {
+                enum MyNested: 8 {
+                    Boxed(OBox<u8, 8>) = 1,
+                    Shared(sync::OArc<u8, 8>) = 255,
+                }
+            }
+            let mut o = OBox::<u8, 8>::new_with_layout(1);
+            o.flip();
+            assert!(o.o());
+            assert_eq!(o.get_ptr_as_usize() & (1 << (usize::BITS - 9)), 0);
+            let mut e = MyNested::new_boxed(o);
+            assert_eq!(e.get_usize(), MyNested::BOXED);
+            assert!(e.map_enum(|o| o.o(), |_| false));
+            e.map_enum_mut(|o| o.flip(), |_| ());
+            assert_eq!(e.map_enum(|o| (o.o(), **o), |_| (true, 0)), (false, 1));
+            let a = Arc::new(2u8);
+            let mut s = sync::OArc::<u8, 8>::from(a.clone());
+            s.set_bool(true);
+            e = MyNested::new_shared(s);
+            assert_eq!(e.get_usize(), 255);
+            assert_eq!(e.map_enum(|_| (false, 0), |o| (o.o(), **o)), (true, 2));
+            drop(e);
+            assert_eq!(Arc::strong_count(&a), 1);
+
+            // Keep a version counter above an orientable Arc.
+            #[repr(transparent)]
+            struct MyVersioned(sync::OArc<u8, 15>);
+            unsafe impl Ointer<15> for MyVersioned {
+                type Pointer = sync::OArc<u8, 15>;
+            }
+            impl Drop for MyVersioned {
+                fn drop(&mut self) {
+                    self.set_usize(0);
+                }
+            }
+            const _: () = assert!(ointers_disjoint::<15, 1, MyVersioned, sync::OArc<u8, 15>>());
+            let mut o = sync::OArc::<u8, 15>::from(a.clone());
+            o.flip();
+            let mut v = MyVersioned(o);
+            v.set_usize(0x7fff);
+            v.map_mut(|c: &mut u16, p| {
+                assert!(p.o());
+                assert_eq!(**p, 2);
+                *c = (*c + 1) & 0x7fff;
+                p.flip();
+            });
+            assert_eq!(v.get_usize(), 0);
+            v.set_isize(-1);
+            assert_eq!(v.get_isize(), -1);
+            assert!(!v.0.o());
+            assert_eq!(*v.0, 2);
+            drop(v);
+            assert_eq!(Arc::strong_count(&a), 1);
+        }
+
+        {
+            // Wrap any stealable pointer with Tagged.
+            let mut b = Tagged::<_, 3>::new(Box::new(7u64));
+            b.set_usize(5);
+            *b += 1;
+            assert_eq!((*b, b.get_usize()), (8, 5));
+            assert_eq!(b.clone(), b);
+            assert_eq!(size_of::<Option<Tagged<Box<u64>, 3>>>(), size_of::<usize>());
+            let x = 1u8;
+            let mut r = Tagged::<&u8, 1>::from(&x);
+            r.flip();
+            assert!(r.o() && !r.clone_and_flip().o());
+            assert_eq!(*r, 1);
+            let w = Tagged::<_, 8>::new(Rc::downgrade(&Rc::new(0)));
+            assert!(w.map(|_: usize, p| p.upgrade()).is_none());
+            assert_eq!(*Tagged::<Rc<u8>, 8>::default(), 0);
+
+            // Wrap a custom handle.
+            #[derive(Clone, PartialEq, Debug)]
+            struct MyHandle(core::ptr::NonNull<u32>);
+            unsafe impl StealablePointer for MyHandle {
+                const FREE_HIGH_BITS: usize = FREE_ADDRESS_BITS;
+                const FREE_LOW_BITS: usize = 2;
+            }
+            let mut y = 9u32;
+            let mut h = Tagged::<_, 16>::new(MyHandle(core::ptr::NonNull::from(&mut y)));
+            h.set_usize(0xabcd);
+            assert_eq!(
+                h.map(|u: usize, p| (u, unsafe { *p.0.as_ptr() })),
+                (0xabcd, 9)
+            );
+            assert_eq!(h.into_inner().0.as_ptr() as *const u32, &y as *const u32);
+        }
+
+        {
+            // Restore high-half addresses by sign extension.
+            #[derive(Clone, Copy, PartialEq, Debug)]
+            struct MyAddr(core::num::NonZeroUsize);
+            unsafe impl StealablePointer for MyAddr {
+                const FREE_HIGH_BITS: usize = FREE_ADDRESS_BITS;
+                const FREE_LOW_BITS: usize = 12;
+            }
+            let high = MyAddr(core::num::NonZeroUsize::new(!0usize << 40).unwrap());
+            let mut t = Tagged::<_, 8, true>::new(high);
+            assert_eq!(t.get_usize(), 0);
+            t.set_usize(0x5a);
+            assert_eq!(t.get_isize(), 0x5a);
+            assert_eq!(t.map(|u: usize, p| (u, *p)), (0x5a, high));
+            assert_eq!(t.get_ptr_as_usize(), !0usize << 40);
+            t.set_isize(-1);
+            assert_eq!(t.get_usize(), 0xff);
+            assert_eq!(t.into_inner(), high);
+            let low = MyAddr(core::num::NonZeroUsize::new(0x1000).unwrap());
+            assert_eq!(Tagged::<_, 16, true>::new(low).into_inner(), low);
+            let mut o = OBox::<u8, 0, true>::new_with_layout(3);
+            o.flip();
+            *o += 1;
+            assert!(o.o());
+            assert_eq!(*o, 4);
+            assert_eq!(*OBox::<u8, 0, true>::pin_with_layout(5), 5);
+        }
+
+        {
+            // Run the same checks against every tag strategy.
+            macro_rules! test_strategy {
+                ($strategy:expr, $pointer:ident) => {{
+                    #[allow(dead_code, unused_imports)]
+                    mod my {
+                        use super::HeaderBox;
+                        use crate::ointer::*;
+                        define_ointer_strong!(MyBox, $pointer, 1, $strategy);
+                    }
+                    use my::*;
+                    assert_eq!(<MyBox<u64> as Ointer<1>>::STRATEGY, $strategy);
+                    assert_eq!(size_of::<Option<MyBox<u64>>>(), size_of::<usize>());
+                    let mut b = Pin::into_inner(MyBox::pin(5u64));
+                    assert!(!b.o());
+                    b.flip();
+                    *b += 1;
+                    assert_eq!((*b, b.get_usize(), b.get_isize()), (6, 1, -1));
+                    assert_eq!(b.clone(), b);
+                    b.map_mut(|o: &mut bool, p| {
+                        *o = false;
+                        *p = $pointer::new(8);
+                    });
+                    assert_eq!((*b, b.o()), (8, false));
+                }};
+                ($strategy:expr, $pointer:ident, $shared:ident, $weak:ident) => {{
+                    test_strategy!($strategy, $pointer);
+                    #[allow(dead_code)]
+                    mod my {
+                        use crate::ointer::*;
+                        use std::rc::{Rc, Weak};
+                        define_shared_ointer!(MyRc, $shared, MyWeak, $weak, 1, $strategy);
+                    }
+                    use my::*;
+                    let r = MyRc::new(7u64);
+                    let c = r.clone_and_flip();
+                    assert_eq!((r.o(), c.o()), (false, true));
+                    assert_eq!((*r, *c, r.strong_count()), (7, 7, 2));
+                    let w = c.downgrade();
+                    assert_eq!((w.get_bool(), r.weak_count()), (true, 1));
+                    assert_eq!(w.upgrade().unwrap(), c);
+                    drop((r, c));
+                    assert!(w.upgrade().is_none());
+                }};
+            }
+            test_strategy!(TagStrategy::HighBits, Box, Rc, Weak);
+            test_strategy!(TagStrategy::LowBits, Box, Rc, Weak);
+            test_strategy!(TagStrategy::Header, HeaderBox);
+            assert_eq!(
+                <OBox<u8> as Ointer<1>>::STRATEGY,
+                TagStrategy::for_target::<Box<u8>>(0, 1)
+            );
+            #[repr(align(0x20000))]
+            struct Wide;
+            assert_eq!(
+                TagStrategy::for_target::<&Wide>(FREE_ADDRESS_BITS, 1),
+                TagStrategy::LowBits
+            );
+            assert_eq!(
+                TagStrategy::for_target::<HeaderBox<u64>>(FREE_ADDRESS_BITS, 8),
+                TagStrategy::Header
+            );
+        }
+
+        #[cfg(all(
+            target_os = "linux",
+            any(target_arch = "x86_64", target_arch = "aarch64")
+        ))]
+        {
+            // Steal more bits from pointers allocated at low addresses.
+            use arena::{ArenaBox, LowArena};
+            assert!(LowArena::is_available());
+            let mut a = ArenaBox::<_, 32>::new(vec![1u8]);
+            a.set_usize(0xdead_beef);
+            a.push(2);
+            assert_eq!((a.get_usize(), &a[..]), (0xdead_beef, &[1, 2][..]));
+            assert!(a.get_ptr_as_usize() < 1 << LowArena::ADDRESS_BITS);
+            let b = a.clone();
+            assert_eq!(b, a);
+            assert_ne!(b.get_ptr_as_usize(), a.get_ptr_as_usize());
+            let p = b.get_ptr_as_usize();
+            drop(b);
+            let mut c = ArenaBox::<_, 16>::new(vec![0u8; 3]);
+            assert_eq!(c.get_ptr_as_usize(), p);
+            c.set_isize(-2);
+            assert_eq!((c.get_isize(), c.len()), (-2, 3));
+            assert_eq!(a.into_inner(), [1, 2]);
+            let v: Vec<_> = (0..1000u64).map(ArenaBox::<_, 24>::new).collect();
+            assert!(v
+                .iter()
+                .all(|o| o.get_ptr_as_usize() >> LowArena::ADDRESS_BITS == 0));
+            assert_eq!(v.iter().map(|o| **o).sum::<u64>(), 499500);
+        }
+
+        {
+            // Allocate ointers by custom allocators.
+            use allocator::{Allocator, ArcIn, Global};
+            use core::{alloc::Layout, cell::Cell, ptr::NonNull};
+            #[derive(Default)]
+            struct MyCounter(Cell<usize>, Cell<usize>);
+            unsafe impl Allocator for MyCounter {
+                fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+                    self.0.set(self.0.get() + 1);
+                    Global.allocate(layout)
+                }
+                unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                    self.1.set(self.1.get() + 1);
+                    Global.deallocate(ptr, layout)
+                }
+            }
+            let counter = MyCounter::default();
+            let mut b = allocator::OBoxIn::new_in(vec![1], &counter);
+            b.flip();
+            b.push(2);
+            let c = b.clone_and_flip();
+            assert!(b.o() && !c.o());
+            assert_eq!((&b[..], &c[..]), (&[1, 2][..], &[1, 2][..]));
+            assert!(core::ptr::eq(*c.allocator(), &counter));
+            let r = Pin::into_inner(allocator::BRcIn::pin_in(3, &counter));
+            let mut s = r.clone();
+            s.set_usize(0x7f);
+            assert_eq!(
+                (*s, s.get_usize(), r.get_usize(), r.strong_count()),
+                (3, 0x7f, 0, 2)
+            );
+            assert_eq!(counter.0.get(), 3);
+            drop((b, c, r));
+            assert_eq!(counter.1.get(), 2);
+            drop(s);
+            assert_eq!(counter.1.get(), 3);
+            assert_eq!(
+                size_of::<allocator::OBoxIn<u8, &MyCounter>>(),
+                size_of::<usize>()
+            );
+            let a: allocator::OArcIn<_> = ArcIn::new(5).into();
+            let t = a.clone_and_flip();
+            let w = t.downgrade();
+            let h = std::thread::spawn(move || (*t, t.o(), t.strong_count()));
+            assert_eq!(h.join().unwrap(), (5, true, 2));
+            assert_eq!((a.strong_count(), a.weak_count(), w.o()), (1, 1, true));
+            assert_eq!(w.upgrade().unwrap(), a.clone_and_flip());
+            assert!(a < a.clone_and_flip());
+            drop(a);
+            assert!(w.upgrade().is_none());
+            let r = allocator::BRcIn::new_in(6, &counter);
+            let w = r.downgrade();
+            drop(r);
+            assert_eq!((w.upgrade(), counter.1.get()), (None, 3));
+            drop(w);
+            assert_eq!(counter.1.get(), 4);
+            assert_eq!(<allocator::BoxIn<u8> as StealablePointer>::FREE_LOW_BITS, 0);
+            assert_eq!(
+                <allocator::RcIn<u8> as StealablePointer>::FREE_LOW_BITS,
+                align_of::<usize>().trailing_zeros() as usize
+            );
+        }
+
+        {
+            // Test compressed handles relative to a heap base.
+            use compressed::{CompressedOinter, Heap};
+            let heap = Heap::new(1 << 16);
+            let mut a = heap.alloc::<_, 3>(vec![1u64]);
+            a.set_isize(-2);
+            let b = heap.compress(Tagged::<_, 3>::new(Box::new(vec![2u64, 3])));
+            assert_eq!((a.get_isize(), a.get_usize(), b.get_usize()), (-2, 6, 0));
+            {
+                let mut guard = heap.enter();
+                unsafe { a.get_entered_mut() }.push(4);
+                assert_eq!(
+                    unsafe { (&a.get_entered()[..], &b.get_entered()[..]) },
+                    (&[1, 4][..], &[2, 3][..])
+                );
+                unsafe { guard.get_mut(&mut a) }.push(5);
+                assert_eq!(unsafe { guard.get(&a) }.last(), Some(&5));
+            }
+            let offset = a.offset();
+            assert_eq!(unsafe { heap.free(a) }, vec![1, 4, 5]);
+            let c = heap.alloc::<_, 3>(vec![5u64]);
+            assert_eq!(c.offset(), offset);
+            let t: Tagged<Box<Vec<u64>>, 3> = unsafe { heap.decompress(b) };
+            assert_eq!(**t, vec![2, 3]);
+            assert_eq!(unsafe { heap.free(c) }, vec![5]);
+            assert_eq!(size_of::<CompressedOinter<u8, 31>>(), 4);
+            let small = Heap::new(0);
+            let mut n = 0;
+            while small.try_alloc::<_, 0>([0u8; 64]).is_ok() {
+                n += 1;
+            }
+            assert_eq!(n, (Heap::MAX_ALIGN >> 6) - 1);
+            // Blocks past the offsets addressable by `32 - N` bits are not handed out.
+            let heap = Heap::new(1 << 16);
+            let x = heap.alloc::<_, 31>(1u8);
+            assert_eq!(heap.try_alloc::<_, 31>(2u8).map(|_| ()), Err(2));
+            let y = heap.alloc::<_, 0>(3u8);
+            assert_eq!((x.offset(), y.offset()), (8, 16));
+            assert_eq!(unsafe { heap.free(y) }, 3);
+            assert_eq!(heap.try_alloc::<_, 31>(4u8).map(|_| ()), Err(4));
+            assert_eq!(unsafe { heap.free(x) }, 1);
+            let z = heap.alloc::<_, 31>(5u8);
+            assert_eq!((z.offset(), unsafe { heap.free(z) }), (8, 5));
+        }
+
+        {
+            // Test tagged indexes and generations of arenas.
+            let mut i = OIndex::<(), 1>::new(5);
+            i.flip();
+            let j = i.clone_and_flip();
+            assert!(i.o() && !j.o());
+            assert_eq!(
+                (i.index(), j.index(), i.map(|b: bool, p| (b, *p))),
+                (5, 5, (true, 5))
+            );
+            let mut arena = OArena::<String, 2>::new();
+            let a = arena.insert("a".to_string());
+            let b = arena.insert("b".to_string());
+            arena[b].push('!');
+            let mut stale = a;
+            for n in 1..4 {
+                assert_eq!(
+                    arena.remove(stale).as_deref(),
+                    Some(if n == 1 { "a" } else { "c" })
+                );
+                stale = arena.insert("c".to_string());
+                assert_eq!((stale.index(), stale.get_usize()), (a.index(), n));
+                assert!(!arena.contains(a));
+            }
+            arena.remove(stale);
+            // The generation wraps around after 2^N removals.
+            let c = arena.insert("d".to_string());
+            assert_eq!((c, arena.get(a).map(|s| &s[..])), (a, Some("d")));
+            assert_eq!(arena.len(), 2);
+            assert_eq!(
+                arena
+                    .iter()
+                    .map(|(i, s)| (i.get_usize(), &s[..]))
+                    .collect::<Vec<_>>(),
+                vec![(0, "d"), (0, "b!")]
+            );
+            assert_eq!(arena.get(OIndex::<String, 2>::new(2)), None);
+            assert_eq!(format!("{arena:?}"), r#"{(0, 0): "d", (0, 1): "b!"}"#);
+            // Without generation bits, indexes are not checked.
+            let mut arena = OArena::<String, 0>::new();
+            let e = arena.insert("e".to_string());
+            arena.remove(e);
+            let f = arena.insert("f".to_string());
+            assert_eq!((e, arena.get(e).map(|s| &s[..])), (f, Some("f")));
+            assert_eq!(format!("{arena:?}"), r#"{(0, 0): "f"}"#);
+        }
+
+        {
+            // Test NaN boxes round-tripping every variant.
+            use nanbox::NanBox;
+            type V = NanBox<String>;
+            let floats = [
+                0.0,
+                -0.0,
+                1.5,
+                -2.25,
+                f64::MIN_POSITIVE,
+                f64::from_bits(1),
+                f64::MAX,
+                f64::MIN,
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+                f64::EPSILON,
+            ];
+            for x in floats {
+                let v = V::new_float(x);
+                assert_eq!((v.get_usize(), v.to_bits()), (0, x.to_bits()));
+                assert_eq!(v.as_float().map(f64::to_bits), Some(x.to_bits()));
+            }
+            for x in [f64::NAN, -f64::NAN, f64::from_bits(0xfff8_0000_0000_0001)] {
+                let v = V::new_float(x);
+                assert!(v.is_float() && v.as_float().unwrap().is_nan());
+            }
+            let f = |v: &V| v.map_enum(|_| 0, |_| 1, |_| 2, |_| 3, |_| 4);
+            assert_eq!(f(&V::new_nil()), V::NIL);
+            for b in [false, true] {
+                let v = V::new_bool(b);
+                assert_eq!(
+                    (f(&v), v.map_enum(|_| !b, |_| !b, |b| *b, |_| !b, |_| !b)),
+                    (V::BOOL, b)
+                );
+            }
+            for i in [i32::MIN, -1, 0, 1, i32::MAX] {
+                let mut v = V::new_int(i);
+                assert_eq!(v.map_enum(|_| 0, |_| 0, |_| 0, |i| *i, |_| 0), i);
+                v.map_enum_mut(|_| (), |_| (), |_| (), |i| *i = i.wrapping_neg(), |_| ());
+                assert_eq!(
+                    v.map_enum(|_| 0, |_| 0, |_| 0, |i| *i, |_| 0),
+                    i.wrapping_neg()
+                );
+            }
+            let mut o = rc::ORc::new("heap".to_string());
+            o.flip();
+            let r = o.clone();
+            let mut v = V::new_object(o);
+            let mut w = v.clone();
+            assert_eq!((f(&w), r.strong_count()), (V::OBJECT, 3));
+            w.map_enum_mut(|_| (), |_| (), |_| (), |_| (), |o| o.flip());
+            let g = |v: &V| {
+                v.map_enum(
+                    |_| None,
+                    |_| None,
+                    |_| None,
+                    |_| None,
+                    |o| Some((o.o(), o.len())),
+                )
+            };
+            assert_eq!((g(&v), g(&w)), (Some((true, 4)), Some((false, 4))));
+            v = V::new_float(1.0);
+            assert_eq!(r.strong_count(), 2);
+            drop(w);
+            assert_eq!((r.strong_count(), v.as_float()), (1, Some(1.0)));
+            assert_eq!(format!("{:?}", V::new_int(-3)), "Int(-3)");
+            assert_eq!(size_of::<V>(), 8);
+            // Drop payloads out of the NaN space rather than leak them, and never leave a stale word.
+            use std::sync::atomic::{AtomicUsize, Ordering};
+            static DROPS: AtomicUsize = AtomicUsize::new(0);
+            #[derive(Clone, Debug)]
+            struct Far(usize);
+            impl Drop for Far {
+                fn drop(&mut self) {
+                    DROPS.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            unsafe impl EnumOinterPayload for Far {
+                const INLINE_BITS: Option<usize> = None;
+                fn into_word(self) -> usize {
+                    core::mem::ManuallyDrop::new(self).0
+                }
+                unsafe fn from_word(u: usize) -> Self {
+                    Far(u)
+                }
+            }
+            define_nan_box! {
+                enum Boxed {
+                    Far(Far),
+                }
+            }
+            let catch = |f: &mut dyn FnMut()| {
+                std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).is_err()
+            };
+            assert!(catch(&mut || drop(Boxed::new_far(Far(1 << 50)))));
+            assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+            let mut b = Boxed::new_far(Far(8));
+            assert!(catch(&mut || b.map_enum_mut(|_| (), |p| *p = Far(1 << 50))));
+            assert!(b.is_float() && DROPS.load(Ordering::Relaxed) == 3);
+            let mut b = Boxed::new_far(Far(8));
+            assert!(catch(&mut || b.map_enum_mut(
+                |_| (),
+                |p| {
+                    *p = Far(16);
+                    panic!()
+                }
+            )));
+            assert_eq!(b.map_enum(|_| 0, |p| p.0), 16);
+            drop(b);
+            assert_eq!(DROPS.load(Ordering::Relaxed), 5);
+        }
+
+        {
+            // Test fixnums promoting to boxed big integers and back.
+            use ovalue::{OValue, OValueRef, Object};
+            type V = OValue<String>;
+            let (min, max) = (V::MIN_FIXNUM, V::MAX_FIXNUM);
+            for i in [min, -1, 0, 1, max] {
+                let v = V::new_int(i);
+                assert_eq!((v.as_fixnum(), v.view()), (Some(i), OValueRef::Int(i)));
+            }
+            let big = V::new_int(max).checked_add(&V::new_int(1)).unwrap();
+            assert_eq!(big.as_int(), Some(max as i128 + 1));
+            assert!(!big.is_fixnum());
+            let small = V::new_int(min).checked_sub(&V::new_int(1)).unwrap();
+            assert_eq!(
+                small.view(),
+                OValueRef::Object(&Object::Big(min as i128 - 1))
+            );
+            assert_eq!(big.checked_add(&small), Some(V::new_int(-1)));
+            assert!(big.checked_add(&small).unwrap().is_fixnum());
+            let square = big.checked_mul(&big).unwrap();
+            assert_eq!(square.as_int(), Some((max as i128 + 1) * (max as i128 + 1)));
+            assert_eq!(square.checked_mul(&square), None);
+            assert_eq!(
+                V::new_int(min).checked_neg().unwrap().as_int(),
+                Some(-(min as i128))
+            );
+            let mut v = V::new_int(max);
+            v.map_enum_mut(|i| *i += 1, |_| ());
+            assert_eq!((v.is_fixnum(), v.as_int()), (false, Some(max as i128 + 1)));
+            v.map_enum_mut(|_| (), |p| *p = Rc::new(Object::Big(5)));
+            assert_eq!((v.view(), v.clone()), (OValueRef::Int(5), V::new_int(5)));
+            let five = V::from_rc(Rc::new(Object::Big(5)));
+            assert_eq!((five.is_fixnum(), five), (true, V::new_int(5)));
+            let o = V::new_object("o".to_string());
+            let p = o.clone();
+            assert_eq!(p.map_enum(|_| 0, Rc::strong_count), 2);
+            assert_eq!((o.as_int(), o.checked_add(&v)), (None, None));
+            let describe = |v: &V| match v.view() {
+                OValueRef::Int(i) => format!("int {i}"),
+                OValueRef::Object(Object::Big(i)) => format!("big {i}"),
+                OValueRef::Object(Object::Custom(s)) => format!("object {s}"),
+            };
+            assert_eq!(describe(&p), "object o");
+            assert_eq!(describe(&V::from(7)), "int 7");
+            drop(o);
+            assert_eq!(p.map_enum(|_| 0, Rc::strong_count), 1);
+            assert_eq!(size_of::<Option<V>>(), size_of::<usize>());
+        }
+
+        {
+            // Test half-edge meshes with twins sharing an edge record.
+            use mesh::{Mesh, MeshError};
+            let octahedron = [
+                [0, 1, 2],
+                [0, 2, 3],
+                [0, 3, 4],
+                [0, 4, 1],
+                [5, 2, 1],
+                [5, 3, 2],
+                [5, 4, 3],
+                [5, 1, 4],
+            ];
+            let mut m = Mesh::from_polygons(0..6, &octahedron).unwrap();
+            assert_eq!((m.num_edges(), m.euler_characteristic()), (12, 2));
+            let h = m.find_half_edge(0, 1).unwrap();
+            let t = h.twin();
+            assert!(h != t && t.twin() == h && h.edge() == t.edge());
+            assert_eq!(
+                (t.origin(), t.dest(), t.face(), h.face()),
+                (1, 0, Some(3), Some(0))
+            );
+            assert_eq!(m.face_vertices(5).collect::<Vec<_>>(), [5, 3, 2]);
+            let mut ring = m.vertex_neighbors(0).collect::<Vec<_>>();
+            ring.sort();
+            assert_eq!(ring, [1, 2, 3, 4]);
+            assert_eq!(m.vertex_faces(5).count(), 4);
+            assert!(m
+                .half_edges()
+                .all(|h| h.next().prev() == h && h.fan().count() == 4));
+            // Split, flip back and forth, then collapse.
+            let v = m.split_edge(&h, 6);
+            assert_eq!((h.dest(), h.next().dest(), *m.vertex(v)), (v, 1, 6));
+            assert_eq!((m.num_vertices(), m.num_edges(), m.num_faces()), (7, 13, 8));
+            m.check().unwrap();
+            let e = m.find_half_edge(1, 2).unwrap();
+            assert!(m.flip_edge(&e).is_err());
+            let e = m.find_half_edge(0, 3).unwrap();
+            m.flip_edge(&e).unwrap();
+            assert_eq!((e.origin(), e.dest()), (2, 4));
+            m.check().unwrap();
+            m.flip_edge(&e).unwrap();
+            assert_eq!((e.origin(), e.dest()), (3, 0));
+            let c = m.find_half_edge(0, 2).unwrap();
+            assert_eq!(m.collapse_edge(&c).unwrap(), 0);
+            assert_eq!((m.num_vertices(), m.num_edges(), m.num_faces()), (6, 11, 7));
+            assert_eq!(m.euler_characteristic(), 2);
+            m.check().unwrap();
+            // No record survives the mesh but the ones held.
+            assert_eq!(h.dest(), v);
+            let w = h.twin();
+            drop(m);
+            assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| w.next())).is_err());
+            // Boundary edges, and the link condition.
+            let mut m = Mesh::from_polygons(0..4, &[[0, 1, 2], [0, 2, 3]]).unwrap();
+            assert!(m.is_boundary_vertex(1) && m.vertex_half_edge(1).unwrap().is_boundary());
+            let b = m.find_half_edge(1, 2).unwrap();
+            let v = m.split_edge(&b.twin(), 4);
+            assert!(m.is_boundary_vertex(v));
+            m.check().unwrap();
+            let d = m.find_half_edge(0, 2).unwrap();
+            assert!(m.collapse_edge(&d).is_err());
+            assert_eq!(m.collapse_edge(&m.find_half_edge(0, 1).unwrap()), Ok(0));
+            m.check().unwrap();
+            assert_eq!(
+                (m.num_vertices(), m.num_faces(), m.euler_characteristic()),
+                (4, 2, 1)
+            );
+            // Non-manifold inputs.
+            let err = |p: &[[usize; 3]]| Mesh::from_polygons(0..5, p).err();
+            assert_eq!(
+                err(&[[0, 1, 2], [0, 1, 3]]),
+                Some(MeshError::NonManifoldEdge(0, 1))
+            );
+            assert_eq!(
+                err(&[[0, 1, 2], [0, 3, 4]]),
+                Some(MeshError::NonManifoldVertex(0))
+            );
+            assert_eq!(err(&[[0, 1, 1]]), Some(MeshError::InvalidFace(0)));
+            let cones = [[0, 1, 2], [0, 2, 3], [0, 3, 1], [0, 4, 1]];
+            assert!(err(&cones).is_some());
+        }
+
+        {
+            // Test quad-edges and the Delaunay triangulation built on them.
+            use quadedge::{Delaunay, QuadEdges};
+            let mut q = QuadEdges::new();
+            let a = q.make_edge(0, 1);
+            assert_eq!(
+                (q.onext(a), q.onext(a.sym()), q.onext(a.rot())),
+                (a, a.sym(), a.rot_inv())
+            );
+            let b = q.make_edge(1, 2);
+            q.splice(a.sym(), b);
+            let c = q.connect(b, a);
+            for e in [a, b, c]
+                .into_iter()
+                .flat_map(|e| [e, e.sym(), e.rot(), e.rot_inv()])
+            {
+                for e in [e, e.flip()] {
+                    assert_eq!(e.rot().rot().rot().rot(), e);
+                    assert_eq!(e.rot().flip().rot(), e.flip());
+                    assert_eq!(q.onext(q.oprev(e)), e);
+                    assert_eq!(q.onext(e.flip()).flip(), q.oprev(e));
+                }
+            }
+            assert_eq!(
+                (q.len(), q.dest(c), q.lnext(c.sym()), q.rnext(a)),
+                (3, Some(&0), b.sym(), c)
+            );
+            q.delete_edge(c);
+            assert_eq!((q.len(), q.lnext(a), q.lnext(b)), (2, b, b.sym()));
+            let d = q.make_edge(2, 0);
+            assert_eq!(d.index(), c.index());
+            assert!(q.contains(d) && !q.contains(c) && !q.contains(c.rot().flip()));
+            assert_ne!(c, d);
+            // Random points, with neither 3 collinear nor 4 cocircular.
+            let mut words = lcg(12345);
+            let mut rand = || (words.next().unwrap() >> 11) as f64 / (1u64 << 53) as f64;
+            let mut d = Delaunay::new(1.0);
+            let n = 200;
+            for _ in 0..n {
+                let p = (rand(), rand());
+                d.insert(p);
+            }
+            assert_eq!(d.insert(d.points()[7]), 7);
+            let p = d.points();
+            let t = d.triangles();
+            let cross = |a: (f64, f64), b: (f64, f64), c: (f64, f64)| {
+                (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+            };
+            for &[i, j, k] in &t {
+                let (a, b, c) = (p[i], p[j], p[k]);
+                assert!(cross(a, b, c) > 0.0);
+                let s = |p: (f64, f64)| p.0 * p.0 + p.1 * p.1;
+                let dd = 2.0 * cross(a, b, c);
+                let ux = (s(a) * (b.1 - c.1) + s(b) * (c.1 - a.1) + s(c) * (a.1 - b.1)) / dd;
+                let uy = (s(a) * (c.0 - b.0) + s(b) * (a.0 - c.0) + s(c) * (b.0 - a.0)) / dd;
+                let r = (a.0 - ux).powi(2) + (a.1 - uy).powi(2);
+                assert!(p[3..]
+                    .iter()
+                    .all(|q| (q.0 - ux).powi(2) + (q.1 - uy).powi(2) >= r * (1.0 - 1e-9)));
+            }
+            // A triangulation of n points with h on the convex hull has 2n - 2 - h triangles.
+            let mut s: Vec<_> = p[3..].to_vec();
+            s.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let mut hull: Vec<(f64, f64)> = Vec::new();
+            for pass in 0..2 {
+                let base = hull.len();
+                for &x in s.iter() {
+                    while hull.len() >= base + 2
+                        && cross(hull[hull.len() - 2], hull[hull.len() - 1], x) <= 0.0
+                    {
+                        hull.pop();
+                    }
+                    hull.push(x);
+                }
+                hull.pop();
+                if pass == 0 {
+                    s.reverse();
+                }
+            }
+            assert_eq!(t.len(), 2 * n - 2 - hull.len());
+            assert_eq!(d.edges().len(), 3 * (n + 3) - 6);
+        }
+
+        {
+            // Test simplicial complexes, their boundaries and Betti numbers.
+            use simplex::{boundary, sign, Complex, Field};
+            fn is_send_sync<T: Send + Sync>(_: &T) {}
+            // The boundary of a tetrahedron, a sphere.
+            let t = Complex::from_simplices([[0, 1, 2, 3]]);
+            is_send_sync(&t);
+            let s = t.simplices(3)[0].clone();
+            let sphere =
+                Complex::from_simplices(boundary(&s).iter().map(|f| f.vertices().to_vec()));
+            assert_eq!((sphere.dim(), sphere.euler_characteristic()), (Some(2), 2));
+            assert!(t.check_boundary() && sphere.check_boundary());
+            assert_eq!(t.betti_numbers(Field::Q), [1, 0, 0, 0]);
+            assert_eq!(sphere.betti_numbers(Field::Q), [1, 0, 1]);
+            assert_eq!(sphere.betti_numbers(Field::Z2), [1, 0, 1]);
+            // Orientation follows the order of vertices, and flips with it.
+            let e = t.get(&[2, 1]).unwrap();
+            assert!(e.o() && !e.clone_and_flip().o());
+            assert_eq!(e.vertices(), [1, 2]);
+            assert!(core::ptr::eq(&*t.get(&[1, 2]).unwrap(), &*e));
+            assert_eq!(boundary(&e).iter().map(sign).collect::<Vec<_>>(), [-1, 1]);
+            assert!(t.get(&[0, 4]).is_none());
+            // The 7-vertex torus.
+            let torus = Complex::from_simplices(
+                (0..7).flat_map(|i| [[i, (i + 1) % 7, (i + 3) % 7], [i, (i + 2) % 7, (i + 3) % 7]]),
+            );
+            assert_eq!(torus.euler_characteristic(), 0);
+            assert!(torus.check_boundary());
+            assert_eq!(torus.betti_numbers(Field::Q), [1, 2, 1]);
+            assert_eq!(torus.betti_numbers(Field::Z2), [1, 2, 1]);
+            // The 6-vertex projective plane, whose homology depends on the field.
+            let rp2 = Complex::from_simplices([
+                [0, 1, 2],
+                [0, 2, 3],
+                [0, 3, 4],
+                [0, 4, 5],
+                [0, 5, 1],
+                [1, 2, 4],
+                [2, 3, 5],
+                [3, 4, 1],
+                [4, 5, 2],
+                [5, 1, 3],
+            ]);
+            assert_eq!(rp2.euler_characteristic(), 1);
+            assert!(rp2.check_boundary());
+            assert_eq!(rp2.betti_numbers(Field::Q), [1, 0, 0]);
+            assert_eq!(rp2.betti_numbers(Field::Z2), [1, 1, 1]);
+            // Two disjoint circles.
+            let circles = Complex::from_simplices([[0, 1], [1, 2], [2, 0], [3, 4], [4, 5], [5, 3]]);
+            assert_eq!(circles.betti_numbers(Field::Z2), [2, 2]);
+        }
+
+        {
+            // Test orienting polygon soups, and finding cycles reversing orientation.
+            use mesh::{Mesh, MeshError};
+            use orient::Soup;
+            // A cycle reverses orientation if an odd number of its steps are inconsistent.
+            let reversing = |s: &Soup, c: &[usize]| {
+                (0..c.len())
+                    .map(|i| !s.is_consistent(c[i], c[(i + 1) % c.len()]).unwrap())
+                    .filter(|&b| b)
+                    .count()
+                    % 2
+                    == 1
+            };
+            // A cube with half of its faces backward.
+            let cube = [
+                [0, 3, 2, 1],
+                [4, 5, 6, 7],
+                [0, 4, 7, 3],
+                [1, 2, 6, 5],
+                [0, 1, 5, 4],
+                [3, 7, 6, 2],
+            ];
+            let mut s = Soup::from_polygons(&cube).unwrap();
+            for f in [1, 2, 5] {
+                s.flip_face(f);
+            }
+            assert!(Mesh::<()>::from_polygons([(); 8], &s.polygons()).is_err());
+            let c = s.orient();
+            assert_eq!(c.len(), 1);
+            assert!(c[0].is_orientable() && c[0].faces.len() == 6);
+            assert_eq!(s.polygons(), cube.map(|p| p.to_vec()));
+            assert!((0..12).all(|e| s.edge_faces(e).len() == 2));
+            let m = Mesh::from_polygons([(); 8], &s.polygons()).unwrap();
+            assert_eq!(m.euler_characteristic(), 2);
+            // Edge references are shared by the faces, and keep the direction in their bit.
+            let e = s.face(0)[0].clone();
+            assert_eq!((e.vertices(), e.o(), e.strong_count()), ([0, 3], false, 3));
+            // A Möbius strip of 3 quads.
+            let mut s = Soup::from_polygons(&[[0, 1, 4, 3], [1, 2, 5, 4], [2, 3, 0, 5]]).unwrap();
+            let c = s.orient();
+            let cycle = c[0].cycle.clone().unwrap();
+            assert_eq!(cycle.len(), 3);
+            assert!(reversing(&s, &cycle));
+            // A Klein bottle and a torus, as a 4 by 4 grid of quads glued with or without a twist.
+            let grid = |twist: bool| {
+                let n = 4;
+                let v = |i: usize, j: usize| match i == n {
+                    true if twist => (n - j % n) % n,
+                    true => j % n,
+                    false => i * n + j % n,
+                };
+                let quads: Vec<_> = (0..n * n)
+                    .map(|k| {
+                        let (i, j) = (k / n, k % n);
+                        [v(i, j), v(i + 1, j), v(i + 1, j + 1), v(i, j + 1)]
+                    })
+                    .collect();
+                Soup::from_polygons(&quads).unwrap()
+            };
+            let mut klein = grid(true);
+            for f in [3, 5, 6, 10] {
+                klein.flip_face(f);
+            }
+            let c = klein.orient();
+            assert_eq!((c.len(), c[0].faces.len()), (1, 16));
+            assert!(reversing(&klein, c[0].cycle.as_ref().unwrap()));
+            let mut torus = grid(false);
+            for f in [3, 5, 6, 10] {
+                torus.flip_face(f);
+            }
+            assert!(torus.orient()[0].is_orientable());
+            let m = Mesh::from_polygons([(); 16], &torus.polygons()).unwrap();
+            assert_eq!(m.euler_characteristic(), 0);
+            // Components are oriented separately, and invalid faces rejected.
+            let mut s =
+                Soup::from_polygons(&[vec![0, 1, 2], vec![5, 4, 3], vec![1, 2, 6]]).unwrap();
+            let c = s.orient();
+            assert_eq!(
+                c.iter().map(|c| c.faces.clone()).collect::<Vec<_>>(),
+                [vec![0, 2], vec![1]]
+            );
+            assert_eq!(s.face_vertices(2), [1, 6, 2]);
+            assert_eq!(
+                Soup::from_polygons(&[[0, 1, 1]]).err(),
+                Some(MeshError::InvalidFace(0))
+            );
+            assert_eq!(
+                Soup::from_polygons(&[[0, 1, 0, 2]]).err(),
+                Some(MeshError::InvalidFace(0))
+            );
+        }
+
+        {
+            // Test combinatorial maps, sewing, cells, and conversion from and to half-edge meshes.
+            use cmap::{CMap2, CMap3, CMapError};
+            use mesh::Mesh;
+            // A cube, from a mesh and back.
+            let cube = [
+                [0, 3, 2, 1],
+                [4, 5, 6, 7],
+                [0, 4, 7, 3],
+                [1, 2, 6, 5],
+                [0, 1, 5, 4],
+                [3, 7, 6, 2],
+            ];
+            let m = Mesh::from_polygons(0..8, &cube).unwrap();
+            let c = CMap2::from_mesh(&m);
+            assert!(c.is_valid());
+            assert_eq!(c.num_darts(), 24);
+            assert_eq!((c.num_cells(0), c.num_cells(1), c.num_cells(2)), (8, 12, 6));
+            assert_eq!(c.euler_characteristic(), 2);
+            assert!(c
+                .cells(0)
+                .iter()
+                .all(|v| v.iter().all(|&d| *c.data(d) == *c.data(v[0]))));
+            let d = c.darts().next().unwrap();
+            let mut tags: Vec<_> = c.links(d).iter().map(|l| l.get_usize()).collect();
+            tags.sort();
+            assert_eq!(tags, [0, 1, 2]);
+            let n = c.to_mesh(0..8).unwrap();
+            assert_eq!(
+                (
+                    n.num_vertices(),
+                    n.num_edges(),
+                    n.num_faces(),
+                    n.euler_characteristic()
+                ),
+                (8, 12, 6, 2)
+            );
+            // Faces keep their orientation, up to rotation.
+            let canon = |mut v: Vec<usize>| {
+                let i = (0..v.len()).min_by_key(|&i| v[i]).unwrap();
+                v.rotate_left(i);
+                v
+            };
+            assert!(n.faces().all(|f| {
+                let v = canon(n.face_vertices(f).collect());
+                cube.iter().any(|p| canon(p.to_vec()) == v)
+            }));
+            // An open square made of two triangles has a boundary, and stays one after conversion.
+            let m = Mesh::from_polygons(0..4, &[[0, 1, 2], [0, 2, 3]]).unwrap();
+            let mut c = CMap2::from_mesh(&m);
+            assert_eq!(
+                (c.num_darts(), c.num_cells(1), c.euler_characteristic()),
+                (6, 5, 1)
+            );
+            assert_eq!(c.to_mesh(0..4).unwrap().euler_characteristic(), 1);
+            let d = c.darts().find(|&d| !c.is_free(2, d)).unwrap();
+            let e = c.beta(2, d).unwrap();
+            assert_eq!(c.sew(2, d, e), Err(CMapError::NotFree(2, d)));
+            c.unsew(2, d).unwrap();
+            assert_eq!(c.unsew(2, d), Err(CMapError::Free(2, d)));
+            assert_eq!(c.num_cells(2), 2);
+            assert_eq!(c.data(d), &c.face_vertices(d).unwrap()[0]);
+            let x = c.beta(1, d).unwrap();
+            let v = *c.data(d);
+            assert_eq!(c.remove_dart(d), v);
+            assert!(!c.contains(d) && c.is_valid());
+            assert_eq!(
+                (c.face_vertices(x), c.face_vertices(e).map(|v| v.len())),
+                (None, Some(3))
+            );
+            assert!(matches!(
+                c.to_mesh(0..4),
+                Err(mesh::MeshError::InvalidFace(_))
+            ));
+            // Two tetrahedra glued along a triangle.
+            let mut c = CMap3::new();
+            let add_volume = |c: &mut CMap3<usize>, faces: [[usize; 3]; 4]| {
+                let mut darts = std::collections::HashMap::new();
+                for f in faces {
+                    let mut d = c.add_face(f);
+                    for i in 0..3 {
+                        darts.insert((f[i], f[(i + 1) % 3]), d);
+                        d = c.beta(1, d).unwrap();
+                    }
+                }
+                for (&(a, b), &d) in &darts {
+                    if a < b {
+                        c.sew(2, d, darts[&(b, a)]).unwrap();
+                    }
+                }
+                darts
+            };
+            let p = add_volume(&mut c, [[0, 2, 1], [0, 1, 3], [1, 2, 3], [0, 3, 2]]);
+            let q = add_volume(&mut c, [[4, 1, 2], [3, 1, 4], [3, 2, 1], [2, 3, 4]]);
+            assert_eq!((c.num_cells(2), c.num_cells(3)), (8, 2));
+            c.sew(3, p[&(1, 2)], q[&(2, 1)]).unwrap();
+            assert!(c.is_valid());
+            assert_eq!(
+                (0..4).map(|i| c.num_cells(i)).collect::<Vec<_>>(),
+                [5, 9, 7, 2]
+            );
+            assert_eq!(c.euler_characteristic(), 1);
+            assert_eq!(c.beta(3, p[&(2, 3)]), Some(q[&(3, 2)]));
+            // Unsewing by 1 in 3 dimensions also unsews the darts glued to them.
+            c.unsew(1, p[&(1, 2)]).unwrap();
+            assert!(
+                c.is_free(1, p[&(1, 2)]) && c.is_free(1, q[&(3, 2)]) && c.is_free(0, q[&(2, 1)])
+            );
+            assert!(c.is_valid());
+            c.sew(1, p[&(1, 2)], p[&(2, 3)]).unwrap();
+            assert_eq!(c.beta(1, q[&(3, 2)]), Some(q[&(2, 1)]));
+            assert!(c.is_valid());
+            // Faces of different sizes cannot be glued.
+            let t = c.add_face([5, 6, 7]);
+            let s = c.add_face([8, 9, 10, 11]);
+            assert_eq!(c.sew(3, t, s), Err(CMapError::OrbitMismatch(3)));
+            assert!(c.is_free(3, t));
+            let n = c.num_darts();
+            assert!(
+                std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| c.add_face([]))).is_err()
+            );
+            assert_eq!(c.num_darts(), n);
+        }
+
+        {
+            // Test reading and writing meshes, round-tripping bundled samples.
+            use mesh::MeshError;
+            use meshio::{read, write, Format, MeshIoError};
+            let samples = [
+                (Format::Obj, include_str!("../samples/cube.obj"), vec![4], 2),
+                (
+                    Format::Off,
+                    include_str!("../samples/octahedron.off"),
+                    vec![],
+                    2,
+                ),
+                (
+                    Format::Ply,
+                    include_str!("../samples/pyramid.ply"),
+                    vec![],
+                    1,
+                ),
+            ];
+            for (format, text, flipped, euler) in samples {
+                let (m, report) = read(format, text).unwrap();
+                assert_eq!((report.flipped, m.euler_characteristic()), (flipped, euler));
+                m.check().unwrap();
+                for other in [Format::Obj, Format::Off, Format::Ply] {
+                    let written = write(other, &m);
+                    let (n, report) = read(other, &written).unwrap();
+                    assert!(report.flipped.is_empty());
+                    assert_eq!(write(other, &n), written);
+                    assert!(m.vertices().all(|v| m.vertex(v) == n.vertex(v)));
+                    assert!(m.faces().all(|f| m.face_vertices(f).eq(n.face_vertices(f))));
+                }
+            }
+            let (m, _) = read(Format::Ply, include_str!("../samples/pyramid.ply")).unwrap();
+            assert_eq!(*m.vertex(4), [0.5, 0.5, 0.75]);
+            assert!(m.is_boundary_vertex(0) && !m.is_boundary_vertex(4));
+            // Errors found while importing.
+            let fan = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 -1 0\nv 0 0 1\nf 1 2 3\nf 2 1 4\nf 1 2 5\n";
+            assert_eq!(
+                read(Format::Obj, fan).err(),
+                Some(MeshIoError::NonManifoldEdges(vec![[0, 1]]))
+            );
+            let mobius = "OFF 6 3 0\n0 0 0\n1 0 0\n2 0 0\n0 1 0\n1 1 0\n2 1 0\n\
+                          4 0 1 4 3\n4 1 2 5 4\n4 2 3 0 5\n";
+            match read(Format::Off, mobius) {
+                Err(MeshIoError::NonOrientable(cycle)) => assert_eq!(cycle.len(), 3),
+                _ => panic!(),
+            }
+            // Colors and normals of OFF variants, and scalar face properties before the vertex list of PLY.
+            let coff = "COFF\n3 1 0\n0 0 0 255 0 0 255\n1 0 0 0 255 0 255\n0 1 0 0 0 255 255\n3 0 1 2 9 9 9\n";
+            assert_eq!(read(Format::Off, coff).unwrap().0.num_faces(), 1);
+            let noff = "NOFF 3 1 0\n0 0 0 0 0 1\n1 0 0 0 0 1\n0 1 0 0 0 1\n3 0 1 2\n";
+            assert_eq!(
+                *read(Format::Off, noff).unwrap().0.vertex(1),
+                [1.0, 0.0, 0.0]
+            );
+            let ply = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\n\
+                       property float z\nelement face 1\nproperty uchar flags\nproperty list uchar int texcoord\n\
+                       property list uchar int vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n7 2 0 1 3 0 1 2\n";
+            let (m, _) = read(Format::Ply, ply).unwrap();
+            assert!(m.faces().all(|f| m.face_vertices(f).eq([0, 1, 2])));
+            let bowtie = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv -1 0 0\nv 0 -1 0\nf 1 2 3\nf 1 4 5\n";
+            assert_eq!(
+                read(Format::Obj, bowtie).err(),
+                Some(MeshIoError::Mesh(MeshError::NonManifoldVertex(0)))
+            );
+            let parse = |format, text| match read(format, text) {
+                Err(MeshIoError::Parse(line, _)) => line,
+                _ => usize::MAX,
+            };
+            assert_eq!(parse(Format::Obj, "# comment\nv 0 x 0\n"), 2);
+            assert_eq!(
+                parse(Format::Obj, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n"),
+                4
+            );
+            assert_eq!(parse(Format::Off, "OFF\n3 1 0\n0 0 0\n1 0 0\n"), 0);
+            assert_eq!(parse(Format::Off, "4OFF\n1 0 0\n0 0 0 0\n"), 1);
+            assert_eq!(
+                parse(Format::Ply, "ply\nformat binary_little_endian 1.0\n"),
+                2
+            );
+            assert!(MeshIoError::Parse(2, "bad".into())
+                .to_string()
+                .contains("line 2"));
+        }
+
+        {
+            // Test graphs with edges stored once, directed by their bits.
+            use ograph::Graph;
+            let token = Rc::new(());
+            let mut g = Graph::new_undirected();
+            let v: Vec<_> = (0..6).map(|i| g.add_vertex(i)).collect();
+            for (a, b) in [(0, 1), (0, 2), (1, 3), (2, 3), (3, 4), (4, 4)] {
+                g.add_edge(v[a], v[b], token.clone());
+            }
+            assert_eq!(Rc::strong_count(&token), 7);
+            assert_eq!((g.num_vertices(), g.num_edges(), g.degree(4)), (6, 6, 3));
+            assert_eq!(g.bfs(0), [0, 1, 2, 3, 4]);
+            assert_eq!(g.dfs(0), [0, 1, 3, 2, 4]);
+            assert_eq!(g.bfs(5), [5]);
+            assert_eq!(g.neighbors(3).collect::<Vec<_>>(), [(2, 1), (3, 2), (4, 4)]);
+            assert_eq!(
+                g.neighbors(4).collect::<Vec<_>>(),
+                g.in_neighbors(4).collect::<Vec<_>>()
+            );
+            // Reversing an undirected edge only swaps its endpoints.
+            g.reverse_edge(0);
+            assert_eq!((g.endpoints(0), g.bfs(0)), ((1, 0), vec![0, 1, 2, 3, 4]));
+            // Removing edges and vertices drops their data and records.
+            let w = g.edge_record(4);
+            assert_eq!((w.upgrade().unwrap().ends(), w.o()), ([3, 4], false));
+            assert_eq!(g.remove_vertex(3), 3);
+            assert!(w.upgrade().is_none());
+            assert_eq!((g.num_edges(), Rc::strong_count(&token)), (3, 4));
+            assert_eq!(g.bfs(0), [0, 1, 2]);
+            assert_eq!(g.degree(4), 2);
+            // A directed graph follows edges forward only, until reversed.
+            let mut d = Graph::new_directed();
+            let v: Vec<_> = (0..4).map(|i| d.add_vertex(i)).collect();
+            let e: Vec<_> = [(0, 1), (1, 2), (2, 0), (2, 3)]
+                .iter()
+                .map(|&(a, b)| d.add_edge(v[a], v[b], token.clone()))
+                .collect();
+            assert_eq!((d.bfs(3), d.bfs(0)), (vec![3], vec![0, 1, 2, 3]));
+            assert_eq!(d.in_neighbors(0).collect::<Vec<_>>(), [(e[2], 2)]);
+            d.reverse_edge(e[3]);
+            assert!(d.edge_record(e[3]).o());
+            assert_eq!((d.endpoints(e[3]), d.bfs(3)), ((3, 2), vec![3, 2, 0, 1]));
+            assert_eq!(d.bfs(0), [0, 1, 2]);
+            *d.edge_mut(e[0]) = Rc::new(());
+            *d.vertex_mut(1) += 10;
+            assert_eq!((*d.vertex(1), Rc::strong_count(d.edge(e[0]))), (11, 1));
+            let records: Vec<_> = d.edges().map(|e| d.edge_record(e)).collect();
+            assert_eq!(Rc::strong_count(&token), 7);
+            drop((d, g));
+            assert_eq!(Rc::strong_count(&token), 1);
+            assert!(records.iter().all(|w| w.upgrade().is_none()));
+        }
+
+        {
+            // Test persistent red-black trees against BTreeMap, keeping snapshots intact.
+            use rbmap::*;
+            let mut m = RbMap::new();
+            let mut b = std::collections::BTreeMap::new();
+            let mut snapshots = Vec::new();
+            for (i, x) in lcg(7).take(2000).enumerate() {
+                let k = (x >> 33) % 300;
+                if x >> 63 == 0 {
+                    assert_eq!(m.insert(k, i), b.insert(k, i));
+                } else {
+                    assert_eq!(m.remove(&k), b.remove(&k));
+                }
+                if i % 100 == 0 {
+                    assert!(m.check());
+                    snapshots.push((m.clone(), b.clone()));
+                }
+            }
+            assert_eq!(m.len(), b.len());
+            assert!(m.iter().eq(b.iter()));
+            assert!(m.range(50..=120).eq(b.range(50..=120)));
+            assert!(m.range(..77).eq(b.range(..77)));
+            assert!(m.range(300..).eq(b.range(300..)));
+            assert!(m.range(5..5).eq(b.range(5..5)));
+            let reversed = (core::ops::Bound::Included(5), core::ops::Bound::Included(3));
+            assert!(std::panic::catch_unwind(|| m.range(reversed).count()).is_err());
+            for (m, b) in &snapshots {
+                assert!(m.check() && m.iter().eq(b.iter()));
+            }
+            // Removing everything leaves an empty map.
+            let keys: Vec<_> = m.iter().map(|(k, _)| *k).collect();
+            keys.iter().for_each(|k| assert!(m.remove(k).is_some()));
+            assert!(m.is_empty() && m.check() && m.get(&keys[0]).is_none());
+            // The Arc variant is shared across threads.
+            let a: ArcRbMap<u64, u64> = (0..100).map(|i| (i, i * i)).collect();
+            let t = {
+                let mut a = a.clone();
+                std::thread::spawn(move || {
+                    a.insert(5, 0);
+                    a.get(&5).copied()
+                })
+            };
+            assert_eq!((t.join().unwrap(), a.get(&5)), (Some(0), Some(&25)));
+            assert!(a.check() && a.range(10..13).map(|(_, v)| *v).eq([100, 121, 144]));
+            // Nodes are smaller than with a separate colour.
+            #[allow(dead_code)]
+            struct Plain(u64, u64, Option<Rc<Plain>>, Option<Rc<Plain>>, bool);
+            assert_eq!(RbMap::<u64, u64>::NODE_SIZE, 32);
+            assert_eq!(ArcRbMap::<u64, u64>::NODE_SIZE, 32);
+            assert_eq!(size_of::<Plain>(), 40);
+        }
+
+        {
+            // Test hash array mapped tries against HashMap, with collisions and shared versions.
+            use hamt::*;
+            // Keys with 4 hashes differing only in their highest bits.
+            #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+            struct Key(u64);
+            impl core::hash::Hash for Key {
+                fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+                    state.write_u64((self.0 % 4) << 61);
+                }
+            }
+            let mut m = HamtMap::new();
+            let mut h = std::collections::HashMap::new();
+            let mut snapshots = Vec::new();
+            for (i, x) in lcg(11).take(3000).enumerate() {
+                let k = ((x >> 33) % 500, Key((x >> 40) % 12));
+                if x >> 63 == 0 {
+                    assert_eq!(m.insert(k, i), h.insert(k, i));
+                } else {
+                    assert_eq!(m.remove(&k), h.remove(&k));
+                }
+                assert_eq!(m.get(&k), h.get(&k));
+                if i % 300 == 0 {
+                    snapshots.push((m.clone(), h.clone()));
+                }
+            }
+            let entries = |m: &HamtMap<_, _>| {
+                let mut v: Vec<_> = m.iter().map(|(k, v)| (*k, *v)).collect();
+                v.sort_by_key(|((a, Key(b)), _)| (*a, *b));
+                v
+            };
+            for (m, h) in snapshots.iter().chain([(m.clone(), h.clone())].iter()) {
+                let mut e: Vec<_> = h.iter().map(|(k, v)| (*k, *v)).collect();
+                e.sort_by_key(|((a, Key(b)), _)| (*a, *b));
+                assert_eq!((m.len(), entries(m)), (h.len(), e));
+            }
+            // Colliding keys alone are kept in collision nodes, down to a leaf.
+            let mut c: HamtMap<Key, u64> = (0..6).map(|i| (Key(i * 4), i)).collect();
+            assert_eq!(
+                (c.len(), c.get(&Key(20)), c.get(&Key(24))),
+                (6, Some(&5), None)
+            );
+            assert_eq!(c.insert(Key(2), 9), None);
+            (1..6).for_each(|i| assert_eq!(c.remove(&Key(i * 4)), Some(i)));
+            assert_eq!(c.iter().count(), 2);
+            assert_eq!((c.remove(&Key(2)), c.remove(&Key(0))), (Some(9), Some(0)));
+            assert!(c.is_empty() && c.iter().next().is_none());
+            // Unshared nodes are modified in place, shared ones copied.
+            let mut m: HamtMap<u32, u32> = (0..1000).map(|i| (i, i)).collect();
+            let p = m.get(&7).unwrap() as *const u32;
+            m.insert(7, 8);
+            assert_eq!(m.get(&7).unwrap() as *const u32, p);
+            let n = m.update(7, 9);
+            assert_ne!(n.get(&7).unwrap() as *const u32, p);
+            assert_eq!(
+                (m.get(&7), n.get(&7), n.without(&7).get(&7)),
+                (Some(&8), Some(&9), None)
+            );
+            assert_eq!(size_of::<HamtMap<u32, u32>>(), 2 * size_of::<usize>());
+            // The Arc variant is shared across threads.
+            let a: ArcHamtMap<u64, u64> = (0..100).map(|i| (i, i * i)).collect();
+            let t = {
+                let a = a.clone();
+                std::thread::spawn(move || a.update(5, 0).get(&5).copied())
+            };
+            assert_eq!((t.join().unwrap(), a.get(&5)), (Some(0), Some(&25)));
+        }
+
+        {
+            // Test adaptive radix trees against BTreeMap, with node growth, shrinking and path compression.
+            use art::*;
+            let mut t = Art::new();
+            let mut b = std::collections::BTreeMap::new();
+            for (i, x) in lcg(5).take(5000).enumerate() {
+                // Short keys over a few bytes, often prefixes of one another, and some longer ones.
+                let n = (x >> 60) as usize % 5;
+                let mut k: Vec<u8> = (0..n)
+                    .map(|j| b"abc"[(x >> (8 * j + 8)) as usize % 3])
+                    .collect();
+                if x >> 59 & 1 == 1 {
+                    k.extend_from_slice(&(x >> 32).to_le_bytes()[..2]);
+                }
+                if x >> 58 & 1 == 0 {
+                    assert_eq!(t.insert(&k, i), b.insert(k.clone(), i));
+                } else {
+                    assert_eq!(t.remove(&k), b.remove(&k));
+                }
+                assert_eq!(t.get(&k), b.get(&k));
+            }
+            assert_eq!(t.len(), b.len());
+            assert!(t.iter().eq(b.iter().map(|(k, v)| (&k[..], v))));
+            for p in [&b""[..], b"a", b"ab", b"cab", b"abcabc"] {
+                let scan = b.iter().filter(|(k, _)| k.starts_with(p));
+                assert!(t.prefix(p).eq(scan.map(|(k, v)| (&k[..], v))));
+            }
+            for (k, v) in b.iter_mut() {
+                *v += 1;
+                *t.get_mut(k).unwrap() += 1;
+            }
+            assert!(t.iter().eq(b.iter().map(|(k, v)| (&k[..], v))));
+            // Nodes grow with their children, and shrink back.
+            let mut t = Art::new();
+            for i in 0..=255u8 {
+                t.insert([b'x', b'y', i], i);
+                let grown = match i {
+                    0 => [0, 0, 0, 0, 1],
+                    1..=3 => [1, 0, 0, 0, i as usize + 1],
+                    4..=15 => [0, 1, 0, 0, i as usize + 1],
+                    16..=47 => [0, 0, 1, 0, i as usize + 1],
+                    _ => [0, 0, 0, 1, i as usize + 1],
+                };
+                assert_eq!(t.node_counts(), grown);
+            }
+            assert!(t
+                .iter()
+                .map(|(k, v)| (k[2], *v))
+                .eq((0..=255).map(|i| (i, i))));
+            (36..=255u8).for_each(|i| assert_eq!(t.remove([b'x', b'y', i]), Some(i)));
+            assert_eq!(t.node_counts(), [0, 0, 1, 0, 36]);
+            (3..36u8).for_each(|i| assert_eq!(t.remove([b'x', b'y', i]), Some(i)));
+            assert_eq!(t.node_counts(), [1, 0, 0, 0, 3]);
+            assert_eq!(t.prefix("xy").count(), 3);
+            // Compressed paths split on insertion and merge back on removal.
+            let mut t: Art<i32> = [("interval", 0), ("internal", 1)].into_iter().collect();
+            assert_eq!(t.node_counts(), [1, 0, 0, 0, 2]);
+            t.insert("in", 2);
+            t.insert("inter", 3);
+            assert_eq!(t.node_counts(), [2, 0, 0, 0, 4]);
+            assert_eq!(
+                (t.get("int"), t.get("inter"), t.get("internals")),
+                (None, Some(&3), None)
+            );
+            assert_eq!(
+                t.prefix("inte").map(|(_, v)| *v).collect::<Vec<_>>(),
+                [3, 1, 0]
+            );
+            assert_eq!(t.prefix("intern").map(|(_, v)| *v).collect::<Vec<_>>(), [1]);
+            assert_eq!(t.prefix("internet").count(), 0);
+            assert_eq!(
+                (t.remove("inter"), t.remove("in"), t.remove("in")),
+                (Some(3), Some(2), None)
+            );
+            assert_eq!(t.node_counts(), [1, 0, 0, 0, 2]);
+            assert_eq!(t.remove("interval"), Some(0));
+            assert_eq!(
+                (t.node_counts(), t.get("internal")),
+                ([0, 0, 0, 0, 1], Some(&1))
+            );
+            assert_eq!(t.remove("internal"), Some(1));
+            assert!(t.is_empty() && t.iter().next().is_none());
+            // Dropping a tree drops its values.
+            let token = Rc::new(());
+            let t: Art<_> = (0..1000u32)
+                .map(|i| (i.to_be_bytes(), token.clone()))
+                .collect();
+            assert_eq!(Rc::strong_count(&token), 1001);
+            drop(t);
+            assert_eq!(Rc::strong_count(&token), 1);
+            // Trees of keys prefixing one another are as deep as their keys are long, and are handled without
+            // recursion, on a stack far smaller than needed for a call per level.
+            let deep = std::thread::Builder::new().stack_size(1 << 17).spawn(|| {
+                let keys: Vec<_> = (1..=4000).map(|n| vec![b'a'; n]).collect();
+                let mut t: Art<_> = keys.iter().zip(0..).collect();
+                assert_eq!(t.node_counts(), [3999, 0, 0, 0, 4000]);
+                assert_eq!(t.get(&keys[3999]), Some(&3999));
+                (0..4000)
+                    .step_by(2)
+                    .for_each(|i| assert_eq!(t.remove(&keys[i]), Some(i)));
+                assert!(t.iter().map(|(_, v)| *v).eq((1..4000).step_by(2)));
+                t.len()
+            });
+            assert_eq!(deep.unwrap().join().unwrap(), 2000);
+        }
+
         // Test size comparison of Rc<i32> and Option<BRc<i32>>.
         assert_eq!(size_of::<Rc<i32>>(), size_of::<Option<BRc<i32>>>());
     }
@@ -0,0 +1,558 @@
+//! This module defines a half-edge `Mesh`, where both half-edges of an edge share a single [`Edge`] record
+//! behind an `ORc`, the stolen bit telling which of them is meant, so that the twin of a [`HalfEdge`] is
+//! just its `clone_and_flip`.
+//!
+//! The mesh owns its edge records, while half-edges link to each other by `OWeak`s, so that dropping the mesh
+//! frees them all.
+//! ```
+//! use ointer::mesh::Mesh;
+//! // A square split into two triangles along its diagonal 0-2.
+//! let mut m = Mesh::from_polygons([(); 4], &[[0, 1, 2], [0, 2, 3]]).unwrap();
+//! assert_eq!((m.num_vertices(), m.num_edges(), m.num_faces()), (4, 5, 2));
+//! let h = m.find_half_edge(0, 2).unwrap();
+//! assert_eq!((h.twin().origin(), h.twin().twin(), h.next().dest()), (2, h.clone(), 3));
+//! m.flip_edge(&h).unwrap();
+//! assert_eq!((h.origin(), h.dest()), (1, 3));
+//! m.check().unwrap();
+//! ```
+
+use crate::rc::{ORc, OWeak};
+use core::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+
+/// Errors of building, editing or checking a [`Mesh`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MeshError {
+    /// A polygon with fewer than 3 vertices, repeated vertices or vertices out of range.
+    InvalidFace(usize),
+    /// A directed edge from the first to the second vertex used by more than one polygon.
+    NonManifoldEdge(usize, usize),
+    /// A vertex whose polygons do not form a single fan.
+    NonManifoldVertex(usize),
+    /// A half-edge, by id, whose links are inconsistent.
+    BrokenHalfEdge(usize),
+    /// An Euler operator whose preconditions do not hold.
+    IllegalOperation(&'static str),
+}
+
+impl core::fmt::Display for MeshError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidFace(i) => write!(f, "invalid face {i}"),
+            Self::NonManifoldEdge(a, b) => write!(f, "non-manifold edge {a} -> {b}"),
+            Self::NonManifoldVertex(v) => write!(f, "non-manifold vertex {v}"),
+            Self::BrokenHalfEdge(h) => write!(f, "broken half-edge {h}"),
+            Self::IllegalOperation(s) => write!(f, "illegal operation: {s}"),
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+/// One direction of an [`Edge`].
+struct Half {
+    origin: Cell<usize>,
+    face: Cell<Option<usize>>,
+    next: RefCell<Option<OWeak<Edge>>>,
+    prev: RefCell<Option<OWeak<Edge>>>,
+}
+
+impl Half {
+    fn new(origin: usize) -> Self {
+        Self {
+            origin: Cell::new(origin),
+            face: Cell::new(None),
+            next: RefCell::new(None),
+            prev: RefCell::new(None),
+        }
+    }
+}
+
+/// Edge record shared by its two half-edges, the one from its first vertex when the stolen bit is `0`,
+/// and the one from its second vertex otherwise.
+pub struct Edge {
+    id: usize,
+    half: [Half; 2],
+}
+
+/// Half-edge of a [`Mesh`], an `ORc` to its [`Edge`] record whose stolen bit selects the direction.
+#[derive(Clone)]
+pub struct HalfEdge(ORc<Edge>);
+
+impl HalfEdge {
+    fn half(&self) -> &Half {
+        &self.0.half[usize::from(self.0.o())]
+    }
+    fn downgrade(&self) -> OWeak<Edge> {
+        self.0.downgrade()
+    }
+    fn upgrade(w: Option<&OWeak<Edge>>) -> Option<Self> {
+        w?.upgrade().map(Self)
+    }
+    fn link(&self, next: &Self) {
+        *self.half().next.borrow_mut() = Some(next.downgrade());
+        *next.half().prev.borrow_mut() = Some(self.downgrade());
+    }
+    /// Get the id of the edge record.
+    pub fn edge(&self) -> usize {
+        self.0.id
+    }
+    /// Get the id of the half-edge, twice the id of its edge plus its stolen bit.
+    pub fn id(&self) -> usize {
+        self.0.id * 2 + usize::from(self.0.o())
+    }
+    /// Get the half-edge in the opposite direction, sharing the same edge record.
+    pub fn twin(&self) -> Self {
+        Self(self.0.clone_and_flip())
+    }
+    /// Get the next half-edge around the face.
+    pub fn next(&self) -> Self {
+        Self::upgrade(self.half().next.borrow().as_ref()).expect("Dangling half-edge")
+    }
+    /// Get the previous half-edge around the face.
+    pub fn prev(&self) -> Self {
+        Self::upgrade(self.half().prev.borrow().as_ref()).expect("Dangling half-edge")
+    }
+    /// Get the vertex it starts from.
+    pub fn origin(&self) -> usize {
+        self.half().origin.get()
+    }
+    /// Get the vertex it points to.
+    pub fn dest(&self) -> usize {
+        self.0.half[usize::from(!self.0.o())].origin.get()
+    }
+    /// Get the face on its left, or `None` on the boundary.
+    pub fn face(&self) -> Option<usize> {
+        self.half().face.get()
+    }
+    /// Whether there is no face on its left.
+    pub fn is_boundary(&self) -> bool {
+        self.face().is_none()
+    }
+    /// Iterate over the half-edges around its face, starting from `self`.
+    pub fn face_loop(&self) -> Circulator {
+        Circulator::new(self.clone(), Self::next)
+    }
+    /// Iterate over the half-edges starting from its origin, starting from `self`.
+    pub fn fan(&self) -> Circulator {
+        Circulator::new(self.clone(), |h| h.twin().next())
+    }
+}
+
+impl PartialEq for HalfEdge {
+    fn eq(&self, rhs: &Self) -> bool {
+        core::ptr::eq(&*self.0, &*rhs.0) && self.0.o() == rhs.0.o()
+    }
+}
+
+impl Eq for HalfEdge {}
+
+impl core::hash::Hash for HalfEdge {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.id().hash(state)
+    }
+}
+
+impl core::fmt::Debug for HalfEdge {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+        write!(f, "HalfEdge({} -> {})", self.origin(), self.dest())
+    }
+}
+
+/// Iterator over half-edges stepping from a start until back to it.
+pub struct Circulator {
+    start: HalfEdge,
+    cur: Option<HalfEdge>,
+    step: fn(&HalfEdge) -> HalfEdge,
+}
+
+impl Circulator {
+    fn new(start: HalfEdge, step: fn(&HalfEdge) -> HalfEdge) -> Self {
+        Self {
+            cur: Some(start.clone()),
+            start,
+            step,
+        }
+    }
+}
+
+impl Iterator for Circulator {
+    type Item = HalfEdge;
+    fn next(&mut self) -> Option<HalfEdge> {
+        let h = self.cur.take()?;
+        let n = (self.step)(&h);
+        if n != self.start {
+            self.cur = Some(n);
+        }
+        Some(h)
+    }
+}
+
+struct Vertex<V> {
+    data: V,
+    half: Option<OWeak<Edge>>,
+}
+
+/// Half-edge mesh of oriented manifold polygons, with vertex data `V`.
+///
+/// Vertices, edges and faces are numbered in order of creation, and numbers of removed ones are not reused.
+pub struct Mesh<V = ()> {
+    vertices: Vec<Option<Vertex<V>>>,
+    edges: Vec<Option<ORc<Edge>>>,
+    faces: Vec<Option<OWeak<Edge>>>,
+}
+
+impl<V> Default for Mesh<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Mesh<V> {
+    /// Make an empty mesh.
+    pub const fn new() -> Self {
+        Self {
+            vertices: Vec::new(),
+            edges: Vec::new(),
+            faces: Vec::new(),
+        }
+    }
+    /// Build a mesh from vertex data and polygons of vertex indices, all oriented the same way.
+    pub fn from_polygons<P: AsRef<[usize]>>(
+        vertices: impl IntoIterator<Item = V>,
+        polygons: &[P],
+    ) -> Result<Self, MeshError> {
+        let mut m = Self::new();
+        vertices.into_iter().for_each(|v| {
+            m.add_vertex(v);
+        });
+        let mut directed = HashMap::<(usize, usize), HalfEdge>::new();
+        for (f, p) in polygons.iter().enumerate() {
+            let p = p.as_ref();
+            let distinct = p.iter().collect::<HashSet<_>>().len() == p.len();
+            if p.len() < 3 || !distinct || p.iter().any(|&v| v >= m.vertices.len()) {
+                return Err(MeshError::InvalidFace(f));
+            }
+            let mut loop_ = Vec::with_capacity(p.len());
+            for (i, &a) in p.iter().enumerate() {
+                let b = p[(i + 1) % p.len()];
+                if directed.contains_key(&(a, b)) {
+                    return Err(MeshError::NonManifoldEdge(a, b));
+                }
+                let h = match directed.get(&(b, a)) {
+                    Some(t) => t.twin(),
+                    None => m.new_edge(a, b),
+                };
+                h.half().face.set(Some(f));
+                directed.insert((a, b), h.clone());
+                loop_.push(h);
+            }
+            for (i, h) in loop_.iter().enumerate() {
+                h.link(&loop_[(i + 1) % loop_.len()]);
+            }
+            m.faces.push(Some(loop_[0].downgrade()));
+        }
+        let mut boundary = HashMap::new();
+        for (&(a, b), h) in &directed {
+            if !directed.contains_key(&(b, a)) && boundary.insert(b, h.twin()).is_some() {
+                return Err(MeshError::NonManifoldVertex(b));
+            }
+        }
+        for h in boundary.values() {
+            h.link(&boundary[&h.dest()]);
+        }
+        for h in directed.values().chain(boundary.values()) {
+            let v = m.vertices[h.origin()].as_mut().unwrap();
+            if v.half.is_none() || h.is_boundary() {
+                v.half = Some(h.downgrade());
+            }
+        }
+        m.check()?;
+        Ok(m)
+    }
+    fn new_edge(&mut self, a: usize, b: usize) -> HalfEdge {
+        let e = ORc::new(Edge {
+            id: self.edges.len(),
+            half: [Half::new(a), Half::new(b)],
+        });
+        self.edges.push(Some(e.clone()));
+        HalfEdge(e)
+    }
+    /// Add an isolated vertex holding `data`, returning its index.
+    pub fn add_vertex(&mut self, data: V) -> usize {
+        self.vertices.push(Some(Vertex { data, half: None }));
+        self.vertices.len() - 1
+    }
+    /// Get the data of vertex `v`.
+    pub fn vertex(&self, v: usize) -> &V {
+        &self.vertices[v].as_ref().expect("Removed vertex").data
+    }
+    /// Get the data of vertex `v` mutably.
+    pub fn vertex_mut(&mut self, v: usize) -> &mut V {
+        &mut self.vertices[v].as_mut().expect("Removed vertex").data
+    }
+    /// Get the number of vertices.
+    pub fn num_vertices(&self) -> usize {
+        self.vertices.iter().flatten().count()
+    }
+    /// Get the number of edges.
+    pub fn num_edges(&self) -> usize {
+        self.edges.iter().flatten().count()
+    }
+    /// Get the number of faces.
+    pub fn num_faces(&self) -> usize {
+        self.faces.iter().flatten().count()
+    }
+    /// Get the Euler characteristic `V - E + F`.
+    pub fn euler_characteristic(&self) -> isize {
+        self.num_vertices() as isize - self.num_edges() as isize + self.num_faces() as isize
+    }
+    /// Iterate over the indices of vertices.
+    pub fn vertices(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.vertices.len()).filter(|&v| self.vertices[v].is_some())
+    }
+    /// Iterate over the indices of faces.
+    pub fn faces(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.faces.len()).filter(|&f| self.faces[f].is_some())
+    }
+    /// Iterate over one half-edge per edge, the one from its first vertex.
+    pub fn edges(&self) -> impl Iterator<Item = HalfEdge> + '_ {
+        self.edges.iter().flatten().map(|e| HalfEdge(e.clone()))
+    }
+    /// Iterate over all half-edges.
+    pub fn half_edges(&self) -> impl Iterator<Item = HalfEdge> + '_ {
+        self.edges().flat_map(|h| [h.twin(), h])
+    }
+    /// Get a half-edge starting from vertex `v`, on the boundary if `v` is, or `None` if `v` is isolated.
+    pub fn vertex_half_edge(&self, v: usize) -> Option<HalfEdge> {
+        let v = self.vertices[v].as_ref().expect("Removed vertex");
+        HalfEdge::upgrade(v.half.as_ref())
+    }
+    /// Get a half-edge of face `f`.
+    pub fn face_half_edge(&self, f: usize) -> HalfEdge {
+        HalfEdge::upgrade(Some(self.faces[f].as_ref().expect("Removed face"))).unwrap()
+    }
+    /// Find the half-edge from vertex `a` to vertex `b`.
+    pub fn find_half_edge(&self, a: usize, b: usize) -> Option<HalfEdge> {
+        self.vertex_half_edge(a)?.fan().find(|h| h.dest() == b)
+    }
+    /// Iterate over the vertices of face `f` in order.
+    pub fn face_vertices(&self, f: usize) -> impl Iterator<Item = usize> {
+        self.face_half_edge(f).face_loop().map(|h| h.origin())
+    }
+    /// Iterate over the vertices adjacent to vertex `v`.
+    pub fn vertex_neighbors(&self, v: usize) -> impl Iterator<Item = usize> {
+        self.vertex_half_edge(v)
+            .into_iter()
+            .flat_map(|h| h.fan())
+            .map(|h| h.dest())
+    }
+    /// Iterate over the faces around vertex `v`.
+    pub fn vertex_faces(&self, v: usize) -> impl Iterator<Item = usize> {
+        self.vertex_half_edge(v)
+            .into_iter()
+            .flat_map(|h| h.fan())
+            .filter_map(|h| h.face())
+    }
+    /// Whether vertex `v` is on the boundary.
+    pub fn is_boundary_vertex(&self, v: usize) -> bool {
+        self.vertex_half_edge(v).is_some_and(|h| h.is_boundary())
+    }
+    fn is_alive(&self, h: &HalfEdge) -> bool {
+        matches!(self.edges.get(h.edge()), Some(Some(e)) if core::ptr::eq(&**e, &*h.0))
+    }
+    fn set_vertex_half_edge(&mut self, v: usize, h: &HalfEdge) {
+        self.vertices[v].as_mut().unwrap().half = Some(h.downgrade());
+    }
+    /// Move origin, face and links of half-edge `src` into the slot of `dst`, redirecting references to `src`.
+    fn replace_half_edge(&mut self, src: &HalfEdge, dst: &HalfEdge) {
+        let (prev, next) = (src.prev(), src.next());
+        dst.half().origin.set(src.origin());
+        dst.half().face.set(src.face());
+        prev.link(dst);
+        dst.link(&next);
+        if let Some(f) = src.face() {
+            if self.face_half_edge(f) == *src {
+                self.faces[f] = Some(dst.downgrade());
+            }
+        }
+        if self.vertex_half_edge(src.origin()).as_ref() == Some(src) {
+            self.set_vertex_half_edge(src.origin(), dst);
+        }
+    }
+    /// Split the edge of `h`, from `a` to `b`, by a new vertex `m` holding `data`, so that `h` goes from `a`
+    /// to `m`, followed by a new half-edge from `m` to `b`. Return `m`.
+    pub fn split_edge(&mut self, h: &HalfEdge, data: V) -> usize {
+        let t = h.twin();
+        let (b, hn, tp) = (h.dest(), h.next(), t.prev());
+        let m = self.add_vertex(data);
+        let n = self.new_edge(m, b);
+        let nt = n.twin();
+        n.half().face.set(h.face());
+        nt.half().face.set(t.face());
+        h.link(&n);
+        n.link(&hn);
+        tp.link(&nt);
+        nt.link(&t);
+        t.half().origin.set(m);
+        if self.vertex_half_edge(b).as_ref() == Some(&t) {
+            self.set_vertex_half_edge(b, &nt);
+        }
+        self.set_vertex_half_edge(m, if t.is_boundary() { &t } else { &n });
+        m
+    }
+    /// Flip the edge of `h` shared by two triangles `(a, b, c)` and `(b, a, d)`, so that it connects `d` to `c`.
+    pub fn flip_edge(&mut self, h: &HalfEdge) -> Result<(), MeshError> {
+        let t = h.twin();
+        let (Some(f), Some(g)) = (h.face(), t.face()) else {
+            return Err(MeshError::IllegalOperation("flip of a boundary edge"));
+        };
+        if h.face_loop().count() != 3 || t.face_loop().count() != 3 {
+            return Err(MeshError::IllegalOperation(
+                "flip of an edge of non-triangles",
+            ));
+        }
+        let (hn, hp, tn, tp) = (h.next(), h.prev(), t.next(), t.prev());
+        let (a, b, c, d) = (h.origin(), t.origin(), hp.origin(), tp.origin());
+        if c == d || self.find_half_edge(c, d).is_some() {
+            return Err(MeshError::IllegalOperation("flip to an existing edge"));
+        }
+        h.half().origin.set(d);
+        t.half().origin.set(c);
+        h.link(&hp);
+        hp.link(&tn);
+        tn.link(h);
+        t.link(&tp);
+        tp.link(&hn);
+        hn.link(&t);
+        tn.half().face.set(Some(f));
+        hn.half().face.set(Some(g));
+        self.faces[f] = Some(h.downgrade());
+        self.faces[g] = Some(t.downgrade());
+        if self.vertex_half_edge(a).as_ref() == Some(h) {
+            self.set_vertex_half_edge(a, &tn);
+        }
+        if self.vertex_half_edge(b).as_ref() == Some(&t) {
+            self.set_vertex_half_edge(b, &hn);
+        }
+        Ok(())
+    }
+    /// Collapse the edge of `h`, from `a` to `b`, merging `b` into `a`, and removing the triangles on its sides.
+    /// Return `a`.
+    ///
+    /// The collapse is rejected unless the vertices adjacent to both `a` and `b` are the apexes of those
+    /// triangles, so that the mesh stays manifold.
+    pub fn collapse_edge(&mut self, h: &HalfEdge) -> Result<usize, MeshError> {
+        let t = h.twin();
+        let (a, b) = (h.origin(), h.dest());
+        let is_triangle = |s: &HalfEdge| !s.is_boundary() && s.face_loop().count() == 3;
+        let apexes = [h, &t]
+            .into_iter()
+            .filter(|s| is_triangle(s))
+            .map(|s| s.prev().origin())
+            .collect::<HashSet<_>>();
+        let na = self.vertex_neighbors(a).collect::<HashSet<_>>();
+        if self
+            .vertex_neighbors(b)
+            .any(|v| na.contains(&v) && !apexes.contains(&v))
+        {
+            return Err(MeshError::IllegalOperation(
+                "collapse breaking the link condition",
+            ));
+        }
+        if !h.is_boundary()
+            && !t.is_boundary()
+            && self.is_boundary_vertex(a)
+            && self.is_boundary_vertex(b)
+        {
+            return Err(MeshError::IllegalOperation(
+                "collapse pinching the boundary",
+            ));
+        }
+        for s in [h, &t] {
+            if is_triangle(s) && s.next().twin().face() == s.prev().twin().face() {
+                return Err(MeshError::IllegalOperation(
+                    "collapse leaving a dangling edge",
+                ));
+            }
+        }
+        let around = h.fan().chain(t.fan()).collect::<Vec<_>>();
+        for s in [h, &t] {
+            if is_triangle(s) {
+                // The two other sides of the triangle fold onto each other: keep the record of the
+                // previous one, moving the outer half-edge of the next one into it.
+                let (sn, sp) = (s.next(), s.prev());
+                self.replace_half_edge(&sn.twin(), &sp);
+                self.faces[s.face().unwrap()] = None;
+                self.edges[sn.edge()] = None;
+            } else {
+                s.prev().link(&s.next());
+                if let Some(f) = s.face() {
+                    if self.face_half_edge(f) == *s {
+                        self.faces[f] = Some(s.next().downgrade());
+                    }
+                }
+            }
+        }
+        self.edges[h.edge()] = None;
+        self.vertices[b] = None;
+        let around = around
+            .into_iter()
+            .filter(|s| self.is_alive(s))
+            .collect::<Vec<_>>();
+        around.iter().for_each(|s| s.half().origin.set(a));
+        match around.iter().find(|s| s.is_boundary()).or(around.first()) {
+            Some(s) => self.set_vertex_half_edge(a, s),
+            None => self.vertices[a].as_mut().unwrap().half = None,
+        }
+        Ok(a)
+    }
+    /// Check that links are consistent and that the mesh is manifold.
+    pub fn check(&self) -> Result<(), MeshError> {
+        let mut outgoing = vec![0; self.vertices.len()];
+        let mut directed = HashSet::new();
+        for h in self.half_edges() {
+            let broken = MeshError::BrokenHalfEdge(h.id());
+            let next = HalfEdge::upgrade(h.half().next.borrow().as_ref()).ok_or(broken)?;
+            let prev = HalfEdge::upgrade(h.half().prev.borrow().as_ref()).ok_or(broken)?;
+            if !self.is_alive(&next)
+                || !self.is_alive(&prev)
+                || next.prev() != h
+                || prev.next() != h
+                || next.origin() != h.dest()
+                || next.face() != h.face()
+                || self.vertices.get(h.origin()).is_none_or(Option::is_none)
+                || h.face()
+                    .is_some_and(|f| self.faces.get(f).is_none_or(Option::is_none))
+            {
+                return Err(broken);
+            }
+            if !directed.insert((h.origin(), h.dest())) {
+                return Err(MeshError::NonManifoldEdge(h.origin(), h.dest()));
+            }
+            outgoing[h.origin()] += 1;
+        }
+        for f in self.faces() {
+            let h = HalfEdge::upgrade(self.faces[f].as_ref())
+                .filter(|h| self.is_alive(h) && h.face() == Some(f))
+                .ok_or(MeshError::InvalidFace(f))?;
+            if h.face_loop().count() < 3 {
+                return Err(MeshError::InvalidFace(f));
+            }
+        }
+        for v in self.vertices() {
+            let fan = match &self.vertices[v].as_ref().unwrap().half {
+                Some(w) => HalfEdge::upgrade(Some(w))
+                    .filter(|h| self.is_alive(h) && h.origin() == v)
+                    .ok_or(MeshError::NonManifoldVertex(v))?
+                    .fan()
+                    .collect(),
+                None => Vec::new(),
+            };
+            if fan.len() != outgoing[v] || fan.iter().filter(|h| h.is_boundary()).count() > 1 {
+                return Err(MeshError::NonManifoldVertex(v));
+            }
+        }
+        Ok(())
+    }
+}
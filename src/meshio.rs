@@ -0,0 +1,327 @@
+//! This module reads and writes polygon meshes as Wavefront OBJ, OFF and ASCII PLY text, importing them into a
+//! half-edge [`Mesh`] of shared `ORc` edges with orientation bits.
+//!
+//! Polygons are first gathered in an [`orient::Soup`](crate::orient::Soup), so that edges shared by more than
+//! two faces and non-orientable surfaces are reported as errors, while faces inconsistent with their neighbors
+//! are flipped and reported in the [`Report`].
+//! ```
+//! use ointer::meshio::{read, write, Format};
+//! let off = "OFF\n4 2 0\n0 0 0\n1 0 0\n1 1 0\n0 1 0\n3 0 1 2\n3 0 3 2\n";
+//! let (m, report) = read(Format::Off, off).unwrap();
+//! assert_eq!((m.num_faces(), report.flipped), (2, vec![1]));
+//! assert_eq!(*m.vertex(2), [1.0, 1.0, 0.0]);
+//! let obj = write(Format::Obj, &m);
+//! assert_eq!(obj, "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3\nf 1 3 4\n");
+//! ```
+
+use crate::{
+    mesh::{Mesh, MeshError},
+    orient::Soup,
+};
+use std::collections::HashMap;
+
+/// Position of a vertex.
+pub type Point = [f64; 3];
+
+/// Text format of a mesh.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// Wavefront OBJ, keeping positions and faces only.
+    Obj,
+    /// Object File Format, with its `ST`, `C` and `N` prefixed variants, keeping positions and faces only.
+    Off,
+    /// ASCII Polygon File Format.
+    Ply,
+}
+
+/// Errors of importing a mesh.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MeshIoError {
+    /// Malformed text at a line, counted from 1, or 0 at the end of the text.
+    Parse(usize, String),
+    /// Edges, by their vertices, shared by more than two faces.
+    NonManifoldEdges(Vec<[usize; 2]>),
+    /// Faces each sharing an edge with the next, and the last with the first, reversing orientation.
+    NonOrientable(Vec<usize>),
+    /// Polygons not forming a manifold mesh.
+    Mesh(MeshError),
+}
+
+impl core::fmt::Display for MeshIoError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Parse(line, s) => write!(f, "line {line}: {s}"),
+            Self::NonManifoldEdges(e) => write!(f, "non-manifold edges {e:?}"),
+            Self::NonOrientable(c) => write!(f, "non-orientable cycle of faces {c:?}"),
+            Self::Mesh(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for MeshIoError {}
+
+impl From<MeshError> for MeshIoError {
+    fn from(e: MeshError) -> Self {
+        Self::Mesh(e)
+    }
+}
+
+/// What was fixed while importing a mesh.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    /// Faces flipped to agree with the orientation of their component.
+    pub flipped: Vec<usize>,
+}
+
+/// Build a mesh from positions and polygons, orienting them consistently.
+pub fn build(
+    points: Vec<Point>,
+    polygons: &[Vec<usize>],
+) -> Result<(Mesh<Point>, Report), MeshIoError> {
+    if let Some(f) = polygons
+        .iter()
+        .position(|p| p.iter().any(|&v| v >= points.len()))
+    {
+        return Err(MeshError::InvalidFace(f).into());
+    }
+    let mut soup = Soup::from_polygons(polygons)?;
+    let non_manifold: Vec<_> = (0..soup.num_edges())
+        .filter(|&e| soup.edge_faces(e).len() > 2)
+        .map(|e| {
+            let f = soup.edge_faces(e)[0];
+            soup.face(f)
+                .iter()
+                .find(|r| r.id() == e)
+                .unwrap()
+                .vertices()
+        })
+        .collect();
+    if !non_manifold.is_empty() {
+        return Err(MeshIoError::NonManifoldEdges(non_manifold));
+    }
+    if let Some(cycle) = soup.orient().into_iter().find_map(|c| c.cycle) {
+        return Err(MeshIoError::NonOrientable(cycle));
+    }
+    let oriented = soup.polygons();
+    let flipped = (0..polygons.len())
+        .filter(|&f| oriented[f] != polygons[f])
+        .collect();
+    Ok((Mesh::from_polygons(points, &oriented)?, Report { flipped }))
+}
+
+/// Read a mesh from `text` in format `format`.
+pub fn read(format: Format, text: &str) -> Result<(Mesh<Point>, Report), MeshIoError> {
+    let (points, polygons) = match format {
+        Format::Obj => parse_obj(text)?,
+        Format::Off => parse_off(text)?,
+        Format::Ply => parse_ply(text)?,
+    };
+    build(points, &polygons)
+}
+
+/// Write mesh `m` in format `format`, with its vertices renumbered densely in order.
+pub fn write(format: Format, m: &Mesh<Point>) -> String {
+    let index: HashMap<_, _> = m.vertices().enumerate().map(|(i, v)| (v, i)).collect();
+    let faces: Vec<Vec<usize>> = m
+        .faces()
+        .map(|f| m.face_vertices(f).map(|v| index[&v]).collect())
+        .collect();
+    let point = |v| {
+        let [x, y, z] = m.vertex(v);
+        format!("{x} {y} {z}\n")
+    };
+    let mut s = String::new();
+    match format {
+        Format::Obj => {
+            m.vertices().for_each(|v| s += &format!("v {}", point(v)));
+            for f in &faces {
+                let f: Vec<_> = f.iter().map(|i| (i + 1).to_string()).collect();
+                s += &format!("f {}\n", f.join(" "));
+            }
+        }
+        Format::Off => {
+            s += &format!("OFF\n{} {} {}\n", index.len(), faces.len(), m.num_edges());
+            m.vertices().for_each(|v| s += &point(v));
+        }
+        Format::Ply => {
+            s += &format!(
+                "ply\nformat ascii 1.0\nelement vertex {}\nproperty double x\nproperty double y\n\
+                 property double z\nelement face {}\nproperty list uchar int vertex_indices\nend_header\n",
+                index.len(),
+                faces.len()
+            );
+            m.vertices().for_each(|v| s += &point(v));
+        }
+    }
+    if format != Format::Obj {
+        for f in &faces {
+            let f: Vec<_> = f.iter().map(usize::to_string).collect();
+            s += &format!("{} {}\n", f.len(), f.join(" "));
+        }
+    }
+    s
+}
+
+/// Lines with their numbers, without comments and blank lines.
+fn lines(text: &str) -> impl Iterator<Item = (usize, Vec<&str>)> {
+    text.lines().enumerate().filter_map(|(i, l)| {
+        let l = l.split('#').next().unwrap();
+        let t: Vec<_> = l.split_whitespace().collect();
+        (!t.is_empty()).then_some((i + 1, t))
+    })
+}
+
+fn number<T: core::str::FromStr>(line: usize, t: &str) -> Result<T, MeshIoError> {
+    t.parse()
+        .map_err(|_| MeshIoError::Parse(line, format!("bad number {t:?}")))
+}
+
+fn point(line: usize, t: &[&str]) -> Result<Point, MeshIoError> {
+    match t {
+        [x, y, z, ..] => Ok([number(line, x)?, number(line, y)?, number(line, z)?]),
+        _ => Err(MeshIoError::Parse(line, "missing coordinates".into())),
+    }
+}
+
+type Parsed = (Vec<Point>, Vec<Vec<usize>>);
+
+/// Name of a PLY property, telling whether it is a list.
+type Property<'a> = (&'a str, bool);
+
+fn next_line<'a>(
+    lines: &mut impl Iterator<Item = (usize, Vec<&'a str>)>,
+) -> Result<(usize, Vec<&'a str>), MeshIoError> {
+    lines
+        .next()
+        .ok_or(MeshIoError::Parse(0, "unexpected end of file".into()))
+}
+
+fn parse_obj(text: &str) -> Result<Parsed, MeshIoError> {
+    let (mut points, mut polygons) = (Vec::new(), Vec::new());
+    for (line, t) in lines(text) {
+        match t[0] {
+            "v" => points.push(point(line, &t[1..])?),
+            "f" => {
+                let face = t[1..]
+                    .iter()
+                    .map(|r| {
+                        // Texture and normal references after slashes are ignored.
+                        let i: isize = number(line, r.split('/').next().unwrap())?;
+                        let v = match i {
+                            1.. => i as usize - 1,
+                            ..=-1 => points.len().wrapping_sub(i.unsigned_abs()),
+                            0 => usize::MAX,
+                        };
+                        match v < points.len() {
+                            true => Ok(v),
+                            false => Err(MeshIoError::Parse(line, format!("bad vertex {i}"))),
+                        }
+                    })
+                    .collect::<Result<_, _>>()?;
+                polygons.push(face);
+            }
+            _ => {}
+        }
+    }
+    Ok((points, polygons))
+}
+
+fn parse_off(text: &str) -> Result<Parsed, MeshIoError> {
+    let mut lines = lines(text);
+    let (mut line, mut t) = next_line(&mut lines)?;
+    // Texture coordinates, colors and normals of `STOFF`, `COFF`, `NOFF` and their mixes follow the positions
+    // and are ignored, as are face colors; `4OFF` and `nOFF` of other dimensions are not supported.
+    if let Some(prefix) = t[0].strip_suffix("OFF") {
+        if !prefix.chars().all(|c| "STCN".contains(c)) {
+            return Err(MeshIoError::Parse(line, format!("unsupported {:?}", t[0])));
+        }
+        t.remove(0);
+        if t.is_empty() {
+            (line, t) = next_line(&mut lines)?;
+        }
+    }
+    let (nv, nf) = match t[..] {
+        [nv, nf, ..] => (number(line, nv)?, number(line, nf)?),
+        _ => return Err(MeshIoError::Parse(line, "missing counts".into())),
+    };
+    let points = (0..nv)
+        .map(|_| next_line(&mut lines).and_then(|(line, t)| point(line, &t)))
+        .collect::<Result<_, _>>()?;
+    let polygons = (0..nf)
+        .map(|_| next_line(&mut lines).and_then(|(line, t)| polygon(line, &t)))
+        .collect::<Result<_, _>>()?;
+    Ok((points, polygons))
+}
+
+/// Parse a count followed by as many vertices, ignoring anything after them.
+fn polygon(line: usize, t: &[&str]) -> Result<Vec<usize>, MeshIoError> {
+    let n: usize = number(line, t[0])?;
+    match t.get(1..=n) {
+        Some(t) => t.iter().map(|v| number(line, v)).collect(),
+        None => Err(MeshIoError::Parse(line, "missing vertices".into())),
+    }
+}
+
+fn parse_ply(text: &str) -> Result<Parsed, MeshIoError> {
+    let mut lines = lines(text).filter(|(_, t)| !matches!(t[0], "comment" | "obj_info"));
+    if next_line(&mut lines)?.1 != ["ply"] {
+        return Err(MeshIoError::Parse(1, "not a PLY file".into()));
+    }
+    let (line, t) = next_line(&mut lines)?;
+    if t.get(..2) != Some(&["format", "ascii"]) {
+        return Err(MeshIoError::Parse(line, "not an ASCII PLY file".into()));
+    }
+    // Elements with their counts and properties.
+    let mut elements: Vec<(&str, usize, Vec<Property>)> = Vec::new();
+    loop {
+        let (line, t) = next_line(&mut lines)?;
+        let property = match t[..] {
+            ["end_header"] => break,
+            ["element", name, n] => {
+                elements.push((name, number(line, n)?, Vec::new()));
+                continue;
+            }
+            ["property", "list", _, _, name] => (name, true),
+            ["property", _, name] => (name, false),
+            _ => return Err(MeshIoError::Parse(line, "bad header".into())),
+        };
+        match elements.last_mut() {
+            Some(e) => e.2.push(property),
+            None => return Err(MeshIoError::Parse(line, "property out of element".into())),
+        }
+    }
+    let (mut points, mut polygons) = (Vec::new(), Vec::new());
+    for (name, n, properties) in elements {
+        for _ in 0..n {
+            let (line, t) = next_line(&mut lines)?;
+            match name {
+                "vertex" => {
+                    let at = |p| match properties.iter().position(|&(q, _)| q == p) {
+                        Some(i) if i < t.len() => number(line, t[i]),
+                        _ => Err(MeshIoError::Parse(line, format!("missing {p}"))),
+                    };
+                    points.push([at("x")?, at("y")?, at("z")?]);
+                }
+                "face" => {
+                    // Skip the properties before the vertex list, each list by its count.
+                    let mut i = 0;
+                    for &(p, list) in &properties {
+                        if list && matches!(p, "vertex_indices" | "vertex_index") {
+                            break;
+                        }
+                        i += match (list, t.get(i)) {
+                            (true, Some(n)) => 1 + number::<usize>(line, n)?,
+                            _ => 1,
+                        };
+                    }
+                    match t.get(i..) {
+                        Some(t) if !t.is_empty() => polygons.push(polygon(line, t)?),
+                        _ => return Err(MeshIoError::Parse(line, "missing vertices".into())),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok((points, polygons))
+}
@@ -0,0 +1,390 @@
+//! This module defines the encoding used by `define_nan_box!`, and `NanBox`, a NaN-boxed dynamic value defined by it.
+//!
+//! A NaN box is a single `u64` holding either an `f64` verbatim, with NaNs made canonical, or a boxed variant
+//! in the space of negative quiet NaNs: the sign bit and the quiet bit set, a 3-bit variant tag from `1` to `7`
+//! in bits 48 to 50, and the encoded word of an [`EnumOinterPayload`] in the low 48 bits. The stolen bits of a
+//! payload which is itself an `ointer` are moved down right below bit 48, so its address must fit in the bits left.
+//! ```
+//! use ointer::nanbox::NanBox;
+//! use ointer::rc::ORc;
+//! let mut o = ORc::new(String::from("object"));
+//! o.flip();
+//! let v = NanBox::new_object(o);
+//! let w = v.clone();
+//! assert_eq!(w.map_enum(|_| 0, |_| 0, |_| 0, |_| 0, |o| o.len() + o.o() as usize), 7);
+//! assert_eq!(NanBox::<String>::new_float(0.25).as_float(), Some(0.25));
+//! assert_eq!(NanBox::<String>::new_int(-1).get_usize(), NanBox::<String>::INT as usize);
+//! ```
+
+use crate::ointer::*;
+use core::mem::ManuallyDrop;
+
+/// Bits set in every boxed variant: the sign bit and the quiet NaN bits.
+const BOXED: u64 = 0xfff8_0000_0000_0000;
+/// Canonical NaN, which is positive and so never taken for a boxed variant.
+const CANONICAL_NAN: u64 = 0x7ff8_0000_0000_0000;
+
+/// Number of low bits holding the payload of a boxed variant.
+pub const PAYLOAD_BITS: usize = 48;
+/// Largest tag of a boxed variant, `0` being the tag of `f64`s.
+pub const MAX_TAG: u64 = 7;
+
+/// Encode `x` verbatim, or as the canonical NaN if it is a NaN.
+#[inline(always)]
+pub fn float_bits(x: f64) -> u64 {
+    if x.is_nan() {
+        CANONICAL_NAN
+    } else {
+        x.to_bits()
+    }
+}
+
+/// Get the tag of a NaN box `w`, `0` for an `f64`.
+#[inline(always)]
+pub fn tag(w: u64) -> u64 {
+    if w & BOXED == BOXED {
+        (w >> PAYLOAD_BITS) & MAX_TAG
+    } else {
+        0
+    }
+}
+
+/// Check at compile time that payload `P` fits in the payload bits of a NaN box.
+pub const fn payload_fits<P: EnumOinterPayload>() -> bool {
+    match P::INLINE_BITS {
+        Some(bits) => bits < PAYLOAD_BITS,
+        None => P::TAG_OFFSET + P::TAG_BITS < PAYLOAD_BITS,
+    }
+}
+
+/// Number of high bits of the word of `P` kept right below bit 48.
+const fn kept_bits<P: EnumOinterPayload>() -> u32 {
+    (P::TAG_OFFSET + P::TAG_BITS) as u32
+}
+
+/// Pack the word `u` of payload `P` with `tag` into a NaN box, or `None` if the word of a pointer does not
+/// fit in the payload bits.
+#[inline(always)]
+pub fn try_pack<P: EnumOinterPayload>(tag: u64, u: usize) -> Option<u64> {
+    let k = kept_bits::<P>();
+    let u = u as u64;
+    let low = u & ((1 << (PAYLOAD_BITS as u32 - k)) - 1);
+    let high = u.checked_shr(64 - k).unwrap_or(0);
+    (low | high.checked_shl(64 - k).unwrap_or(0) == u)
+        .then_some(BOXED | (tag << PAYLOAD_BITS) | (high << (PAYLOAD_BITS as u32 - k)) | low)
+}
+
+/// Pack payload `p` with `tag` into a NaN box.
+///
+/// # Panics
+/// Panics if the word of a pointer does not fit in the payload bits, after dropping `p`.
+#[inline(always)]
+pub fn pack<P: EnumOinterPayload>(tag: u64, p: P) -> u64 {
+    let u = p.into_word();
+    match try_pack::<P>(tag, u) {
+        Some(w) => w,
+        None => {
+            drop(unsafe { P::from_word(u) });
+            panic!("Payload out of the NaN space")
+        }
+    }
+}
+
+/// Map the payload `P` of a NaN box `*w` mutably by fn `f`, then pack it back with `tag`, even if `f` panics.
+///
+/// # Safety
+/// `*w` must hold a payload `P` tagged `tag`.
+///
+/// # Panics
+/// Panics if the word of the payload no longer fits in the payload bits, after dropping it and leaving the
+/// canonical NaN in `*w`, which is left there also when `f` panics with such a payload.
+#[inline(always)]
+pub unsafe fn map_payload_mut<P: EnumOinterPayload, R>(
+    w: &mut u64,
+    tag: u64,
+    f: impl FnOnce(&mut P) -> R,
+) -> R {
+    struct Repack<'a, P: EnumOinterPayload> {
+        w: &'a mut u64,
+        tag: u64,
+        p: ManuallyDrop<P>,
+    }
+    impl<P: EnumOinterPayload> Drop for Repack<'_, P> {
+        fn drop(&mut self) {
+            let u = unsafe { ManuallyDrop::take(&mut self.p) }.into_word();
+            *self.w = try_pack::<P>(self.tag, u).unwrap_or_else(|| {
+                drop(unsafe { P::from_word(u) });
+                CANONICAL_NAN
+            });
+        }
+    }
+    let p = ManuallyDrop::new(P::from_word(unpack::<P>(*w)));
+    let mut repack = Repack { w: &mut *w, tag, p };
+    let r = f(&mut repack.p);
+    drop(repack);
+    assert!(*w != CANONICAL_NAN, "Payload out of the NaN space");
+    r
+}
+
+/// Unpack the word of payload `P` from a NaN box `w`.
+#[inline(always)]
+pub fn unpack<P: EnumOinterPayload>(w: u64) -> usize {
+    let k = kept_bits::<P>();
+    let e = w & ((1 << PAYLOAD_BITS) - 1);
+    let low = e & ((1 << (PAYLOAD_BITS as u32 - k)) - 1);
+    let high = e >> (PAYLOAD_BITS as u32 - k);
+    (low | high.checked_shl(64 - k).unwrap_or(0)) as usize
+}
+
+/// Define a NaN-boxed enum, holding either an `f64` or one of up to `7` boxed variants.
+///
+/// Variants are declared like those of a Rust `enum`, with or without an [`EnumOinterPayload`], and
+/// numbered from `1`, exposed as associated constants in upper snake case, while `FLOAT` is `0`. The `f64`
+/// variant is implicit, created by `new_float`, and mapped by the first fn of `map_enum` and `map_enum_mut`.
+/// `Clone` and `Debug` are implemented variant-wise, and payloads are dropped along with the box.
+/// ```
+/// use ointer::define_nan_box;
+/// use std::rc::Rc;
+/// define_nan_box! {
+///     /// A script value.
+///     pub enum Value {
+///         Nil,
+///         Int(i32),
+///         Str(Rc<String>),
+///     }
+/// }
+/// let s = Value::new_str(Rc::new("s".to_owned()));
+/// let t = s.clone();
+/// assert_eq!(s.map_enum(|_| 0, |_| 0, |_| 0, |s| Rc::strong_count(s)), 2);
+/// drop(s);
+/// assert_eq!(t.map_enum(|_| 0, |_| 0, |_| 0, |s| Rc::strong_count(s)), 1);
+/// let mut i = Value::new_int(1);
+/// i.map_enum_mut(|_| (), |_| (), |i| *i += 1, |_| ());
+/// assert_eq!(format!("{:?}", [i, Value::new_float(0.5), Value::new_nil()]), "[Int(2), Float(0.5), Nil]");
+/// ```
+///
+/// At most `7` variants fit in the tag, and payloads must fit in the 48 payload bits, which are both
+/// checked at compile time:
+/// ```compile_fail
+/// use ointer::define_nan_box;
+/// define_nan_box! {
+///     enum TooMany { A, B, C, D, E, F, G, H }
+/// }
+/// ```
+#[macro_export]
+macro_rules! define_nan_box {
+    (@payload) => { () };
+    (@payload $payload:ty) => { $payload };
+    (@new $variant:ident, $new:ident, $tag:ident) => {
+        #[doc = concat!("Create the `", stringify!($variant), "` variant.")]
+        #[inline(always)]
+        pub fn $new() -> Self {
+            Self::from_payload(Self::$tag, ())
+        }
+    };
+    (@new $variant:ident, $new:ident, $tag:ident, $payload:ty) => {
+        #[doc = concat!("Create the `", stringify!($variant), "` variant holding `p`.")]
+        #[inline(always)]
+        pub fn $new(p: $payload) -> Self {
+            Self::from_payload(Self::$tag, p)
+        }
+    };
+    (@debug $f:ident, $p:ident, $variant:ident) => {{
+        let _ = $p;
+        $f.write_str(stringify!($variant))
+    }};
+    (@debug $f:ident, $p:ident, $variant:ident, $payload:ty) => {
+        $f.debug_tuple(stringify!($variant)).field($p).finish()
+    };
+    (
+        $(#[$attr:meta])*
+        $vis:vis enum $name:ident $(<$($param:ident),+ $(,)?>)? {
+            $($variant:ident $(($payload:ty))?),* $(,)?
+        }
+    ) => {
+        $crate::paste::paste! {
+            $(#[$attr])*
+            #[repr(transparent)]
+            $vis struct $name<$($($param),+)?>(
+                u64,
+                core::marker::PhantomData<($($crate::define_nan_box!(@payload $($payload)?),)*)>,
+            );
+
+            const _: () = {
+                #[allow(dead_code)]
+                #[repr(u64)]
+                enum Tag {
+                    Float,
+                    $($variant),*
+                }
+
+                assert!(
+                    $(Tag::$variant as u64 <= $crate::nanbox::MAX_TAG &&)* true,
+                    "Too many variants for a NaN box"
+                );
+
+                #[allow(dead_code)]
+                impl<$($($param),+)?> $name<$($($param),+)?> {
+                    /// Tag of the `f64` variant.
+                    pub const FLOAT: u64 = 0;
+                    $(
+                        #[doc = concat!("Tag of the `", stringify!($variant), "` variant.")]
+                        pub const [<$variant:snake:upper>]: u64 = Tag::$variant as u64;
+                    )*
+                    const PAYLOADS_FIT: () = assert!(
+                        $($crate::nanbox::payload_fits::<
+                            $crate::define_nan_box!(@payload $($payload)?)
+                        >() &&)* true,
+                        "Payload out of the NaN space"
+                    );
+
+                    #[inline(always)]
+                    fn from_payload<P: $crate::EnumOinterPayload>(tag: u64, p: P) -> Self {
+                        #[allow(clippy::let_unit_value)]
+                        let _ = Self::PAYLOADS_FIT;
+                        Self($crate::nanbox::pack(tag, p), core::marker::PhantomData)
+                    }
+                    /// Create the `f64` variant holding `x`, with NaNs made canonical.
+                    #[inline(always)]
+                    pub fn new_float(x: f64) -> Self {
+                        Self($crate::nanbox::float_bits(x), core::marker::PhantomData)
+                    }
+                    $(
+                        $crate::define_nan_box!(
+                            @new $variant, [<new_ $variant:snake>], [<$variant:snake:upper>]
+                            $(, $payload)?
+                        );
+                    )*
+                    /// Get the tag, `0` for an `f64`.
+                    #[inline(always)]
+                    pub fn get_usize(&self) -> usize {
+                        $crate::nanbox::tag(self.0) as usize
+                    }
+                    /// Whether `self` holds an `f64`.
+                    #[inline(always)]
+                    pub fn is_float(&self) -> bool {
+                        self.get_usize() == 0
+                    }
+                    /// Get the `f64` held by `self`, if any.
+                    #[inline(always)]
+                    pub fn as_float(&self) -> Option<f64> {
+                        self.is_float().then(|| f64::from_bits(self.0))
+                    }
+                    /// Get the raw bits.
+                    #[inline(always)]
+                    pub fn to_bits(&self) -> u64 {
+                        self.0
+                    }
+                    /// Map the `f64` or the payload of `self` by the fn of its variant.
+                    #[inline(always)]
+                    pub fn map_enum<R>(
+                        &self,
+                        float: impl FnOnce(f64) -> R,
+                        $([<$variant:snake>]: impl FnOnce(
+                            &$crate::define_nan_box!(@payload $($payload)?)
+                        ) -> R),*
+                    ) -> R {
+                        match $crate::nanbox::tag(self.0) {
+                            0 => float(f64::from_bits(self.0)),
+                            $(Self::[<$variant:snake:upper>] => {
+                                let u = $crate::nanbox::unpack::<$crate::define_nan_box!(@payload $($payload)?)>(self.0);
+                                unsafe { $crate::map_enum_payload(u, |p: &$crate::define_nan_box!(@payload $($payload)?)| [<$variant:snake>](p)) }
+                            })*
+                            _ => unreachable!(),
+                        }
+                    }
+                    /// Map the `f64` or the payload of `self` mutably by the fn of its variant, then store changes back.
+                    #[inline(always)]
+                    pub fn map_enum_mut<R>(
+                        &mut self,
+                        float: impl FnOnce(&mut f64) -> R,
+                        $([<$variant:snake>]: impl FnOnce(
+                            &mut $crate::define_nan_box!(@payload $($payload)?)
+                        ) -> R),*
+                    ) -> R {
+                        match $crate::nanbox::tag(self.0) {
+                            0 => {
+                                let mut x = f64::from_bits(self.0);
+                                let r = float(&mut x);
+                                self.0 = $crate::nanbox::float_bits(x);
+                                r
+                            }
+                            $(Self::[<$variant:snake:upper>] => {
+                                unsafe {
+                                    $crate::nanbox::map_payload_mut(
+                                        &mut self.0,
+                                        Self::[<$variant:snake:upper>],
+                                        |p: &mut $crate::define_nan_box!(@payload $($payload)?)| [<$variant:snake>](p),
+                                    )
+                                }
+                            })*
+                            _ => unreachable!(),
+                        }
+                    }
+                }
+
+                impl<$($($param),+)?> Clone for $name<$($($param),+)?>
+                where
+                    $($crate::define_nan_box!(@payload $($payload)?): Clone,)*
+                {
+                    fn clone(&self) -> Self {
+                        match $crate::nanbox::tag(self.0) {
+                            0 => Self(self.0, core::marker::PhantomData),
+                            $(Self::[<$variant:snake:upper>] => {
+                                let u = $crate::nanbox::unpack::<$crate::define_nan_box!(@payload $($payload)?)>(self.0);
+                                let p = unsafe { $crate::map_enum_payload(u, |p: &$crate::define_nan_box!(@payload $($payload)?)| p.clone()) };
+                                Self::from_payload(Self::[<$variant:snake:upper>], p)
+                            })*
+                            _ => unreachable!(),
+                        }
+                    }
+                }
+
+                impl<$($($param),+)?> Drop for $name<$($($param),+)?> {
+                    fn drop(&mut self) {
+                        match $crate::nanbox::tag(self.0) {
+                            0 => {}
+                            $(Self::[<$variant:snake:upper>] => {
+                                let u = $crate::nanbox::unpack::<$crate::define_nan_box!(@payload $($payload)?)>(self.0);
+                                let _ = unsafe {
+                                    <$crate::define_nan_box!(@payload $($payload)?)
+                                        as $crate::EnumOinterPayload>::from_word(u)
+                                };
+                            })*
+                            _ => unreachable!(),
+                        }
+                    }
+                }
+
+                impl<$($($param),+)?> core::fmt::Debug for $name<$($($param),+)?>
+                where
+                    $($crate::define_nan_box!(@payload $($payload)?): core::fmt::Debug,)*
+                {
+                    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+                        match $crate::nanbox::tag(self.0) {
+                            0 => f.debug_tuple("Float").field(&f64::from_bits(self.0)).finish(),
+                            $(Self::[<$variant:snake:upper>] => {
+                                let u = $crate::nanbox::unpack::<$crate::define_nan_box!(@payload $($payload)?)>(self.0);
+                                let g = |p: &$crate::define_nan_box!(@payload $($payload)?)| {
+                                    $crate::define_nan_box!(@debug f, p, $variant $(, $payload)?)
+                                };
+                                unsafe { $crate::map_enum_payload(u, g) }
+                            })*
+                            _ => unreachable!(),
+                        }
+                    }
+                }
+            };
+        }
+    };
+}
+
+define_nan_box! {
+    /// NaN-boxed dynamic value, holding an `f64`, nil, a bool, a small integer, or an object behind an `ORc`.
+    pub enum NanBox<T> {
+        Nil,
+        Bool(bool),
+        Int(i32),
+        Object(crate::rc::ORc<T>),
+    }
+}
@@ -0,0 +1,234 @@
+//! This module defines `Graph`, a directed or undirected graph storing each edge once as an [`EdgeRecord`]
+//! behind an `ORc`, whose stolen bit is the direction of the edge, and referring to it from both endpoints by
+//! `OWeak`s, whose stolen bits tell which end they are at.
+//!
+//! A reference to a record with bit `b` goes from `ends()[b]` to the other end, so that an edge is reversed by
+//! a single `flip()`, and since the graph alone holds strong references, removing edges or dropping the graph
+//! frees them all.
+//! ```
+//! use ointer::ograph::Graph;
+//! let mut g = Graph::new_directed();
+//! let (a, b, c) = (g.add_vertex('a'), g.add_vertex('b'), g.add_vertex('c'));
+//! let ab = g.add_edge(a, b, 1);
+//! g.add_edge(b, c, 2);
+//! assert_eq!(g.bfs(a), [a, b, c]);
+//! g.reverse_edge(ab);
+//! assert_eq!((g.endpoints(ab), g.bfs(a)), ((b, a), vec![a]));
+//! assert_eq!(g.neighbors(b).collect::<Vec<_>>(), [(ab, a), (1, c)]);
+//! ```
+
+use crate::rc::{ORc, OWeak};
+
+/// Edge between two vertices, shared by the references from both.
+#[derive(Debug)]
+pub struct EdgeRecord {
+    id: usize,
+    ends: [usize; 2],
+}
+
+impl EdgeRecord {
+    /// Get the index of the edge.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+    /// Get the vertices it was added between, in order.
+    pub fn ends(&self) -> [usize; 2] {
+        self.ends
+    }
+}
+
+struct Vertex<V> {
+    data: V,
+    adjacency: Vec<OWeak<EdgeRecord>>,
+}
+
+struct Edge<E> {
+    record: ORc<EdgeRecord>,
+    data: E,
+}
+
+/// Graph with data `V` on vertices and `E` on edges.
+///
+/// Vertices and edges are numbered in order of creation, and numbers of removed ones are not reused.
+pub struct Graph<V, E> {
+    directed: bool,
+    vertices: Vec<Option<Vertex<V>>>,
+    edges: Vec<Option<Edge<E>>>,
+}
+
+impl<V, E> Graph<V, E> {
+    /// Make an empty directed graph.
+    pub const fn new_directed() -> Self {
+        Self {
+            directed: true,
+            vertices: Vec::new(),
+            edges: Vec::new(),
+        }
+    }
+    /// Make an empty undirected graph.
+    pub const fn new_undirected() -> Self {
+        Self {
+            directed: false,
+            vertices: Vec::new(),
+            edges: Vec::new(),
+        }
+    }
+    /// Whether edges are directed.
+    pub fn is_directed(&self) -> bool {
+        self.directed
+    }
+    fn vertex_ref(&self, v: usize) -> &Vertex<V> {
+        self.vertices[v].as_ref().expect("Removed vertex")
+    }
+    fn vertex_ref_mut(&mut self, v: usize) -> &mut Vertex<V> {
+        self.vertices[v].as_mut().expect("Removed vertex")
+    }
+    fn edge_ref(&self, e: usize) -> &Edge<E> {
+        self.edges[e].as_ref().expect("Removed edge")
+    }
+    /// Add a vertex holding `data`, returning its index.
+    pub fn add_vertex(&mut self, data: V) -> usize {
+        self.vertices.push(Some(Vertex {
+            data,
+            adjacency: Vec::new(),
+        }));
+        self.vertices.len() - 1
+    }
+    /// Add an edge from vertex `a` to vertex `b` holding `data`, returning its index.
+    pub fn add_edge(&mut self, a: usize, b: usize, data: E) -> usize {
+        let record = ORc::new(EdgeRecord {
+            id: self.edges.len(),
+            ends: [a, b],
+        });
+        self.vertex_ref(b);
+        self.vertex_ref_mut(a).adjacency.push(record.downgrade());
+        self.vertex_ref_mut(b)
+            .adjacency
+            .push(record.clone_and_flip().downgrade());
+        self.edges.push(Some(Edge { record, data }));
+        self.edges.len() - 1
+    }
+    /// Get the data of vertex `v`.
+    pub fn vertex(&self, v: usize) -> &V {
+        &self.vertex_ref(v).data
+    }
+    /// Get the data of vertex `v` mutably.
+    pub fn vertex_mut(&mut self, v: usize) -> &mut V {
+        &mut self.vertex_ref_mut(v).data
+    }
+    /// Get the data of edge `e`.
+    pub fn edge(&self, e: usize) -> &E {
+        &self.edge_ref(e).data
+    }
+    /// Get the data of edge `e` mutably.
+    pub fn edge_mut(&mut self, e: usize) -> &mut E {
+        &mut self.edges[e].as_mut().expect("Removed edge").data
+    }
+    /// Get a weak reference to edge `e`, directed as it is.
+    pub fn edge_record(&self, e: usize) -> OWeak<EdgeRecord> {
+        self.edge_ref(e).record.downgrade()
+    }
+    /// Get the source and the target of edge `e`.
+    pub fn endpoints(&self, e: usize) -> (usize, usize) {
+        let r = &self.edge_ref(e).record;
+        (r.ends[usize::from(r.o())], r.ends[usize::from(!r.o())])
+    }
+    /// Reverse edge `e`, flipping the bit of its reference.
+    pub fn reverse_edge(&mut self, e: usize) {
+        self.edges[e].as_mut().expect("Removed edge").record.flip();
+    }
+    /// Get the number of vertices.
+    pub fn num_vertices(&self) -> usize {
+        self.vertices.iter().flatten().count()
+    }
+    /// Get the number of edges.
+    pub fn num_edges(&self) -> usize {
+        self.edges.iter().flatten().count()
+    }
+    /// Iterate over the indices of vertices.
+    pub fn vertices(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.vertices.len()).filter(|&v| self.vertices[v].is_some())
+    }
+    /// Iterate over the indices of edges.
+    pub fn edges(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.edges.len()).filter(|&e| self.edges[e].is_some())
+    }
+    /// Iterate over the edges at vertex `v` with the other end, and whether they go out of `v`.
+    fn incident(&self, v: usize) -> impl Iterator<Item = (usize, usize, bool)> + '_ {
+        self.vertex_ref(v).adjacency.iter().map(move |w| {
+            let r = w.upgrade().expect("Dangling edge");
+            let out = r.o() == self.edge_ref(r.id).record.o();
+            (r.id, r.ends[usize::from(!r.o())], out)
+        })
+    }
+    /// Iterate over the edges from vertex `v` with their other ends, in order of addition, all of them if
+    /// undirected.
+    pub fn neighbors(&self, v: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.incident(v)
+            .filter(|&(_, _, out)| out || !self.directed)
+            .map(|(e, w, _)| (e, w))
+    }
+    /// Iterate over the edges to vertex `v` with their other ends, in order of addition, all of them if
+    /// undirected.
+    pub fn in_neighbors(&self, v: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.incident(v)
+            .filter(|&(_, _, out)| !out || !self.directed)
+            .map(|(e, w, _)| (e, w))
+    }
+    /// Get the number of edges at vertex `v`, counting loops twice.
+    pub fn degree(&self, v: usize) -> usize {
+        self.vertex_ref(v).adjacency.len()
+    }
+    /// Remove edge `e`, returning its data.
+    pub fn remove_edge(&mut self, e: usize) -> E {
+        let edge = self.edges[e].take().expect("Removed edge");
+        for v in edge.record.ends {
+            self.vertex_ref_mut(v)
+                .adjacency
+                .retain(|w| w.upgrade().is_some_and(|r| r.id != e));
+        }
+        edge.data
+    }
+    /// Remove vertex `v` with its edges, returning its data.
+    pub fn remove_vertex(&mut self, v: usize) -> V {
+        let edges: Vec<_> = self.incident(v).map(|(e, _, _)| e).collect();
+        for e in edges {
+            if self.edges[e].is_some() {
+                self.remove_edge(e);
+            }
+        }
+        self.vertices[v].take().expect("Removed vertex").data
+    }
+    /// Get the vertices reachable from vertex `v` in breadth first order.
+    pub fn bfs(&self, v: usize) -> Vec<usize> {
+        let mut seen = vec![false; self.vertices.len()];
+        seen[v] = true;
+        let mut order = vec![v];
+        let mut i = 0;
+        while let Some(&u) = order.get(i) {
+            i += 1;
+            for (_, w) in self.neighbors(u) {
+                if !seen[w] {
+                    seen[w] = true;
+                    order.push(w);
+                }
+            }
+        }
+        order
+    }
+    /// Get the vertices reachable from vertex `v` in depth first preorder.
+    pub fn dfs(&self, v: usize) -> Vec<usize> {
+        let mut seen = vec![false; self.vertices.len()];
+        let mut order = Vec::new();
+        let mut stack = vec![v];
+        while let Some(u) = stack.pop() {
+            if !seen[u] {
+                seen[u] = true;
+                order.push(u);
+                let next: Vec<_> = self.neighbors(u).map(|(_, w)| w).collect();
+                stack.extend(next.into_iter().rev().filter(|&w| !seen[w]));
+            }
+        }
+        order
+    }
+}
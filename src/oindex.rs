@@ -0,0 +1,276 @@
+//! This module defines `OIndex`, an `ointer` over an integer index instead of a pointer, and `OArena`, a
+//! collection handing out typed `OIndex`es whose stolen bits hold a generation counter to detect stale handles.
+//!
+//! ```
+//! use ointer::{OArena, OIndex, Ointer};
+//! let mut i = OIndex::<(), 2>::new(7);
+//! i.set_mut(3u8);
+//! assert_eq!(i.map(|x: u8, p| (x, *p)), (3, 7));
+//! assert_eq!((i.index(), i.get_usize()), (7, 3));
+//! ```
+
+use crate::ointer::*;
+use core::{
+    marker::PhantomData,
+    ops::{Index, IndexMut},
+};
+
+/// Integer index with high `N` bits stolen, tied to the elements `T` of an [`OArena`], or untyped with `T = ()`.
+#[repr(transparent)]
+pub struct OIndex<T: ?Sized = (), const N: usize = 0>(usize, PhantomData<fn() -> *const T>);
+
+unsafe impl<T: ?Sized, const N: usize> Ointer<N> for OIndex<T, N> {
+    type Pointer = usize;
+}
+
+impl<T: ?Sized, const N: usize> OIndex<T, N> {
+    /// Make index `i` with high `N` bits all `0`.
+    pub fn new(i: usize) -> Self {
+        let mut o = Self(i, PhantomData);
+        o.steal();
+        o
+    }
+    /// Get the index without the stolen bits.
+    pub fn index(&self) -> usize {
+        self.get_ptr_as_usize()
+    }
+    /// Get the stolen bits, `0` if there are none.
+    fn tag(&self) -> usize {
+        match N {
+            0 => 0,
+            _ => self.get_usize(),
+        }
+    }
+    /// Cast to an index of elements `U`, keeping the stolen bits.
+    pub fn cast<U: ?Sized>(self) -> OIndex<U, N> {
+        OIndex(self.0, PhantomData)
+    }
+}
+
+impl<T: ?Sized, const N: usize> From<usize> for OIndex<T, N> {
+    fn from(i: usize) -> Self {
+        Self::new(i)
+    }
+}
+
+impl<T: ?Sized, const N: usize> Default for OIndex<T, N> {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl<T: ?Sized, const N: usize> Clone for OIndex<T, N> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized, const N: usize> Copy for OIndex<T, N> {}
+
+impl<T: ?Sized, const N: usize> core::fmt::Debug for OIndex<T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+        (self.tag(), self.index()).fmt(f)
+    }
+}
+
+impl<T: ?Sized, const N: usize> core::hash::Hash for OIndex<T, N> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        (self.tag(), self.index()).hash(state)
+    }
+}
+
+impl<T: ?Sized, const N: usize> PartialEq for OIndex<T, N> {
+    fn eq(&self, rhs: &Self) -> bool {
+        self.0 == rhs.0
+    }
+}
+
+impl<T: ?Sized, const N: usize> Eq for OIndex<T, N> {}
+
+impl<T: ?Sized, const N: usize> PartialOrd for OIndex<T, N> {
+    fn partial_cmp(&self, rhs: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(rhs))
+    }
+}
+
+impl<T: ?Sized, const N: usize> Ord for OIndex<T, N> {
+    fn cmp(&self, rhs: &Self) -> core::cmp::Ordering {
+        (self.tag(), self.index()).cmp(&(rhs.tag(), rhs.index()))
+    }
+}
+
+impl<T: ?Sized> OIndex<T, 1> {
+    /// Get first bit and cast as bool.
+    pub fn o(&self) -> bool {
+        self.get_bool()
+    }
+    /// Flip first bit.
+    pub fn flip(&mut self) {
+        self.set_bool(!self.o());
+    }
+    /// Copy and flip.
+    pub fn clone_and_flip(&self) -> Self {
+        let mut o = *self;
+        o.flip();
+        o
+    }
+}
+
+enum Slot<T> {
+    Occupied { generation: usize, value: T },
+    Vacant { generation: usize, next: usize },
+}
+
+/// Collection of `T` handing out [`OIndex`]es, with their high `N` bits holding the generation of their slot,
+/// bumped on each removal, so that a stale index no longer gets a value, unless `2^N` removals wrapped it around.
+/// With `N = 0`, indexes are not checked at all.
+/// ```
+/// use ointer::{OArena, OIndex};
+/// let mut arena = OArena::<_, 8>::new();
+/// let a: OIndex<&str, 8> = arena.insert("a");
+/// assert_eq!(arena.remove(a), Some("a"));
+/// let b = arena.insert("b");
+/// assert_eq!((a.index(), arena.get(a), arena[b]), (b.index(), None, "b"));
+/// ```
+pub struct OArena<T, const N: usize> {
+    slots: Vec<Slot<T>>,
+    free: usize,
+    len: usize,
+}
+
+impl<T, const N: usize> OArena<T, N> {
+    /// Make an empty arena.
+    pub const fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: usize::MAX,
+            len: 0,
+        }
+    }
+    /// Get the number of values.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// Whether there is no value.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    fn generation(generation: usize) -> usize {
+        match N {
+            0 => 0,
+            _ => {
+                generation
+                    & (<OIndex<T, N> as Ointer<N>>::HIGH_MASK
+                        >> <OIndex<T, N> as Ointer<N>>::SHIFT_BITS)
+            }
+        }
+    }
+    fn tagged(i: usize, generation: usize) -> OIndex<T, N> {
+        let mut o = OIndex::new(i);
+        if N > 0 {
+            o.set_usize(Self::generation(generation));
+        }
+        o
+    }
+    fn matches(i: OIndex<T, N>, generation: usize) -> bool {
+        N == 0 || Self::generation(generation) == i.get_usize()
+    }
+    /// Insert `x`, returning its index, with high `N` bits holding the generation of its slot.
+    pub fn insert(&mut self, x: T) -> OIndex<T, N> {
+        let (i, generation) = match self.slots.get(self.free) {
+            Some(&Slot::Vacant { generation, next }) => {
+                let i = self.free;
+                self.free = next;
+                self.slots[i] = Slot::Occupied {
+                    generation,
+                    value: x,
+                };
+                (i, generation)
+            }
+            _ => {
+                self.slots.push(Slot::Occupied {
+                    generation: 0,
+                    value: x,
+                });
+                (self.slots.len() - 1, 0)
+            }
+        };
+        self.len += 1;
+        Self::tagged(i, generation)
+    }
+    /// Whether index `i` is not stale.
+    pub fn contains(&self, i: OIndex<T, N>) -> bool {
+        self.get(i).is_some()
+    }
+    /// Get the value at index `i`, or `None` if it is stale.
+    pub fn get(&self, i: OIndex<T, N>) -> Option<&T> {
+        match self.slots.get(i.index()) {
+            Some(Slot::Occupied { generation, value }) if Self::matches(i, *generation) => {
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+    /// Get the value at index `i` mutably, or `None` if it is stale.
+    pub fn get_mut(&mut self, i: OIndex<T, N>) -> Option<&mut T> {
+        match self.slots.get_mut(i.index()) {
+            Some(Slot::Occupied { generation, value }) if Self::matches(i, *generation) => {
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+    /// Remove the value at index `i`, bumping the generation of its slot, or return `None` if it is stale.
+    pub fn remove(&mut self, i: OIndex<T, N>) -> Option<T> {
+        self.get(i)?;
+        let generation = match self.slots[i.index()] {
+            Slot::Occupied { generation, .. } => generation.wrapping_add(1),
+            Slot::Vacant { .. } => unreachable!(),
+        };
+        let slot = core::mem::replace(
+            &mut self.slots[i.index()],
+            Slot::Vacant {
+                generation,
+                next: self.free,
+            },
+        );
+        self.free = i.index();
+        self.len -= 1;
+        match slot {
+            Slot::Occupied { value, .. } => Some(value),
+            Slot::Vacant { .. } => None,
+        }
+    }
+    /// Iterate over values with their indexes.
+    pub fn iter(&self) -> impl Iterator<Item = (OIndex<T, N>, &T)> {
+        self.slots.iter().enumerate().filter_map(|(i, s)| match s {
+            Slot::Occupied { generation, value } => Some((Self::tagged(i, *generation), value)),
+            Slot::Vacant { .. } => None,
+        })
+    }
+}
+
+impl<T, const N: usize> Default for OArena<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: core::fmt::Debug, const N: usize> core::fmt::Debug for OArena<T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T, const N: usize> Index<OIndex<T, N>> for OArena<T, N> {
+    type Output = T;
+    fn index(&self, i: OIndex<T, N>) -> &T {
+        self.get(i).expect("Stale index")
+    }
+}
+
+impl<T, const N: usize> IndexMut<OIndex<T, N>> for OArena<T, N> {
+    fn index_mut(&mut self, i: OIndex<T, N>) -> &mut T {
+        self.get_mut(i).expect("Stale index")
+    }
+}
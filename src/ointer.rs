@@ -1,22 +1,134 @@
+use core::{mem::ManuallyDrop, ops::Deref, ptr::NonNull};
+use std::{rc::Rc, sync::Arc};
+
+/// Where an [`Ointer`] stores its stolen bits.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TagStrategy {
+    /// High bits of the pointer word, left free by user space addresses on most 64-bit targets.
+    HighBits,
+    /// Low bits of the pointer word, left free by the alignment of the pointee.
+    LowBits,
+    /// First word of the allocation, reserved by pointers with [`StealablePointer::TAG_HEADER`], for
+    /// targets without enough free bits in their pointers.
+    Header,
+}
+
+impl TagStrategy {
+    /// Strategy of pointer `P` on this target for `n` stolen bits past `offset` bits left to outer
+    /// `ointer`s: its free high bits, its free low bits or the header of its allocation, in this order
+    /// of preference.
+    ///
+    /// # Panics
+    /// If there are not enough free bits and no header, at compile time when evaluated in a constant.
+    pub const fn for_target<P: StealablePointer>(offset: usize, n: usize) -> Self {
+        if offset + n <= P::FREE_HIGH_BITS {
+            Self::HighBits
+        } else if offset + n <= P::FREE_LOW_BITS {
+            Self::LowBits
+        } else if P::TAG_HEADER {
+            Self::Header
+        } else {
+            panic!("No enough free bits to be stolen")
+        }
+    }
+    /// Check that pointer `P` reserves a header if this strategy is [`TagStrategy::Header`].
+    ///
+    /// # Panics
+    /// If it does not, at compile time when evaluated in a constant.
+    pub const fn check<P: StealablePointer>(self) -> Self {
+        assert!(
+            !matches!(self, Self::Header) || P::TAG_HEADER,
+            "No header in the allocation to store the stolen bits"
+        );
+        self
+    }
+}
+
+/// Trait of pointer-sized types whose high `N` bits are stolen to store an extra value.
+///
+/// The stolen bits start `OFFSET` bits below the top, so that `ointer`s can be nested: an outer
+/// `ointer` steals bits above `OFFSET`, and keeps the inner one, tag included, as its pointer.
+///
+/// With `SIGN_EXTEND`, the pointer is restored by sign-extending its highest remaining bit, so that
+/// canonical high-half addresses (such as kernel space on x86_64) whose top bits are all `1` can
+/// be stored as well.
+///
+/// `STRATEGY` moves the stolen bits to the low bits of the pointer (`OFFSET` bits above the
+/// bottom), or to the same bits of the header word of its allocation, behind the same methods.
+///
+/// # Safety
+/// Implementors must be `usize`-sized and keep a valid `Self::Pointer` in the low bits, with the
+/// high `OFFSET + N` bits of that pointer always `0`, or all equal to the highest remaining bit
+/// with `SIGN_EXTEND`. With [`TagStrategy::LowBits`] the low `OFFSET + N` bits of that pointer
+/// must be `0` instead, and with [`TagStrategy::Header`] the pointer is kept whole and must point
+/// to a word reserved for the stolen bits, as [`StealablePointer::TAG_HEADER`] promises.
 pub unsafe trait Ointer<const N: usize> {
     type Pointer;
-    const LOW_MASK: usize = { !0usize >> N };
-    const HIGH_MASK: usize = { !Self::LOW_MASK };
-    const MIN_SIGNED: isize = { isize::MIN >> Self::SHIFT_BITS };
-    const MAX_SIGNED: isize = { isize::MAX >> Self::SHIFT_BITS };
+    /// Number of high bits above the stolen ones, left to an outer `ointer`.
+    const OFFSET: usize = 0;
+    /// Whether the pointer is restored by sign-extending, instead of zeroing, its high bits.
+    const SIGN_EXTEND: bool = false;
+    /// Where the stolen bits are stored.
+    const STRATEGY: TagStrategy = TagStrategy::HighBits;
+    /// Mask of the pointer bits in the stored word.
+    const LOW_MASK: usize = {
+        assert!(
+            Self::OFFSET + N <= usize::BITS as usize,
+            "Stolen bits exceed the pointer width"
+        );
+        match Self::STRATEGY {
+            TagStrategy::HighBits => match 1usize.checked_shl(Self::SHIFT_BITS as u32) {
+                Some(b) => b - 1,
+                None => !0,
+            },
+            TagStrategy::LowBits => match (!0usize).checked_shl((Self::OFFSET + N) as u32) {
+                Some(m) => m,
+                None => 0,
+            },
+            TagStrategy::Header => !0,
+        }
+    };
+    /// Mask of the stolen bits in the stored word, or in the header word with [`TagStrategy::Header`].
+    const HIGH_MASK: usize = {
+        match Self::STRATEGY {
+            TagStrategy::HighBits => match (!0usize).checked_shr(Self::OFFSET as u32) {
+                Some(m) => m & !Self::LOW_MASK,
+                None => 0,
+            },
+            TagStrategy::LowBits => match 1usize.checked_shl(Self::OFFSET as u32) {
+                Some(b) => !Self::LOW_MASK & !(b - 1),
+                None => 0,
+            },
+            TagStrategy::Header => {
+                let high = match (!0usize).checked_shl((Self::OFFSET + N) as u32) {
+                    Some(m) => !m,
+                    None => !0,
+                };
+                match 1usize.checked_shl(Self::OFFSET as u32) {
+                    Some(b) => high & !(b - 1),
+                    None => 0,
+                }
+            }
+        }
+    };
+    const MIN_SIGNED: isize = { isize::MIN >> (usize::BITS as usize - N) };
+    const MAX_SIGNED: isize = { isize::MAX >> (usize::BITS as usize - N) };
     const SHIFT_BITS: usize = {
-        if cfg!(target_pointer_width = "128") {
-            128 - N
-        } else if cfg!(target_pointer_width = "64") {
-            64 - N
-        } else if cfg!(target_pointer_width = "32") {
-            32 - N
-        } else if cfg!(target_pointer_width = "16") {
-            16 - N
-        } else {
-            panic!("Unsupported target pointer width")
+        match Self::STRATEGY {
+            TagStrategy::HighBits => usize::BITS as usize - Self::OFFSET - N,
+            TagStrategy::LowBits | TagStrategy::Header => Self::OFFSET,
         }
     };
+    /// Get the word holding the stolen bits: the header word the pointer points to with
+    /// [`TagStrategy::Header`], or `self` otherwise.
+    #[inline(always)]
+    fn tag_word(&self) -> *mut usize {
+        let p = self as *const Self as *mut usize;
+        match Self::STRATEGY {
+            TagStrategy::Header => unsafe { *p as *mut usize },
+            _ => p,
+        }
+    }
     /// Get high `N` bits and return `false` if they are all `0`.
     #[inline(always)]
     fn get_bool(&self) -> bool {
@@ -25,17 +137,26 @@ pub unsafe trait Ointer<const N: usize> {
     /// Get high `N` bits and cast as `isize`.
     #[inline(always)]
     fn get_isize(&self) -> isize {
-        unsafe { *(self as *const Self as *const isize) >> Self::SHIFT_BITS }
+        let u = unsafe { *self.tag_word() } & Self::HIGH_MASK;
+        ((u >> Self::SHIFT_BITS << (usize::BITS as usize - N)) as isize)
+            >> (usize::BITS as usize - N)
     }
     /// Get high `N` bits and cast as `usize`.
     #[inline(always)]
     fn get_usize(&self) -> usize {
-        unsafe { *(self as *const Self as *const usize) >> Self::SHIFT_BITS }
+        unsafe { (*self.tag_word() & Self::HIGH_MASK) >> Self::SHIFT_BITS }
     }
     /// Get stored pointer and cast as `usize`.
     #[inline(always)]
     fn get_ptr_as_usize(&self) -> usize {
-        unsafe { *(self as *const Self as *const usize) & Self::LOW_MASK }
+        let u = unsafe { *(self as *const Self as *const usize) };
+        match Self::STRATEGY {
+            TagStrategy::HighBits if Self::SIGN_EXTEND => {
+                let high = Self::OFFSET + N;
+                ((((u & Self::LOW_MASK) << high) as isize) >> high) as usize
+            }
+            _ => u & Self::LOW_MASK,
+        }
     }
     /// Set high `N` bits all to `1` if `true`, all to `0` if `false`.
     #[inline(always)]
@@ -48,9 +169,9 @@ pub unsafe trait Ointer<const N: usize> {
         if i < Self::MIN_SIGNED || i > Self::MAX_SIGNED {
             panic!("No enough bits to be stolen.")
         }
-        let p = self as *mut Self as *mut usize;
+        let p = self.tag_word();
         unsafe {
-            *p = (*p & Self::LOW_MASK) | ((i as usize) << Self::SHIFT_BITS);
+            *p = (*p & !Self::HIGH_MASK) | (((i as usize) << Self::SHIFT_BITS) & Self::HIGH_MASK);
         }
     }
     /// Set high `N` bits from `usize`.
@@ -59,24 +180,51 @@ pub unsafe trait Ointer<const N: usize> {
         if (u >> N) != 0 {
             panic!("No enough bits to be stolen.")
         }
-        let p = self as *mut Self as *mut usize;
+        let p = self.tag_word();
         unsafe {
-            *p = (*p & Self::LOW_MASK) | (u << Self::SHIFT_BITS);
+            *p = (*p & !Self::HIGH_MASK) | (u << Self::SHIFT_BITS);
         }
     }
-    /// Store pointer to low bits.
+    /// Store pointer to low bits, clearing the stolen bits.
     #[inline(always)]
     fn set_ptr(&mut self, p: &mut Self::Pointer) {
         let u = unsafe { *(p as *mut Self::Pointer as *mut usize) };
         unsafe {
             *(self as *mut Self as *mut usize) = u;
         }
-        self.assert_stealable();
+        self.steal();
     }
-    /// Assert high `N` bits is all `0`.
+    /// Assert the stolen bits of the stored pointer are all `0`, or sign-extended with `SIGN_EXTEND`.
     #[inline(always)]
     fn assert_stealable(&self) {
-        assert_eq!(self.get_bool(), false);
+        let u = unsafe { *(self as *const Self as *const usize) };
+        match Self::STRATEGY {
+            TagStrategy::HighBits if Self::SIGN_EXTEND => assert_eq!(self.get_ptr_as_usize(), u),
+            _ => assert_eq!(u & !Self::LOW_MASK, 0),
+        }
+    }
+    /// Assert the stored pointer is stealable, then clear its stolen bits to store an extra value.
+    #[inline(always)]
+    fn steal(&mut self) {
+        self.assert_stealable();
+        let p = self as *mut Self as *mut usize;
+        unsafe {
+            *p &= Self::LOW_MASK;
+            if matches!(Self::STRATEGY, TagStrategy::Header) {
+                *self.tag_word() &= !Self::HIGH_MASK;
+            }
+        }
+    }
+    /// Clear stolen bits and restore the stored pointer, so that it can be dropped as `Self::Pointer`.
+    #[inline(always)]
+    fn restore_ptr(&mut self) {
+        if matches!(Self::STRATEGY, TagStrategy::Header) {
+            unsafe { *self.tag_word() &= !Self::HIGH_MASK };
+        }
+        let u = self.get_ptr_as_usize();
+        unsafe {
+            *(self as *mut Self as *mut usize) = u;
+        }
     }
     /// Get high `N` bits and cast as `T`.
     #[inline(always)]
@@ -119,8 +267,27 @@ pub unsafe trait Ointer<const N: usize> {
         self.set_high_bits_mut(x);
         ret
     }
+    /// Get the target of `Self::Pointer` mutably for as long as `self`, copying it first if it is shared.
+    #[inline(always)]
+    fn make_mut(&mut self) -> &mut <Self::Pointer as Deref>::Target
+    where
+        Self::Pointer: StableDerefMut,
+    {
+        let x = self.get_usize();
+        let mut u = self.get_ptr_as_usize();
+        let p = unsafe { &mut *(&mut u as *mut usize as *mut Self::Pointer) };
+        let t: *mut _ = p.make_mut();
+        self.set_ptr(p);
+        self.set_usize(x);
+        // The target stays put while `self` holds the pointer, which it does until the borrow ends.
+        unsafe { &mut *t }
+    }
 }
 
+/// Read the high `N` bits of an [`Ointer`] as a value of `T`.
+///
+/// # Safety
+/// `T` must be valid for every bit pattern that may be stored in the high `N` bits.
 pub unsafe trait OinterGet<T: Copy, const N: usize>: Ointer<N> {
     /// Get high `N` bits and cast as `T`.
     #[inline(always)]
@@ -142,6 +309,10 @@ pub unsafe trait OinterGet<T: Copy, const N: usize>: Ointer<N> {
     }
 }
 
+/// Write a value of `T` into the high `N` bits of an [`Ointer`].
+///
+/// # Safety
+/// Values of `T` must fit in `N` bits once converted to an unsigned integer.
 pub unsafe trait OinterSet<T: Copy, const N: usize>: Ointer<N> {
     /// Set high `N` bits from `T`.
     #[inline(always)]
@@ -156,13 +327,9 @@ pub unsafe trait OinterSet<T: Copy, const N: usize>: Ointer<N> {
                 .try_into()
                 .unwrap()
         } else if size_of::<T>() == 2 {
-            unsafe { *(&x as *const T as *const u16) }
-                .try_into()
-                .unwrap()
+            usize::from(unsafe { *(&x as *const T as *const u16) })
         } else if size_of::<T>() == 1 {
-            unsafe { *(&x as *const T as *const u8) }
-                .try_into()
-                .unwrap()
+            usize::from(unsafe { *(&x as *const T as *const u8) })
         } else {
             panic!("Unsupported value size")
         };
@@ -173,28 +340,449 @@ pub unsafe trait OinterSet<T: Copy, const N: usize>: Ointer<N> {
 unsafe impl<const N: usize, T: Copy, Ty: Ointer<N>> OinterGet<T, N> for Ty {}
 unsafe impl<const N: usize, T: Copy, Ty: Ointer<N>> OinterSet<T, N> for Ty {}
 
+/// Types that can be stored as the payload of an enum `ointer` variant.
+///
+/// A payload is encoded into a single word by `into_word` and decoded back by `from_word`.
+/// Pointers are stored as their address, while inline scalars are shifted left by one bit and
+/// tagged with a `1` in the lowest bit, so that the encoded word is never `0`.
+///
+/// # Safety
+/// `into_word` must return a nonzero word, `from_word` must restore the payload from it, and
+/// inline payloads must only occupy the low `INLINE_BITS + 1` bits of the word.
+pub unsafe trait EnumOinterPayload: Sized {
+    /// Width of an inline payload in bits, or `None` for a pointer whose high bits are checked at run time.
+    const INLINE_BITS: Option<usize>;
+    /// Offset from the top of the bits stolen by a payload which is itself an `ointer`.
+    const TAG_OFFSET: usize = 0;
+    /// Number of bits stolen by a payload which is itself an `ointer`.
+    const TAG_BITS: usize = 0;
+    /// Encode `self` into a nonzero word.
+    fn into_word(self) -> usize;
+    /// Decode a word produced by `into_word`.
+    ///
+    /// # Safety
+    /// `u` must have been returned by `into_word` and must not be decoded more than once unless `Self: Copy`.
+    unsafe fn from_word(u: usize) -> Self;
+}
+
+/// Check at compile time that payload `P` leaves the high `n` bits of its encoded word free.
+pub const fn enum_payload_fits<P: EnumOinterPayload>(n: usize) -> bool {
+    match P::INLINE_BITS {
+        Some(bits) => bits + 1 + n <= usize::BITS as usize,
+        None => n < usize::BITS as usize && (P::TAG_BITS == 0 || P::TAG_OFFSET >= n),
+    }
+}
+
+/// Check at compile time that the stolen bits of `Outer` and of the nested `Inner` do not collide.
+///
+/// An outer `ointer` must steal bits above the ones stolen by the inner `ointer` it points by:
+/// ```
+/// use ointer::{ointers_disjoint, sync::{BArc, OArc}};
+/// const _: () = assert!(ointers_disjoint::<8, 1, BArc<u8>, OArc<u8, 8>>());
+/// ```
+/// ```compile_fail
+/// use ointer::{ointers_disjoint, sync::{BArc, OArc}};
+/// const _: () = assert!(ointers_disjoint::<8, 1, BArc<u8>, OArc<u8, 4>>());
+/// ```
+pub const fn ointers_disjoint<const N: usize, const M: usize, Outer, Inner>() -> bool
+where
+    Outer: Ointer<N>,
+    Inner: Ointer<M>,
+{
+    M == 0 || Outer::OFFSET + N <= Inner::OFFSET
+}
+
+/// Minimum number of high bits (at least `1`) to store every discriminant in `discriminants`.
+pub const fn enum_discriminant_bits(discriminants: &[usize]) -> usize {
+    let mut max = 1;
+    let mut i = 0;
+    while i < discriminants.len() {
+        if discriminants[i] > max {
+            max = discriminants[i];
+        }
+        i += 1;
+    }
+    (usize::BITS - max.leading_zeros()) as usize
+}
+
+/// Decode the payload `P` from word `u` and map it by fn `f` without dropping it.
+///
+/// # Safety
+/// `u` must have been returned by `P::into_word`.
+#[inline(always)]
+pub unsafe fn map_enum_payload<P: EnumOinterPayload, R, F: FnOnce(&P) -> R>(u: usize, f: F) -> R {
+    let p = ManuallyDrop::new(P::from_word(u));
+    f(&p)
+}
+
+/// Decode the payload `P` from word `u`, map it by fn `f`, then encode it back into `u`.
+///
+/// # Safety
+/// `*u` must have been returned by `P::into_word`.
+#[inline(always)]
+pub unsafe fn map_enum_payload_mut<P: EnumOinterPayload, R, F: FnOnce(&mut P) -> R>(
+    u: &mut usize,
+    f: F,
+) -> R {
+    let mut p = ManuallyDrop::new(P::from_word(*u));
+    let r = f(&mut p);
+    *u = ManuallyDrop::into_inner(p).into_word();
+    r
+}
+
+/// Decode the payloads `P` from words `u` and `v` and map them by fn `f` without dropping them.
+///
+/// # Safety
+/// `u` and `v` must have been returned by `P::into_word`.
+#[inline(always)]
+pub unsafe fn map_enum_payloads<P: EnumOinterPayload, R, F: FnOnce(&P, &P) -> R>(
+    u: usize,
+    v: usize,
+    f: F,
+) -> R {
+    map_enum_payload(u, |p| map_enum_payload(v, |q| f(p, q)))
+}
+
+/// Cast payload `p` to `Q` if `P` and `Q` are the same type, or give it back otherwise.
+#[inline(always)]
+pub fn cast_enum_payload<P: 'static, Q: 'static>(p: P) -> Result<Q, P> {
+    use core::any::TypeId;
+    if TypeId::of::<P>() == TypeId::of::<Q>() {
+        let p = ManuallyDrop::new(p);
+        Ok(unsafe { core::mem::transmute_copy::<P, Q>(&p) })
+    } else {
+        Err(p)
+    }
+}
+
+/// Payloads with a weak counterpart, used by enum `ointer`s paired with a weak enum by `#[weak(..)]`.
+///
+/// Shared pointers downgrade to their `Weak`, while inline scalars and shared references are their own
+/// weak counterpart.
+pub trait Downgrade {
+    /// Weak counterpart of `Self`.
+    type Weak: Upgrade<Strong = Self> + EnumOinterPayload;
+    /// Create the weak counterpart of `self`.
+    fn downgrade(&self) -> Self::Weak;
+}
+
+/// Weak payloads that can be upgraded back to their strong counterpart.
+pub trait Upgrade {
+    /// Strong counterpart of `Self`.
+    type Strong;
+    /// Attempt to upgrade `self`, returning `None` if the value has been dropped.
+    fn upgrade(&self) -> Option<Self::Strong>;
+}
+
+impl<T> Downgrade for Rc<T> {
+    type Weak = std::rc::Weak<T>;
+    #[inline(always)]
+    fn downgrade(&self) -> Self::Weak {
+        Rc::downgrade(self)
+    }
+}
+
+impl<T> Upgrade for std::rc::Weak<T> {
+    type Strong = Rc<T>;
+    #[inline(always)]
+    fn upgrade(&self) -> Option<Self::Strong> {
+        std::rc::Weak::upgrade(self)
+    }
+}
+
+impl<T> Downgrade for Arc<T> {
+    type Weak = std::sync::Weak<T>;
+    #[inline(always)]
+    fn downgrade(&self) -> Self::Weak {
+        Arc::downgrade(self)
+    }
+}
+
+impl<T> Upgrade for std::sync::Weak<T> {
+    type Strong = Arc<T>;
+    #[inline(always)]
+    fn upgrade(&self) -> Option<Self::Strong> {
+        std::sync::Weak::upgrade(self)
+    }
+}
+
+impl<'a, T> Downgrade for &'a T {
+    type Weak = &'a T;
+    #[inline(always)]
+    fn downgrade(&self) -> Self::Weak {
+        self
+    }
+}
+
+impl<'a, T> Upgrade for &'a T {
+    type Strong = &'a T;
+    #[inline(always)]
+    fn upgrade(&self) -> Option<Self::Strong> {
+        Some(self)
+    }
+}
+
+macro_rules! impl_inline_enum_payload {
+    ($($ty:ty: $bits:literal, |$x:ident| $into:expr, |$u:ident| $from:expr);* $(;)?) => {
+        $(
+            unsafe impl EnumOinterPayload for $ty {
+                const INLINE_BITS: Option<usize> = Some($bits);
+                #[inline(always)]
+                fn into_word(self) -> usize {
+                    let $x = self;
+                    ($into << 1) | 1
+                }
+                #[inline(always)]
+                unsafe fn from_word(u: usize) -> Self {
+                    let $u = u >> 1;
+                    $from
+                }
+            }
+
+            impl Downgrade for $ty {
+                type Weak = $ty;
+                #[inline(always)]
+                fn downgrade(&self) -> Self::Weak {
+                    *self
+                }
+            }
+
+            impl Upgrade for $ty {
+                type Strong = $ty;
+                #[inline(always)]
+                fn upgrade(&self) -> Option<Self::Strong> {
+                    Some(*self)
+                }
+            }
+        )*
+    };
+}
+
+impl_inline_enum_payload! {
+    (): 0, |_x| 0usize, |_u| ();
+    bool: 1, |x| x as usize, |u| u != 0;
+    u8: 8, |x| x as usize, |u| u as u8;
+    i8: 8, |x| x as u8 as usize, |u| u as u8 as i8;
+    u16: 16, |x| x as usize, |u| u as u16;
+    i16: 16, |x| x as u16 as usize, |u| u as u16 as i16;
+    u32: 32, |x| x as usize, |u| u as u32;
+    i32: 32, |x| x as u32 as usize, |u| u as u32 as i32;
+    char: 32, |x| x as usize, |u| unsafe { char::from_u32_unchecked(u as u32) };
+    f32: 32, |x| x.to_bits() as usize, |u| f32::from_bits(u as u32);
+}
+
+macro_rules! impl_pointer_enum_payload {
+    ($([$($lt:lifetime)?] $pointer:ty, |$x:ident| $into:expr, |$u:ident| $from:expr);* $(;)?) => {
+        $(
+            unsafe impl<$($lt,)? T> EnumOinterPayload for $pointer {
+                const INLINE_BITS: Option<usize> = None;
+                #[inline(always)]
+                fn into_word(self) -> usize {
+                    let $x = self;
+                    $into as usize
+                }
+                #[inline(always)]
+                unsafe fn from_word(u: usize) -> Self {
+                    let $u = u;
+                    $from
+                }
+            }
+        )*
+    };
+}
+
+impl_pointer_enum_payload! {
+    [] Box<T>, |x| Box::into_raw(x), |u| Box::from_raw(u as *mut T);
+    [] Rc<T>, |x| Rc::into_raw(x), |u| Rc::from_raw(u as *const T);
+    [] std::rc::Weak<T>, |x| x.into_raw(), |u| std::rc::Weak::from_raw(u as *const T);
+    [] Arc<T>, |x| Arc::into_raw(x), |u| Arc::from_raw(u as *const T);
+    [] std::sync::Weak<T>, |x| x.into_raw(), |u| std::sync::Weak::from_raw(u as *const T);
+    [] NonNull<T>, |x| x.as_ptr(), |u| NonNull::new_unchecked(u as *mut T);
+    ['a] &'a T, |x| x as *const T, |u| &*(u as *const T);
+    ['a] &'a mut T, |x| x as *mut T, |u| &mut *(u as *mut T);
+}
+
+/// Pointers whose target stays put while the pointer is neither mutated nor dropped, and so can be
+/// borrowed for as long as an `ointer` holding the pointer, as by `map_enum_ref` of enum `ointer`s.
+///
+/// # Safety
+/// `deref` must not return a reference into the pointer itself, and the reference must stay valid
+/// while the pointer is only moved or encoded into a word.
+pub unsafe trait StableDeref: Deref {}
+
+/// Pointers of [`StableDeref`] targets that can be borrowed mutably, as by `map_enum_ref_mut` of
+/// enum `ointer`s and [`Ointer::make_mut`].
+///
+/// # Safety
+/// The reference returned by `make_mut` must follow the rules of [`StableDeref`], and be unique.
+pub unsafe trait StableDerefMut: StableDeref {
+    /// Get the target mutably, copying it first if it is shared.
+    fn make_mut(&mut self) -> &mut Self::Target;
+}
+
+unsafe impl<T: ?Sized> StableDeref for Box<T> {}
+unsafe impl<T: ?Sized> StableDeref for Rc<T> {}
+unsafe impl<T: ?Sized> StableDeref for Arc<T> {}
+unsafe impl<T: ?Sized> StableDeref for &T {}
+unsafe impl<T: ?Sized> StableDeref for &mut T {}
+
+unsafe impl<T: ?Sized> StableDerefMut for Box<T> {
+    #[inline(always)]
+    fn make_mut(&mut self) -> &mut T {
+        self
+    }
+}
+
+unsafe impl<T: Clone> StableDerefMut for Rc<T> {
+    #[inline(always)]
+    fn make_mut(&mut self) -> &mut T {
+        Rc::make_mut(self)
+    }
+}
+
+unsafe impl<T: Clone> StableDerefMut for Arc<T> {
+    #[inline(always)]
+    fn make_mut(&mut self) -> &mut T {
+        Arc::make_mut(self)
+    }
+}
+
+unsafe impl<T: ?Sized> StableDerefMut for &mut T {
+    #[inline(always)]
+    fn make_mut(&mut self) -> &mut T {
+        self
+    }
+}
+
+/// Single-word, non-null pointers whose high (and low) bits are known to be free.
+///
+/// # Safety
+/// Implementors must be `usize`-sized and never `0`, with the high `FREE_HIGH_BITS` bits and the
+/// low `FREE_LOW_BITS` bits of their word always `0`, apart from dangling values which are checked
+/// at run time.
+pub unsafe trait StealablePointer: Sized {
+    /// Number of high bits always `0` in the pointer word.
+    const FREE_HIGH_BITS: usize;
+    /// Number of low bits always `0` in the pointer word, given by the alignment of the pointee.
+    const FREE_LOW_BITS: usize;
+    /// Whether the pointer owns its allocation alone and points to a word at its start reserved for the
+    /// stolen bits of [`TagStrategy::Header`], which steals up to a word on any target.
+    const TAG_HEADER: bool = false;
+}
+
+/// Number of high bits left free by user space addresses on this target.
+pub const FREE_ADDRESS_BITS: usize = if usize::BITS == 64 { 16 } else { 0 };
+
+macro_rules! impl_stealable_pointer {
+    ($([$($lt:lifetime)?] $pointer:ty, $low:expr);* $(;)?) => {
+        $(
+            unsafe impl<$($lt,)? T> StealablePointer for $pointer {
+                const FREE_HIGH_BITS: usize = FREE_ADDRESS_BITS;
+                const FREE_LOW_BITS: usize = $low;
+            }
+        )*
+    };
+}
+
+impl_stealable_pointer! {
+    [] Box<T>, core::mem::align_of::<T>().trailing_zeros() as usize;
+    [] Rc<T>, core::mem::align_of::<T>().trailing_zeros() as usize;
+    [] std::rc::Weak<T>, core::mem::align_of::<T>().trailing_zeros() as usize;
+    [] Arc<T>, core::mem::align_of::<T>().trailing_zeros() as usize;
+    [] std::sync::Weak<T>, core::mem::align_of::<T>().trailing_zeros() as usize;
+    [] NonNull<T>, core::mem::align_of::<T>().trailing_zeros() as usize;
+    ['a] &'a T, core::mem::align_of::<T>().trailing_zeros() as usize;
+    ['a] &'a mut T, core::mem::align_of::<T>().trailing_zeros() as usize;
+}
+
 /// Macro used to define `Weak` like `ointer`s.
+/// A trailing [`TagStrategy`] argument selects where the stolen bits are stored, which is
+/// [`TagStrategy::for_target`] by default.
+/// A pointer written as `Pointer<A: Bound = Default>` takes an allocator `A` after its value, which
+/// the `ointer` takes too, as in [`allocator::OBoxIn`](crate::allocator::OBoxIn).
 #[macro_export]
 macro_rules! define_ointer {
-    ($ointer:ident, $pointer:ident, $bits:literal) => {
+    (@strategy $pointer:ty, $offset:ident, $bits:literal) => {
+        TagStrategy::for_target::<$pointer>($offset, $bits)
+    };
+    (@strategy $pointer:ty, $offset:ident, $bits:literal, $strategy:expr) => {
+        TagStrategy::check::<$pointer>($strategy)
+    };
+    (
+        $ointer:ident,
+        $pointer:ident<$alloc:ident: $bound:path = $default:ty>,
+        $bits:literal
+        $(, $strategy:expr)?
+    ) => {
+        define_ointer!(
+            @define $ointer, $pointer, [$alloc: $bound = $default], $bits $(, $strategy)?
+        );
+    };
+    ($ointer:ident, $pointer:ident, $bits:literal $(, $strategy:expr)?) => {
+        define_ointer!(@define $ointer, $pointer, [], $bits $(, $strategy)?);
+    };
+    (
+        @define
+        $ointer:ident,
+        $pointer:ident,
+        [$($alloc:ident: $bound:path = $default:ty)?],
+        $bits:literal
+        $(, $strategy:expr)?
+    ) => {
         #[repr(transparent)]
-        pub struct $ointer<T: ?Sized>($pointer<T>);
+        pub struct $ointer<
+            T,
+            $($alloc: $bound = $default,)?
+            const O: usize = 0,
+            const S: bool = false,
+        >($pointer<T $(, $alloc)?>);
 
-        unsafe impl<T: ?Sized> Ointer<$bits> for $ointer<T> {
-            type Pointer = $pointer<T>;
+        unsafe impl<T, $($alloc: $bound,)? const O: usize, const S: bool> Ointer<$bits>
+            for $ointer<T, $($alloc,)? O, S>
+        {
+            type Pointer = $pointer<T $(, $alloc)?>;
+            const OFFSET: usize = O;
+            const SIGN_EXTEND: bool = S;
+            const STRATEGY: TagStrategy =
+                define_ointer!(@strategy $pointer<T $(, $alloc)?>, O, $bits $(, $strategy)?);
         }
 
-        impl<T: ?Sized> core::convert::From<$pointer<T>> for $ointer<T> {
-            fn from(p: $pointer<T>) -> Self {
-                let s = Self(p);
-                s.assert_stealable();
+        unsafe impl<T, $($alloc: $bound,)? const O: usize, const S: bool> EnumOinterPayload
+            for $ointer<T, $($alloc,)? O, S>
+        where
+            $pointer<T $(, $alloc)?>: EnumOinterPayload,
+        {
+            const INLINE_BITS: Option<usize> = None;
+            const TAG_OFFSET: usize = O;
+            const TAG_BITS: usize = match <Self as Ointer<$bits>>::STRATEGY {
+                TagStrategy::HighBits => $bits,
+                _ => 0,
+            };
+            #[inline(always)]
+            fn into_word(self) -> usize {
+                let o = core::mem::ManuallyDrop::new(self);
+                unsafe { *(&*o as *const Self as *const usize) }
+            }
+            #[inline(always)]
+            unsafe fn from_word(u: usize) -> Self {
+                core::mem::transmute_copy(&u)
+            }
+        }
+
+        impl<T, $($alloc: $bound,)? const O: usize, const S: bool> core::convert::From<$pointer<T $(, $alloc)?>>
+            for $ointer<T, $($alloc,)? O, S>
+        {
+            fn from(p: $pointer<T $(, $alloc)?>) -> Self {
+                let mut s = Self(p);
+                s.steal();
                 s
             }
         }
 
-        impl<T> core::default::Default for $ointer<T>
+        impl<T, $($alloc: $bound,)? const O: usize, const S: bool> core::default::Default
+            for $ointer<T, $($alloc,)? O, S>
         where
-            Self: Ointer<$bits, Pointer = $pointer<T>>,
+            Self: Ointer<$bits, Pointer = $pointer<T $(, $alloc)?>>,
             <Self as Ointer<$bits>>::Pointer: core::default::Default,
         {
             fn default() -> Self {
@@ -202,23 +790,25 @@ macro_rules! define_ointer {
             }
         }
 
-        impl<T: ?Sized> core::clone::Clone for $ointer<T>
+        impl<T, $($alloc: $bound,)? const O: usize, const S: bool> core::clone::Clone
+            for $ointer<T, $($alloc,)? O, S>
         where
-            Self: Ointer<$bits, Pointer = $pointer<T>>,
+            Self: Ointer<$bits, Pointer = $pointer<T $(, $alloc)?>>,
             <Self as Ointer<$bits>>::Pointer: Clone,
         {
             fn clone(&self) -> Self {
                 self.map(|u: usize, p| {
-                    let mut o = Self(p.clone());
+                    let mut o = Self::from(p.clone());
                     o.set_usize(u);
                     o
                 })
             }
         }
 
-        impl<T: ?Sized> core::fmt::Debug for $ointer<T>
+        impl<T, $($alloc: $bound,)? const O: usize, const S: bool> core::fmt::Debug
+            for $ointer<T, $($alloc,)? O, S>
         where
-            Self: Ointer<$bits, Pointer = $pointer<T>>,
+            Self: Ointer<$bits, Pointer = $pointer<T $(, $alloc)?>>,
             <Self as Ointer<$bits>>::Pointer: core::fmt::Debug,
         {
             fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
@@ -226,18 +816,20 @@ macro_rules! define_ointer {
             }
         }
 
-        impl<T: ?Sized> core::ops::Drop for $ointer<T>
+        impl<T, $($alloc: $bound,)? const O: usize, const S: bool> core::ops::Drop
+            for $ointer<T, $($alloc,)? O, S>
         where
             Self: Ointer<$bits>,
         {
             fn drop(&mut self) {
-                self.set_bool(false);
+                self.restore_ptr();
             }
         }
 
-        impl<T: ?Sized> core::hash::Hash for $ointer<T>
+        impl<T, $($alloc: $bound,)? const O: usize, const S: bool> core::hash::Hash
+            for $ointer<T, $($alloc,)? O, S>
         where
-            Self: Ointer<$bits, Pointer = $pointer<T>>,
+            Self: Ointer<$bits, Pointer = $pointer<T $(, $alloc)?>>,
             <Self as Ointer<$bits>>::Pointer: core::hash::Hash,
         {
             fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
@@ -245,9 +837,10 @@ macro_rules! define_ointer {
             }
         }
 
-        impl<T: ?Sized> core::cmp::PartialEq for $ointer<T>
+        impl<T, $($alloc: $bound,)? const O: usize, const S: bool> core::cmp::PartialEq
+            for $ointer<T, $($alloc,)? O, S>
         where
-            Self: Ointer<$bits, Pointer = $pointer<T>>,
+            Self: Ointer<$bits, Pointer = $pointer<T $(, $alloc)?>>,
             <Self as Ointer<$bits>>::Pointer: core::cmp::PartialEq,
         {
             fn eq(&self, rhs: &Self) -> bool {
@@ -255,9 +848,10 @@ macro_rules! define_ointer {
             }
         }
 
-        impl<T: ?Sized> core::cmp::PartialOrd for $ointer<T>
+        impl<T, $($alloc: $bound,)? const O: usize, const S: bool> core::cmp::PartialOrd
+            for $ointer<T, $($alloc,)? O, S>
         where
-            Self: Ointer<$bits, Pointer = $pointer<T>>,
+            Self: Ointer<$bits, Pointer = $pointer<T $(, $alloc)?>>,
             <Self as Ointer<$bits>>::Pointer: core::cmp::PartialOrd,
         {
             fn partial_cmp(&self, rhs: &Self) -> Option<core::cmp::Ordering> {
@@ -265,9 +859,29 @@ macro_rules! define_ointer {
             }
         }
 
-        impl<T: ?Sized> core::ops::Deref for $ointer<T>
+        impl<T, $($alloc: $bound,)? const O: usize, const S: bool> core::cmp::Eq
+            for $ointer<T, $($alloc,)? O, S>
         where
-            Self: Ointer<$bits, Pointer = $pointer<T>>,
+            Self: Ointer<$bits, Pointer = $pointer<T $(, $alloc)?>>,
+            <Self as Ointer<$bits>>::Pointer: core::cmp::Eq,
+        {
+        }
+
+        impl<T, $($alloc: $bound,)? const O: usize, const S: bool> core::cmp::Ord
+            for $ointer<T, $($alloc,)? O, S>
+        where
+            Self: Ointer<$bits, Pointer = $pointer<T $(, $alloc)?>>,
+            <Self as Ointer<$bits>>::Pointer: core::cmp::Ord,
+        {
+            fn cmp(&self, rhs: &Self) -> core::cmp::Ordering {
+                self.map(|u: usize, p| rhs.map(|c, q| (u, p).cmp(&(c, q))))
+            }
+        }
+
+        impl<T, $($alloc: $bound,)? const O: usize, const S: bool> core::ops::Deref
+            for $ointer<T, $($alloc,)? O, S>
+        where
+            Self: Ointer<$bits, Pointer = $pointer<T $(, $alloc)?>>,
             <Self as Ointer<$bits>>::Pointer: core::ops::Deref<Target = T>,
         {
             type Target = T;
@@ -276,9 +890,10 @@ macro_rules! define_ointer {
             }
         }
 
-        impl<T: ?Sized> core::ops::DerefMut for $ointer<T>
+        impl<T, $($alloc: $bound,)? const O: usize, const S: bool> core::ops::DerefMut
+            for $ointer<T, $($alloc,)? O, S>
         where
-            Self: Ointer<$bits, Pointer = $pointer<T>>,
+            Self: Ointer<$bits, Pointer = $pointer<T $(, $alloc)?>>,
             <Self as Ointer<$bits>>::Pointer: core::ops::DerefMut<Target = T>,
         {
             fn deref_mut(&mut self) -> &mut T {
@@ -286,7 +901,8 @@ macro_rules! define_ointer {
             }
         }
 
-        impl<T: ?Sized> $ointer<T>
+        impl<T, $($alloc: $bound,)? const O: usize, const S: bool>
+            $ointer<T, $($alloc,)? O, S>
         where
             Self: Ointer<1>,
         {
@@ -311,29 +927,132 @@ macro_rules! define_ointer {
     };
 }
 
+#[doc(hidden)]
+#[macro_export]
 macro_rules! define_ointer_methods {
+    ($ointer:ident, $pointer:ident<$alloc:ident: $bound:path = $default:ty>, $bits:literal) => {
+        impl<T> $ointer<T>
+        where
+            Self: Ointer<$bits, Pointer = $pointer<T>>,
+        {
+            /// Allocate `x` by the default allocator.
+            pub fn new(x: T) -> Self {
+                let () = Self::ALIGNED;
+                $pointer::new(x).into()
+            }
+            /// Allocate and pin `x` by the default allocator.
+            pub fn pin(x: T) -> core::pin::Pin<Self> {
+                unsafe { core::pin::Pin::new_unchecked(Self::new(x)) }
+            }
+        }
+
+        impl<T, $alloc: $bound> $ointer<T, $alloc>
+        where
+            Self: Ointer<$bits, Pointer = $pointer<T, $alloc>>,
+        {
+            /// Allocate `x` by `alloc`.
+            pub fn new_in(x: T, alloc: $alloc) -> Self {
+                Self::new_in_with_layout(x, alloc)
+            }
+            /// Allocate and pin `x` by `alloc`.
+            pub fn pin_in(x: T, alloc: $alloc) -> core::pin::Pin<Self> {
+                unsafe { core::pin::Pin::new_unchecked(Self::new_in(x, alloc)) }
+            }
+        }
+
+        impl<T, $alloc: $bound, const O: usize, const S: bool> $ointer<T, $alloc, O, S>
+        where
+            Self: Ointer<$bits, Pointer = $pointer<T, $alloc>>,
+            $pointer<T, $alloc>: StealablePointer,
+        {
+            /// Fail to compile if the stolen bits are low bits that the allocation is not aligned for.
+            const ALIGNED: () = assert!(
+                !matches!(<Self as Ointer<$bits>>::STRATEGY, TagStrategy::LowBits)
+                    || <$pointer<T, $alloc> as StealablePointer>::FREE_LOW_BITS.saturating_sub(O)
+                        >= $bits,
+                "No enough alignment bits to be stolen"
+            );
+            /// Allocate `x` by `alloc` in an `ointer` with any offset `O` and sign extension `S`.
+            pub fn new_in_with_layout(x: T, alloc: $alloc) -> Self {
+                let () = Self::ALIGNED;
+                $pointer::new_in(x, alloc).into()
+            }
+            /// Allocate and pin `x` by `alloc` with any offset `O` and sign extension `S`.
+            pub fn pin_in_with_layout(x: T, alloc: $alloc) -> core::pin::Pin<Self> {
+                unsafe { core::pin::Pin::new_unchecked(Self::new_in_with_layout(x, alloc)) }
+            }
+            /// Get the allocator.
+            pub fn allocator(&self) -> &$alloc {
+                self.map(|_: usize, p| unsafe { &*(p.allocator() as *const $alloc) })
+            }
+        }
+    };
     ($ointer:ident, $pointer:ident, $bits:literal) => {
         impl<T> $ointer<T>
         where
             Self: Ointer<$bits, Pointer = $pointer<T>>,
         {
             pub fn new(x: T) -> Self {
+                let () = Self::ALIGNED;
                 $pointer::new(x).into()
             }
             pub fn pin(x: T) -> core::pin::Pin<Self> {
                 unsafe { core::pin::Pin::new_unchecked(Self::new(x)) }
             }
         }
+
+        impl<T, const O: usize, const S: bool> $ointer<T, O, S>
+        where
+            Self: Ointer<$bits, Pointer = $pointer<T>>,
+        {
+            /// Fail to compile if the stolen bits are low bits that `T` is not aligned enough for.
+            const ALIGNED: () = assert!(
+                !matches!(<Self as Ointer<$bits>>::STRATEGY, TagStrategy::LowBits)
+                    || (!<Self as Ointer<$bits>>::LOW_MASK & !(core::mem::align_of::<T>() - 1))
+                        == 0,
+                "No enough alignment bits to be stolen"
+            );
+            /// Make an `ointer` with any offset `O` and sign extension `S`, which `new` leaves to their defaults
+            /// so that they need not be annotated.
+            pub fn new_with_layout(x: T) -> Self {
+                let () = Self::ALIGNED;
+                $pointer::new(x).into()
+            }
+            /// Pin an `ointer` with any offset `O` and sign extension `S`.
+            pub fn pin_with_layout(x: T) -> core::pin::Pin<Self> {
+                unsafe { core::pin::Pin::new_unchecked(Self::new_with_layout(x)) }
+            }
+        }
     };
 }
 
-pub(crate) use define_ointer_methods;
-
 /// Macro used to define `Box`/`Rc`/`Arc` like `ointer`s.
 /// This crate defines `BBox`(called byte stolen `Box`) that wraps `Box` and steal high 8-bits(1-byte), by using
 /// `define_ointer_strong!(BBox, Box, 8);`
 /// And define `OBox`(called orientable `Box`) by using
 /// `define_ointer_strong!(OBox, Box, 1);`
+/// A `Box` storing its bit in the low alignment bits could be defined by using
+/// `define_ointer_strong!(LBox, Box, 1, TagStrategy::LowBits);`
+/// whose pointees must then be aligned enough, which `new` checks at compile time:
+/// ```
+/// use ointer::*;
+/// define_ointer_strong!(LBox, Box, 1, TagStrategy::LowBits);
+/// let mut o = LBox::new(1u16);
+/// o.flip();
+/// assert_eq!((*o, o.o()), (1, true));
+/// ```
+/// ```compile_fail
+/// use ointer::*;
+/// define_ointer_strong!(LBox, Box, 1, TagStrategy::LowBits);
+/// let o = LBox::new(1u8);
+/// ```
+/// [`TagStrategy::Header`] needs a pointer reserving a header word in its allocation, which `Box`
+/// does not:
+/// ```compile_fail
+/// use ointer::*;
+/// define_ointer_strong!(HBox, Box, 1, TagStrategy::Header);
+/// let o = HBox::new(1u64);
+/// ```
 ///
 /// Tests over`OBox`
 /// ```
@@ -341,20 +1060,29 @@ pub(crate) use define_ointer_methods;
 /// use std::pin::Pin;
 /// let mut o = OBox::new(1);
 /// assert_eq!(*o, 1);
-/// assert_eq!(o.get::<bool>(), false);
+/// assert!(!o.get::<bool>());
 /// assert_eq!(*o, 1);
 /// *o = i32::default();
 /// assert_eq!(*o, i32::default());
 /// o.set_bool(true);
 /// let b = o.get_bool();
-/// assert_eq!(b, true);
+/// assert!(b);
 /// o.set_mut(false);
 /// assert_eq!(o, Pin::into_inner(OBox::pin(Default::default())));
 /// ```
 #[macro_export]
 macro_rules! define_ointer_strong {
-    ($ointer:ident, $pointer:ident, $bits:literal) => {
-        define_ointer!($ointer, $pointer, $bits);
+    (
+        $ointer:ident,
+        $pointer:ident<$alloc:ident: $bound:path = $default:ty>,
+        $bits:literal
+        $(, $strategy:expr)?
+    ) => {
+        define_ointer!($ointer, $pointer<$alloc: $bound = $default>, $bits $(, $strategy)?);
+        define_ointer_methods!($ointer, $pointer<$alloc: $bound = $default>, $bits);
+    };
+    ($ointer:ident, $pointer:ident, $bits:literal $(, $strategy:expr)?) => {
+        define_ointer!($ointer, $pointer, $bits $(, $strategy)?);
         define_ointer_methods!($ointer, $pointer, $bits);
     };
 }
@@ -371,7 +1099,7 @@ macro_rules! define_ointer_strong {
 /// use core::mem::size_of;
 /// let mut o = BArc::new(1);
 /// assert_eq!(*o, 1);
-/// assert_eq!(o.get::<bool>(), false);
+/// assert!(!o.get::<bool>());
 ///
 /// // Define a small enum for testing.
 /// #[derive(Clone, Copy, PartialEq, Debug)]
@@ -395,13 +1123,56 @@ macro_rules! define_ointer_strong {
 /// ```
 #[macro_export]
 macro_rules! define_shared_ointer {
-    ($ointer_strong:ident, $pointer_strong:ident, $ointer_weak:ident, $pointer_weak:ident, $bits:literal) => {
-        define_ointer_strong!($ointer_strong, $pointer_strong, $bits);
-        define_ointer!($ointer_weak, $pointer_weak, $bits);
-        impl<T: ?Sized> $ointer_strong<T> {
-            pub fn downgrade(&self) -> $ointer_weak<T> {
+    (
+        $ointer_strong:ident,
+        $pointer_strong:ident<$alloc:ident: $bound:path = $default:ty>,
+        $ointer_weak:ident,
+        $pointer_weak:ident,
+        $bits:literal
+        $(, $strategy:expr)?
+    ) => {
+        define_ointer_strong!(
+            $ointer_strong,
+            $pointer_strong<$alloc: $bound = $default>,
+            $bits
+            $(, $strategy)?
+        );
+        define_ointer!(
+            $ointer_weak,
+            $pointer_weak<$alloc: $bound = $default>,
+            $bits
+            $(, $strategy)?
+        );
+        define_shared_ointer!(
+            @define $ointer_strong, $pointer_strong, $ointer_weak, [$alloc: $bound]
+        );
+    };
+    (
+        $ointer_strong:ident,
+        $pointer_strong:ident,
+        $ointer_weak:ident,
+        $pointer_weak:ident,
+        $bits:literal
+        $(, $strategy:expr)?
+    ) => {
+        define_ointer_strong!($ointer_strong, $pointer_strong, $bits $(, $strategy)?);
+        define_ointer!($ointer_weak, $pointer_weak, $bits $(, $strategy)?);
+        define_shared_ointer!(@define $ointer_strong, $pointer_strong, $ointer_weak, []);
+    };
+    (
+        @define
+        $ointer_strong:ident,
+        $pointer_strong:ident,
+        $ointer_weak:ident,
+        [$($alloc:ident: $bound:path)?]
+    ) => {
+        impl<T, $($alloc: $bound,)? const O: usize, const S: bool>
+            $ointer_strong<T, $($alloc,)? O, S>
+        {
+            pub fn downgrade(&self) -> $ointer_weak<T, $($alloc,)? O, S> {
                 self.map(|u: usize, p| {
-                    let mut o: $ointer_weak<T> = $pointer_strong::downgrade(p).into();
+                    let mut o: $ointer_weak<T, $($alloc,)? O, S> =
+                        $pointer_strong::downgrade(p).into();
                     o.set_usize(u);
                     o
                 })
@@ -413,12 +1184,14 @@ macro_rules! define_shared_ointer {
                 self.map(|_: usize, p| $pointer_strong::weak_count(p))
             }
         }
-        impl<T: ?Sized> $ointer_weak<T> {
-            pub fn upgrade(&self) -> Option<$ointer_strong<T>> {
+        impl<T, $($alloc: $bound,)? const O: usize, const S: bool>
+            $ointer_weak<T, $($alloc,)? O, S>
+        {
+            pub fn upgrade(&self) -> Option<$ointer_strong<T, $($alloc,)? O, S>> {
                 self.map(|u: usize, w| {
                     let p = w.upgrade();
                     p.map(|p| {
-                        let mut o: $ointer_strong<T> = p.into();
+                        let mut o: $ointer_strong<T, $($alloc,)? O, S> = p.into();
                         o.set_usize(u);
                         o
                     })
@@ -470,143 +1243,857 @@ macro_rules! define_shared_ointer {
 /// assert_eq!(e.map_enum(|p| **p, |_| panic!(), |_| panic!()), 2.0);
 /// assert_eq!(size_of::<MyEnumOinters>(), size_of::<usize>());
 /// ```
+///
+/// Enum `ointer`s can also be declared like a Rust `enum` with named variants. A variant may
+/// carry a pointer payload (`Box`, `Rc`, `Arc`, their `Weak`s, `NonNull` and references), an
+/// inline scalar payload (`bool`, `char`, `f32` and integers up to 32 bits) stored in the low
+/// bits, or no payload at all. Each variant gets a typed constructor named `new_<variant>`.
+/// ```
+/// use ointer::define_enum_ointers;
+/// define_enum_ointers! {
+///     /// A small dynamic value.
+///     pub enum Value: 8 {
+///         Nil = 0,
+///         Int(i32) = 1,
+///         Char(char) = 2,
+///         Float(Box<f64>) = 3,
+///     }
+/// }
+/// let mut v = Value::new_int(0);
+/// assert_eq!(v.map_enum(|_| -1, |i| *i, |_| -1, |_| -1), 0);
+/// v.map_enum_mut(|_| (), |i| *i -= 7, |_| (), |_| ());
+/// assert_eq!(v.map_enum(|_| -1, |i| *i, |_| -1, |_| -1), -7);
+/// v = Value::new_nil();
+/// assert!(v.map_enum(|_| true, |_| false, |_| false, |_| false));
+/// v = Value::new_float(Box::new(0.5));
+/// assert_eq!(v.map_enum(|_| 0.0, |_| 0.0, |_| 0.0, |f| **f), 0.5);
+/// ```
+///
+/// Both forms accept generic parameters, which are lifetimes or type names whose bounds go to a
+/// `where` clause in the second form. `new` and `set_mut` check the payload type at run time and
+/// need `'static` payloads, while the typed constructors work for any payload.
+/// ```
+/// use ointer::define_enum_ointers;
+/// use std::sync::Arc;
+/// define_enum_ointers! {
+///     pub enum Node<'a, K, V>: 2 where K: Ord {
+///         Leaf(Box<(K, V)>) = 1,
+///         Inner(Arc<Vec<(K, V)>>) = 2,
+///         Borrowed(&'a (K, V)) = 3,
+///     }
+/// }
+/// let kv = (1, "one");
+/// let n = Node::new_borrowed(&kv);
+/// assert_eq!(n.map_enum(|l| l.0, |i| i[0].0, |b| b.0), 1);
+/// ```
+///
+/// `Debug`, `PartialEq`, `Eq`, `Hash`, `PartialOrd`, `Ord` and `Default` can be derived variant-wise
+/// by a `#[derive(...)]` attribute, or a trailing `derive(...)` list in the first form. Comparisons
+/// look at discriminants first, then at payloads, and `Default` is the variant marked `#[default]`
+/// holding the default payload, or the first variant in the first form. `Clone` is always
+/// implemented.
+/// ```
+/// use ointer::define_enum_ointers;
+/// use std::rc::Rc;
+/// define_enum_ointers! {
+///     #[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+///     pub enum Key: 2 {
+///         #[default]
+///         Nil = 0,
+///         Small(u16) = 1,
+///         Large(Rc<u64>) = 2,
+///     }
+/// }
+/// assert_eq!(Key::default(), Key::new_nil());
+/// assert!(Key::new_small(u16::MAX) < Key::new_large(Rc::new(0)));
+/// assert!(Key::new_large(Rc::new(1)) < Key::new_large(Rc::new(2)));
+/// assert_eq!(format!("{:?}", [Key::new_nil(), Key::new_small(3)]), "[Nil, Small(3)]");
+/// ```
+///
+/// Deriving `Default` without a `#[default]` variant is rejected at compile time:
+/// ```compile_fail
+/// use ointer::define_enum_ointers;
+/// define_enum_ointers! {
+///     #[derive(Default)]
+///     pub enum Key: 2 {
+///         Nil = 0,
+///         Small(u16) = 1,
+///     }
+/// }
+/// ```
+///
+/// Targets of payloads dereferencing to a [`StableDeref`] target, as `Box`, `Rc` and `Arc` do, can be
+/// borrowed for as long as the enum `ointer` by `map_enum_ref`, and mutably by `map_enum_ref_mut`,
+/// which copies shared targets first.
+/// ```
+/// use ointer::define_enum_ointers;
+/// use std::rc::Rc;
+/// define_enum_ointers! {
+///     pub enum List: 1 {
+///         One(Box<u32>) = 0,
+///         Many(Rc<Vec<u32>>) = 1,
+///     }
+/// }
+/// fn first(l: &List) -> &u32 {
+///     l.map_enum_ref(|o| o, |m| &m[0])
+/// }
+/// let mut l = List::new_many(Rc::new(vec![1, 2]));
+/// let m = l.clone();
+/// l.map_enum_ref_mut(|o| *o += 1, |m| m[0] = 3);
+/// assert_eq!((first(&l), first(&m)), (&3, &1));
+/// ```
+///
+/// In the second form discriminants and the bit width are optional. Discriminants are numbered
+/// like those of a Rust `enum`, and the bit width defaults to the minimum that stores them all.
+/// Both are exposed as associated constants, one per variant in upper snake case and `BITS`.
+/// ```
+/// use ointer::define_enum_ointers;
+/// define_enum_ointers! {
+///     pub enum Shape {
+///         Empty,
+///         Circle(f32),
+///         Polygon(Box<Vec<(f32, f32)>>) = 6,
+///         Text(Box<String>),
+///     }
+/// }
+/// assert_eq!([Shape::EMPTY, Shape::CIRCLE, Shape::POLYGON, Shape::TEXT], [0, 1, 6, 7]);
+/// assert_eq!(Shape::BITS, 3);
+/// let s = Shape::new(Shape::CIRCLE, 1.5f32);
+/// assert_eq!(s.map_enum(|_| 0.0, |r| *r, |_| 0.0, |_| 0.0), 1.5);
+/// ```
+///
+/// A `#[weak(Name)]` attribute also defines a weak enum `ointer` `Name` with the same variants and
+/// discriminants, holding the `Weak` of each `Rc`/`Arc` payload, like `define_shared_ointer!` does
+/// for a single pointer. Every payload must implement [`Downgrade`]. Strong enums `downgrade`
+/// variant-wise, and weak enums `upgrade` to `None` once the payload has been dropped.
+/// ```
+/// use ointer::define_enum_ointers;
+/// use std::{rc::Rc, sync::Arc};
+/// define_enum_ointers! {
+///     #[weak(WeakChild)]
+///     pub enum Child {
+///         Leaf(u32),
+///         Local(Rc<String>),
+///         Shared(Arc<Vec<u8>>),
+///     }
+/// }
+/// let c = Child::new_local(Rc::new("a".to_owned()));
+/// let w = c.downgrade();
+/// assert_eq!(WeakChild::LOCAL, Child::LOCAL);
+/// assert!(w.upgrade().is_some());
+/// drop(c);
+/// assert!(w.upgrade().is_none());
+/// let w = Child::new_leaf(7).downgrade();
+/// let c = w.upgrade().unwrap();
+/// assert_eq!(c.map_enum(|i| *i, |_| 0, |_| 0), 7);
+/// ```
+///
+/// `ointer`s defined by this crate take the offset of their stolen bits as a second parameter, `0`
+/// by default, so they can be nested as payloads below the discriminant.
+/// ```
+/// use ointer::{define_enum_ointers, OBox, Ointer};
+/// define_enum_ointers! {
+///     pub enum Edge: 8 {
+///         Boxed(OBox<u32, 8>) = 1,
+///     }
+/// }
+/// let mut o = OBox::<u32, 8>::new_with_layout(3);
+/// o.flip();
+/// let mut e = Edge::new_boxed(o);
+/// e.map_enum_mut(|o| o.flip());
+/// assert_eq!(e.map_enum(|o| (o.o(), **o)), (false, 3));
+/// assert_eq!(e.get_usize(), Edge::BOXED);
+/// ```
+///
+/// Duplicated discriminants, discriminants overflowing the bit width and payloads wider than a
+/// pointer are all rejected at compile time:
+/// ```compile_fail
+/// use ointer::define_enum_ointers;
+/// define_enum_ointers! {
+///     enum Duplicated {
+///         A(Box<u8>) = 1,
+///         B(Box<u16>) = 1,
+///     }
+/// }
+/// ```
+/// ```compile_fail
+/// use ointer::define_enum_ointers;
+/// define_enum_ointers! {
+///     enum Overflowed: 8 {
+///         A(Box<u8>) = 300,
+///     }
+/// }
+/// ```
+///
+/// Inline payloads and nested `ointer`s must fit in the bits left by the discriminant, which is
+/// checked at compile time:
+/// ```compile_fail
+/// use ointer::define_enum_ointers;
+/// define_enum_ointers! {
+///     enum TooWide: 40 {
+///         Int(u32) = 1,
+///     }
+/// }
+/// ```
+/// ```compile_fail
+/// use ointer::{define_enum_ointers, OBox};
+/// define_enum_ointers! {
+///     enum Collided: 8 {
+///         Boxed(OBox<u32, 4>) = 1,
+///     }
+/// }
+/// ```
 #[macro_export]
 macro_rules! define_enum_ointers {
+    (@payload) => { () };
+    (@payload $payload:ty) => { $payload };
+    (@new $variant:ident, $new:ident, $discriminant:ident) => {
+        #[doc = concat!("Create the `", stringify!($variant), "` variant.")]
+        #[inline(always)]
+        pub fn $new() -> Self {
+            unsafe { Self::from_payload($discriminant, ()) }
+        }
+    };
+    (@new $variant:ident, $new:ident, $discriminant:ident, $payload:ty) => {
+        #[doc = concat!("Create the `", stringify!($variant), "` variant holding `p`.")]
+        #[inline(always)]
+        pub fn $new(p: $payload) -> Self {
+            unsafe { Self::from_payload($discriminant, p) }
+        }
+    };
     (
-        $name:ident {
-            $($pointer:ty = $unsigned:literal),*
+        $name:ident $(<$($param:tt),+ $(,)?>)? {
+            $first:ty = $first_unsigned:literal $(, $pointer:ty = $unsigned:literal)* $(,)?
         },
         $bits:literal
+        $(, derive($($derive:ident),* $(,)?))?
     ) => {
-        paste::paste!{
-            #[repr(transparent)]
-            pub struct $name(core::num::NonZeroUsize);
-
-            unsafe impl Ointer<$bits> for $name {
-                type Pointer = core::num::NonZeroUsize;
+        $crate::paste::paste! {
+            $crate::define_enum_ointers! {
+                $(#[derive($($derive),*)])?
+                pub enum $name $(<$($param),+>)?: $bits {
+                    #[default]
+                    [<V $first_unsigned>]($first) = $first_unsigned,
+                    $([<V $unsigned>]($pointer) = $unsigned),*
+                }
             }
-
-            impl $name {
-                #[inline(always)]
-                pub fn new<P: 'static>(u: usize, p: P) -> Self {
-                    use core::any::TypeId;
-                    use core::mem::size_of;
-                    match u {
-                        $($unsigned => {
-                            if TypeId::of::<P>() != TypeId::of::<$pointer>() {
-                                panic!("Unmatched pointer type")
-                            }
-                            if size_of::<P>() > size_of::<usize>() {
-                                panic!("Size overflow")
-                            }
-                            let mut inner = $name(unsafe {
-                                *(&p as *const P as *const core::num::NonZeroUsize)
-                            });
-                            inner.set_usize(u);
-                            core::mem::forget(p);
-                            inner
+        }
+    };
+    (@derives [] $($ctx:tt)*) => {};
+    (@derives [$derive:ident $($derives:ident)*] $($ctx:tt)*) => {
+        $crate::define_enum_ointers!(@derive $derive $($ctx)*);
+        $crate::define_enum_ointers!(@derives [$($derives)*] $($ctx)*);
+    };
+    (@derive Clone $($ctx:tt)*) => {};
+    (
+        @derive Debug $name:ident [$($param:tt)*] [$($wc:tt)*]
+        [$([$variant:ident, $discriminant:ident, ($($payload:ty)?) $($default:ident)?])*]
+    ) => {
+        impl<$($param),*> core::fmt::Debug for $name<$($param),*>
+        where
+            $($crate::define_enum_ointers!(@payload $($payload)?): core::fmt::Debug,)*
+            $($wc)*
+        {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+                let a = self.get_ptr_as_usize();
+                match self.get_usize() {
+                    $($discriminant => {
+                        let g = |p: &$crate::define_enum_ointers!(@payload $($payload)?)| {
+                            $crate::define_enum_ointers!(@debug f, p, $variant $(, $payload)?)
+                        };
+                        unsafe { $crate::map_enum_payload(a, g) }
+                    }),
+                    *,
+                    _ => panic!("Unmatched unsigned num")
+                }
+            }
+        }
+    };
+    (@debug $f:ident, $p:ident, $variant:ident) => {{
+        let _ = $p;
+        $f.write_str(stringify!($variant))
+    }};
+    (@debug $f:ident, $p:ident, $variant:ident, $payload:ty) => {
+        $f.debug_tuple(stringify!($variant)).field($p).finish()
+    };
+    (
+        @derive PartialEq $name:ident [$($param:tt)*] [$($wc:tt)*]
+        [$([$variant:ident, $discriminant:ident, ($($payload:ty)?) $($default:ident)?])*]
+    ) => {
+        impl<$($param),*> core::cmp::PartialEq for $name<$($param),*>
+        where
+            $($crate::define_enum_ointers!(@payload $($payload)?): core::cmp::PartialEq,)*
+            $($wc)*
+        {
+            fn eq(&self, rhs: &Self) -> bool {
+                let u = self.get_usize();
+                let (a, b) = (self.get_ptr_as_usize(), rhs.get_ptr_as_usize());
+                u == rhs.get_usize()
+                    && match u {
+                        $($discriminant => {
+                            let f = <$crate::define_enum_ointers!(@payload $($payload)?)
+                                as core::cmp::PartialEq>::eq;
+                            unsafe { $crate::map_enum_payloads(a, b, f) }
                         }),
                         *,
                         _ => panic!("Unmatched unsigned num")
                     }
+            }
+        }
+    };
+    (
+        @derive Eq $name:ident [$($param:tt)*] [$($wc:tt)*]
+        [$([$variant:ident, $discriminant:ident, ($($payload:ty)?) $($default:ident)?])*]
+    ) => {
+        impl<$($param),*> core::cmp::Eq for $name<$($param),*>
+        where
+            $($crate::define_enum_ointers!(@payload $($payload)?): core::cmp::Eq,)*
+            $($wc)*
+        {
+        }
+    };
+    (
+        @derive PartialOrd $name:ident [$($param:tt)*] [$($wc:tt)*]
+        [$([$variant:ident, $discriminant:ident, ($($payload:ty)?) $($default:ident)?])*]
+    ) => {
+        #[allow(clippy::non_canonical_partial_ord_impl)]
+        impl<$($param),*> core::cmp::PartialOrd for $name<$($param),*>
+        where
+            $($crate::define_enum_ointers!(@payload $($payload)?): core::cmp::PartialOrd,)*
+            $($wc)*
+        {
+            fn partial_cmp(&self, rhs: &Self) -> Option<core::cmp::Ordering> {
+                let u = self.get_usize();
+                let (a, b) = (self.get_ptr_as_usize(), rhs.get_ptr_as_usize());
+                match u.cmp(&rhs.get_usize()) {
+                    core::cmp::Ordering::Equal => match u {
+                        $($discriminant => {
+                            let f = <$crate::define_enum_ointers!(@payload $($payload)?)
+                                as core::cmp::PartialOrd>::partial_cmp;
+                            unsafe { $crate::map_enum_payloads(a, b, f) }
+                        }),
+                        *,
+                        _ => panic!("Unmatched unsigned num")
+                    },
+                    o => Some(o),
                 }
-                #[inline(always)]
-                pub fn set_mut<P: 'static>(&mut self, u: usize, p: P) {
-                    *self = Self::new(u, p);
+            }
+        }
+    };
+    (
+        @derive Ord $name:ident [$($param:tt)*] [$($wc:tt)*]
+        [$([$variant:ident, $discriminant:ident, ($($payload:ty)?) $($default:ident)?])*]
+    ) => {
+        impl<$($param),*> core::cmp::Ord for $name<$($param),*>
+        where
+            $($crate::define_enum_ointers!(@payload $($payload)?): core::cmp::Ord,)*
+            $($wc)*
+        {
+            fn cmp(&self, rhs: &Self) -> core::cmp::Ordering {
+                let u = self.get_usize();
+                let (a, b) = (self.get_ptr_as_usize(), rhs.get_ptr_as_usize());
+                match u.cmp(&rhs.get_usize()) {
+                    core::cmp::Ordering::Equal => match u {
+                        $($discriminant => {
+                            let f = <$crate::define_enum_ointers!(@payload $($payload)?)
+                                as core::cmp::Ord>::cmp;
+                            unsafe { $crate::map_enum_payloads(a, b, f) }
+                        }),
+                        *,
+                        _ => panic!("Unmatched unsigned num")
+                    },
+                    o => o,
                 }
-                #[inline(always)]
-                pub unsafe fn as_ointer<P: Ointer<$bits> + 'static>(&self) -> &P {
-                    &*(self as *const Self as *const P)
+            }
+        }
+    };
+    (
+        @derive Hash $name:ident [$($param:tt)*] [$($wc:tt)*]
+        [$([$variant:ident, $discriminant:ident, ($($payload:ty)?) $($default:ident)?])*]
+    ) => {
+        impl<$($param),*> core::hash::Hash for $name<$($param),*>
+        where
+            $($crate::define_enum_ointers!(@payload $($payload)?): core::hash::Hash,)*
+            $($wc)*
+        {
+            fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+                let u = self.get_usize();
+                let a = self.get_ptr_as_usize();
+                core::hash::Hash::hash(&u, state);
+                match u {
+                    $($discriminant => {
+                        let f = |p: &$crate::define_enum_ointers!(@payload $($payload)?)| {
+                            core::hash::Hash::hash(p, state)
+                        };
+                        unsafe { $crate::map_enum_payload(a, f) }
+                    }),
+                    *,
+                    _ => panic!("Unmatched unsigned num")
                 }
-                #[inline(always)]
-                pub unsafe fn as_ointer_mut<P: Ointer<$bits> + 'static>(&mut self) -> &mut P {
-                    &mut *(self as *mut Self as *mut P)
+            }
+        }
+    };
+    (@derive Default $name:ident [$($param:tt)*] [$($wc:tt)*] []) => {
+        compile_error!(concat!("Deriving `Default` for `", stringify!($name), "` needs a `#[default]` variant"));
+    };
+    (
+        @derive Default $name:ident [$($param:tt)*] [$($wc:tt)*]
+        [[$variant:ident, $discriminant:ident, ($($payload:ty)?) default] $($variants:tt)*]
+    ) => {
+        impl<$($param),*> core::default::Default for $name<$($param),*>
+        where
+            $crate::define_enum_ointers!(@payload $($payload)?): core::default::Default,
+            $($wc)*
+        {
+            #[doc = concat!("Create the `", stringify!($variant), "` variant holding the default payload.")]
+            fn default() -> Self {
+                let p = <$crate::define_enum_ointers!(@payload $($payload)?)
+                    as core::default::Default>::default();
+                unsafe { Self::from_payload($discriminant, p) }
+            }
+        }
+
+        $crate::define_enum_ointers!(@default_unique $name $($variants)*);
+    };
+    (
+        @derive Default $name:ident [$($param:tt)*] [$($wc:tt)*]
+        [[$variant:ident, $discriminant:ident, ($($payload:ty)?)] $($variants:tt)*]
+    ) => {
+        $crate::define_enum_ointers!(@derive Default $name [$($param)*] [$($wc)*] [$($variants)*]);
+    };
+    (@default_unique $name:ident) => {};
+    (@default_unique $name:ident [$variant:ident, $discriminant:ident, ($($payload:ty)?) default] $($variants:tt)*) => {
+        compile_error!(concat!("Multiple `#[default]` variants of `", stringify!($name), "`"));
+    };
+    (@default_unique $name:ident [$variant:ident, $discriminant:ident, ($($payload:ty)?)] $($variants:tt)*) => {
+        $crate::define_enum_ointers!(@default_unique $name $($variants)*);
+    };
+    (@variant_attr) => {};
+    (@variant_attr default) => {};
+    (@variant_attr $attr:ident) => {
+        compile_error!(concat!("Unsupported variant attribute `#[", stringify!($attr), "]` for enum ointers"));
+    };
+    (@derive $derive:ident $($ctx:tt)*) => {
+        compile_error!(concat!("Unsupported derive `", stringify!($derive), "` for enum ointers"));
+    };
+    (
+        @enum [$($attr:tt)*] [$($derives:ident)*] [$($weak:ident)?]
+        #[derive($($derive:ident),* $(,)?)] $($rest:tt)*
+    ) => {
+        $crate::define_enum_ointers!(
+            @enum [$($attr)*] [$($derives)* $($derive)*] [$($weak)?] $($rest)*
+        );
+    };
+    (@enum [$($attr:tt)*] [$($derive:ident)*] [] #[weak($weak:ident)] $($rest:tt)*) => {
+        $crate::define_enum_ointers!(@enum [$($attr)*] [$($derive)*] [$weak] $($rest)*);
+    };
+    (@enum [$($attr:tt)*] [$($derive:ident)*] [$($weak:ident)?] #[$($a:tt)*] $($rest:tt)*) => {
+        $crate::define_enum_ointers!(
+            @enum [$($attr)* #[$($a)*]] [$($derive)*] [$($weak)?] $($rest)*
+        );
+    };
+    (
+        @enum [$($attr:tt)*] [$($derive:ident)*] [$($weak:ident)?]
+        $vis:vis enum $name:ident $(<$($param:tt),+ $(,)?>)? $(: $bits:literal)? { $($body:tt)* }
+    ) => {
+        $crate::define_enum_ointers!(
+            @impl [$($attr)*] [$($derive)*] [$($weak)?] [refs] [$vis] $name [$($($param)*)?] [$($bits)?] []
+            { $($body)* }
+        );
+    };
+    (
+        @enum [$($attr:tt)*] [$($derive:ident)*] [$($weak:ident)?]
+        $vis:vis enum $name:ident $(<$($param:tt),+ $(,)?>)? $(: $bits:literal)? where $($rest:tt)*
+    ) => {
+        $crate::define_enum_ointers!(
+            @where [$($attr)*] [$($derive)*] [$($weak)?] [$vis] $name [$($($param)*)?] [$($bits)?] []
+            $($rest)*
+        );
+    };
+    (
+        @where [$($attr:tt)*] [$($derive:ident)*] [$($weak:ident)?] [$vis:vis] $name:ident
+        [$($param:tt)*] [$($bits:literal)?] [$($wc:tt)*] { $($body:tt)* }
+    ) => {
+        $crate::define_enum_ointers!(
+            @impl [$($attr)*] [$($derive)*] [$($weak)?] [refs] [$vis] $name [$($param)*] [$($bits)?] [$($wc)*]
+            { $($body)* }
+        );
+    };
+    (
+        @where [$($attr:tt)*] [$($derive:ident)*] [$($weak:ident)?] [$vis:vis] $name:ident
+        [$($param:tt)*] [$($bits:literal)?] [$($wc:tt)*] $t:tt $($rest:tt)*
+    ) => {
+        $crate::define_enum_ointers!(
+            @where [$($attr)*] [$($derive)*] [$($weak)?] [$vis] $name [$($param)*] [$($bits)?]
+            [$($wc)* $t] $($rest)*
+        );
+    };
+    (@weak [] $($rest:tt)*) => {};
+    (
+        @weak [$weak:ident] [$vis:vis] $name:ident [$($param:tt)*] [$($bits:literal)?] [$($wc:tt)*]
+        [$([$variant:ident ($($payload:ty)?) ($($discriminant:expr)?)])*]
+    ) => {
+        $crate::define_enum_ointers! {
+            @impl [#[doc = concat!("Weak counterpart of [`", stringify!($name), "`].")]] [] [] []
+            [$vis] $weak [$($param)*] [$($bits)?] [$($wc)*]
+            { $($variant $((<$payload as $crate::Downgrade>::Weak))? $(= $discriminant)?),* }
+        }
+
+        const _: () = {
+            use $crate::Ointer as _;
+
+            impl<$($param),*> $name<$($param),*>
+            where
+                $($crate::define_enum_ointers!(@payload $($payload)?): $crate::Downgrade,)*
+                $($wc)*
+            {
+                /// Create the weak counterpart of `self` variant-wise.
+                pub fn downgrade(&self) -> $weak<$($param),*> {
+                    let u = self.get_usize();
+                    self.map_enum($(
+                        |p: &$crate::define_enum_ointers!(@payload $($payload)?)| {
+                            let w = $crate::Downgrade::downgrade(p);
+                            unsafe { $weak::from_payload(u, w) }
+                        }
+                    ), *)
                 }
+            }
+
+            impl<$($param),*> $weak<$($param),*>
+            where
+                $($crate::define_enum_ointers!(@payload $($payload)?): $crate::Downgrade,)*
+                $($wc)*
+            {
+                /// Attempt to upgrade `self` variant-wise, returning `None` if the payload has been dropped.
+                pub fn upgrade(&self) -> Option<$name<$($param),*>> {
+                    let u = self.get_usize();
+                    self.map_enum($(
+                        |p: &<$crate::define_enum_ointers!(@payload $($payload)?)
+                            as $crate::Downgrade>::Weak| {
+                            let p = $crate::Upgrade::upgrade(p)?;
+                            Some(unsafe { $name::from_payload(u, p) })
+                        }
+                    ), *)
+                }
+            }
+        };
+    };
+    (@refs [] $($ctx:tt)*) => {};
+    (
+        @refs [refs] $name:ident [$($param:tt)*] [$($wc:tt)*]
+        [$([$variant:ident, $discriminant:ident, ($($payload:ty)?) $($default:ident)?])*]
+    ) => {
+        $crate::paste::paste! {
+            // The bounds of these fns are higher-ranked so that they are checked where the fns are called,
+            // not where enums holding payloads without a target are defined. `Deref` is spelled out, as
+            // higher-ranked bounds do not imply supertraits.
+            #[allow(dead_code)]
+            impl<$($param),*> $name<$($param),*>
+            where
+                $($wc)*
+            {
+                /// Map the target of the payload of `self` by the fn of its variant, borrowing it for as
+                /// long as `self`.
                 #[inline(always)]
-                pub fn map_enum<
-                    R,
-                    $([<F $unsigned>]: FnOnce(&$pointer) -> R),
+                pub fn map_enum_ref<'__a, R>(
+                    &'__a self,
+                    $([<f_ $variant:snake>]: impl FnOnce(
+                        &'__a <$crate::define_enum_ointers!(@payload $($payload)?) as core::ops::Deref>::Target
+                    ) -> R),
                     *
-                >(
-                    &self,
-                    $([<f $unsigned>]: [<F $unsigned>]),
-                    *
-                ) -> R {
-                    let u = self.get_usize();
-                    match u {
-                        $($unsigned => {
-                            let mut u = self.get_ptr_as_usize();
-                            let p = unsafe {
-                                &mut *(&mut u as *mut usize as *mut Self)
+                ) -> R
+                where
+                    $(for<'__b> $crate::define_enum_ointers!(@payload $($payload)?): $crate::StableDeref + core::ops::Deref,)*
+                {
+                    let w = self.get_ptr_as_usize();
+                    match self.get_usize() {
+                        $($discriminant => {
+                            let t = unsafe {
+                                $crate::map_enum_payload(
+                                    w,
+                                    |p: &$crate::define_enum_ointers!(@payload $($payload)?)| {
+                                        core::ops::Deref::deref(p) as *const _
+                                    },
+                                )
                             };
-                            p.set_usize(0);
-                            [<f $unsigned>](unsafe {
-                                &*(p as *const Self as *const $pointer)
-                            })
+                            [<f_ $variant:snake>](unsafe { &*t })
                         }),
                         *,
                         _ => panic!("Unmatched unsigned num")
                     }
                 }
+                /// Map the target of the payload of `self` mutably by the fn of its variant, copying it
+                /// first if it is shared, and borrowing it for as long as `self`.
                 #[inline(always)]
-                pub fn map_enum_mut<
-                    R,
-                    $([<F $unsigned>]: FnOnce(&mut $pointer) -> R),
+                pub fn map_enum_ref_mut<'__a, R>(
+                    &'__a mut self,
+                    $([<f_ $variant:snake>]: impl FnOnce(
+                        &'__a mut <$crate::define_enum_ointers!(@payload $($payload)?) as core::ops::Deref>::Target
+                    ) -> R),
                     *
-                >(
-                    &mut self,
-                    $([<f $unsigned>]: [<F $unsigned>]),
-                    *
-                ) -> R {
+                ) -> R
+                where
+                    $(for<'__b> $crate::define_enum_ointers!(@payload $($payload)?): $crate::StableDerefMut + core::ops::Deref,)*
+                {
                     let u = self.get_usize();
+                    let mut w = self.get_ptr_as_usize();
                     match u {
-                        $($unsigned => {
-                            self.set_usize(0);
-                            let r = [<f $unsigned>](unsafe {
-                                &mut *(self as *mut Self as *mut $pointer)
-                            });
+                        $($discriminant => {
+                            let t = unsafe {
+                                $crate::map_enum_payload_mut(
+                                    &mut w,
+                                    |p: &mut $crate::define_enum_ointers!(@payload $($payload)?)| {
+                                        $crate::StableDerefMut::make_mut(p) as *mut _
+                                    },
+                                )
+                            };
+                            self.0 = unsafe { core::num::NonZeroUsize::new_unchecked(w) };
+                            self.assert_stealable();
                             self.set_usize(u);
-                            r
+                            [<f_ $variant:snake>](unsafe { &mut *t })
                         }),
                         *,
                         _ => panic!("Unmatched unsigned num")
                     }
                 }
             }
+        }
+    };
+    (@bits [] $min:expr) => { $min };
+    (@bits [$bits:literal] $min:expr) => { $bits };
+    (
+        @impl [$(#[$attr:meta])*] [$($derive:ident)*] [$($weak:ident)?] [$($refs:ident)?] [$vis:vis]
+        $name:ident
+        [$($param:tt)*]
+        [$($bits:literal)?] [$($wc:tt)*] {
+            $($(#[$variant_attr:ident])? $variant:ident $(($payload:ty))? $(= $discriminant:expr)?),* $(,)?
+        }
+    ) => {
+        $crate::paste::paste! {
+            $(#[$attr])*
+            #[repr(transparent)]
+            $vis struct $name<$($param),*>(
+                core::num::NonZeroUsize,
+                core::marker::PhantomData<($($crate::define_enum_ointers!(@payload $($payload)?),)*)>,
+            )
+            where
+                $($wc)*;
+
+            const _: () = {
+                use $crate::Ointer as _;
+
+                #[allow(dead_code)]
+                #[repr(usize)]
+                enum Discriminant {
+                    $($variant $(= $discriminant)?),*
+                }
+                $(const [<$variant:snake:upper>]: usize = Discriminant::$variant as usize;)*
+                const DISCRIMINANT_BITS: usize = $crate::define_enum_ointers!(
+                    @bits [$($bits)?] $crate::enum_discriminant_bits(&[$([<$variant:snake:upper>]),*])
+                );
+                assert!(
+                    $crate::enum_discriminant_bits(&[$([<$variant:snake:upper>]),*]) <= DISCRIMINANT_BITS
+                        && DISCRIMINANT_BITS < usize::BITS as usize,
+                    concat!("Discriminants of `", stringify!($name), "` overflow the stolen bits")
+                );
 
-            impl core::clone::Clone for $name
+                unsafe impl<$($param),*> $crate::Ointer<DISCRIMINANT_BITS> for $name<$($param),*>
                 where
+                    $($wc)*
+                {
+                    type Pointer = core::num::NonZeroUsize;
+                }
+
+                #[allow(dead_code)]
+                impl<$($param),*> $name<$($param),*>
+                where
+                    $($wc)*
+                {
+                    /// Number of high bits stolen to store the discriminant.
+                    pub const BITS: usize = DISCRIMINANT_BITS;
                     $(
-                        $pointer: core::clone::Clone
-                    ), *
-            {
-                fn clone(&self) -> Self {
-                    self.map_enum($(|p| Self::new($unsigned, p.clone())), *)
+                        #[doc = concat!("Discriminant of the `", stringify!($variant), "` variant.")]
+                        pub const [<$variant:snake:upper>]: usize = [<$variant:snake:upper>];
+                    )*
+                    const PAYLOADS_FIT: () = {
+                        $(
+                            assert!(
+                                core::mem::size_of::<$crate::define_enum_ointers!(@payload $($payload)?)>()
+                                    <= core::mem::size_of::<usize>(),
+                                concat!("Payload of `", stringify!($variant), "` is wider than a pointer")
+                            );
+                            assert!(
+                                $crate::enum_payload_fits::<
+                                    $crate::define_enum_ointers!(@payload $($payload)?)
+                                >(DISCRIMINANT_BITS),
+                                concat!(
+                                    "Payload of `",
+                                    stringify!($variant),
+                                    "` does not fit in the bits left by the discriminant"
+                                )
+                            );
+                        )*
+                    };
+                    #[inline(always)]
+                    unsafe fn from_payload<P: $crate::EnumOinterPayload>(u: usize, p: P) -> Self {
+                        #[allow(clippy::let_unit_value)]
+                        let () = Self::PAYLOADS_FIT;
+                        let mut o = Self(
+                            core::num::NonZeroUsize::new_unchecked(p.into_word()),
+                            core::marker::PhantomData,
+                        );
+                        o.assert_stealable();
+                        o.set_usize(u);
+                        o
+                    }
+                    $(
+                        $crate::define_enum_ointers!(
+                            @new $variant, [<new_ $variant:snake>], [<$variant:snake:upper>]
+                            $(, $payload)?
+                        );
+                    )*
+                    #[inline(always)]
+                    pub fn new<P: 'static>(u: usize, p: P) -> Self
+                    where
+                        Self: 'static,
+                    {
+                        match u {
+                            $([<$variant:snake:upper>] => {
+                                let p: $crate::define_enum_ointers!(@payload $($payload)?) =
+                                    $crate::cast_enum_payload(p)
+                                        .unwrap_or_else(|_| panic!("Unmatched pointer type"));
+                                unsafe { Self::from_payload(u, p) }
+                            }),
+                            *,
+                            _ => panic!("Unmatched unsigned num")
+                        }
+                    }
+                    #[inline(always)]
+                    pub fn set_mut<P: 'static>(&mut self, u: usize, p: P)
+                    where
+                        Self: 'static,
+                    {
+                        *self = Self::new(u, p);
+                    }
+                    /// # Safety
+                    /// The current variant must hold a payload of type `P`.
+                    #[inline(always)]
+                    pub unsafe fn as_ointer<P: $crate::Ointer<DISCRIMINANT_BITS>>(&self) -> &P {
+                        &*(self as *const Self as *const P)
+                    }
+                    /// # Safety
+                    /// The current variant must hold a payload of type `P`.
+                    #[inline(always)]
+                    pub unsafe fn as_ointer_mut<P: $crate::Ointer<DISCRIMINANT_BITS>>(&mut self) -> &mut P {
+                        &mut *(self as *mut Self as *mut P)
+                    }
+                    #[inline(always)]
+                    pub fn map_enum<
+                        R,
+                        $([<F $variant>]: FnOnce(
+                            &$crate::define_enum_ointers!(@payload $($payload)?)
+                        ) -> R),
+                        *
+                    >(
+                        &self,
+                        $([<f_ $variant:snake>]: [<F $variant>]),
+                        *
+                    ) -> R {
+                        let w = self.get_ptr_as_usize();
+                        match self.get_usize() {
+                            $([<$variant:snake:upper>] => unsafe {
+                                $crate::map_enum_payload(w, [<f_ $variant:snake>])
+                            }),
+                            *,
+                            _ => panic!("Unmatched unsigned num")
+                        }
+                    }
+                    #[inline(always)]
+                    pub fn map_enum_mut<
+                        R,
+                        $([<F $variant>]: FnOnce(
+                            &mut $crate::define_enum_ointers!(@payload $($payload)?)
+                        ) -> R),
+                        *
+                    >(
+                        &mut self,
+                        $([<f_ $variant:snake>]: [<F $variant>]),
+                        *
+                    ) -> R {
+                        let u = self.get_usize();
+                        let mut w = self.get_ptr_as_usize();
+                        let r = match u {
+                            $([<$variant:snake:upper>] => unsafe {
+                                $crate::map_enum_payload_mut(&mut w, [<f_ $variant:snake>])
+                            }),
+                            *,
+                            _ => panic!("Unmatched unsigned num")
+                        };
+                        self.0 = unsafe { core::num::NonZeroUsize::new_unchecked(w) };
+                        self.assert_stealable();
+                        self.set_usize(u);
+                        r
+                    }
                 }
-            }
 
-            impl core::ops::Drop for $name {
-                fn drop(&mut self) {
-                    self.map_enum_mut(
-                        $(|p| {
-                            $unsigned;
-                            unsafe {
-                                core::mem::ManuallyDrop::drop(
-                                    &mut *(
-                                        p as *mut $pointer
-                                        as *mut core::mem::ManuallyDrop<$pointer>
-                                    )
-                                );
+                impl<$($param),*> core::clone::Clone for $name<$($param),*>
+                where
+                    $($crate::define_enum_ointers!(@payload $($payload)?): core::clone::Clone,)*
+                    $($wc)*
+                {
+                    fn clone(&self) -> Self {
+                        let u = self.get_usize();
+                        self.map_enum($(
+                            |p: &$crate::define_enum_ointers!(@payload $($payload)?)| unsafe {
+                                Self::from_payload(u, core::clone::Clone::clone(p))
                             }
-                        }), *
-                    );
+                        ), *)
+                    }
                 }
-            }
+
+                impl<$($param),*> core::ops::Drop for $name<$($param),*>
+                where
+                    $($wc)*
+                {
+                    fn drop(&mut self) {
+                        let w = self.get_ptr_as_usize();
+                        match self.get_usize() {
+                            $([<$variant:snake:upper>] => {
+                                let _ = unsafe {
+                                    <$crate::define_enum_ointers!(@payload $($payload)?)
+                                        as $crate::EnumOinterPayload>::from_word(w)
+                                };
+                            }),
+                            *,
+                            _ => panic!("Unmatched unsigned num")
+                        }
+                    }
+                }
+
+                $($crate::define_enum_ointers!(@variant_attr $($variant_attr)?);)*
+                $crate::define_enum_ointers!(
+                    @derives [$($derive)*] $name [$($param)*] [$($wc)*]
+                    [$([$variant, [<$variant:snake:upper>], ($($payload)?) $($variant_attr)?])*]
+                );
+                $crate::define_enum_ointers!(
+                    @refs [$($refs)?] $name [$($param)*] [$($wc)*]
+                    [$([$variant, [<$variant:snake:upper>], ($($payload)?) $($variant_attr)?])*]
+                );
+            };
+
+            $crate::define_enum_ointers!(
+                @weak [$($weak)?] [$vis] $name [$($param)*] [$($bits)?] [$($wc)*]
+                [$([$variant ($($payload)?) ($($discriminant)?)])*]
+            );
         }
     };
+    ($(#[$($attr:tt)*])* $vis:vis enum $($rest:tt)*) => {
+        $crate::define_enum_ointers!(@enum [] [] [] $(#[$($attr)*])* $vis enum $($rest)*);
+    };
 }
 
-pub use define_enum_ointers;
 pub use define_ointer;
+#[doc(hidden)]
+pub use define_ointer_methods;
 pub use define_ointer_strong;
 pub use define_shared_ointer;
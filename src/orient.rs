@@ -0,0 +1,204 @@
+//! This module defines `Soup`, a polygon soup whose faces reference shared edges by `ORc`s, the stolen bit
+//! telling whether a face runs along an edge backward, and orients it consistently by flipping faces, or
+//! finds a cycle of faces reversing orientation, proving a component non-orientable.
+//!
+//! Two faces sharing an edge are consistently oriented when they run along it in opposite directions, that is
+//! when their references to it differ in their bits.
+//! ```
+//! use ointer::orient::Soup;
+//! // A square split into two triangles, the second one backward.
+//! let mut s = Soup::from_polygons(&[[0, 1, 2], [0, 2, 3]]).unwrap();
+//! s.flip_face(1);
+//! assert_eq!((s.face_vertices(1), s.is_consistent(0, 1)), (vec![0, 3, 2], Some(false)));
+//! let c = s.orient();
+//! assert!(c.len() == 1 && c[0].is_orientable());
+//! assert_eq!((s.polygons(), s.is_consistent(0, 1)), (vec![vec![0, 1, 2], vec![0, 2, 3]], Some(true)));
+//! ```
+
+use crate::{mesh::MeshError, ointer::*, rc::ORc};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Edge shared by faces of a [`Soup`], from its smaller vertex to its larger one.
+#[derive(Debug)]
+pub struct SharedEdge {
+    id: usize,
+    vertices: [usize; 2],
+}
+
+impl SharedEdge {
+    /// Get the index in its soup.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+    /// Get the vertices, the smaller one first.
+    pub fn vertices(&self) -> [usize; 2] {
+        self.vertices
+    }
+}
+
+/// Connected component of the faces of a [`Soup`], with a cycle of faces reversing orientation if any.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Component {
+    /// Faces, in the order they were reached.
+    pub faces: Vec<usize>,
+    /// Faces each sharing an edge with the next, and the last with the first, such that carrying an
+    /// orientation around them reverses it.
+    pub cycle: Option<Vec<usize>>,
+}
+
+impl Component {
+    /// Whether the component is orientable.
+    pub fn is_orientable(&self) -> bool {
+        self.cycle.is_none()
+    }
+}
+
+/// Polygon soup, each face a loop of references to shared edges, flipped when run backward.
+pub struct Soup {
+    faces: Vec<Vec<ORc<SharedEdge>>>,
+    edge_faces: Vec<Vec<usize>>,
+}
+
+impl Soup {
+    /// Make a soup of polygons `polygons`, each a loop of at least 3 distinct vertices, sharing edges by their
+    /// vertices.
+    pub fn from_polygons<P: AsRef<[usize]>>(polygons: &[P]) -> Result<Self, MeshError> {
+        let mut edges = HashMap::<[usize; 2], ORc<SharedEdge>>::new();
+        let mut s = Self {
+            faces: Vec::new(),
+            edge_faces: Vec::new(),
+        };
+        for (f, p) in polygons.iter().enumerate() {
+            let p = p.as_ref();
+            let n = p.len();
+            let distinct = p.iter().collect::<HashSet<_>>().len() == n;
+            if n < 3 || !distinct {
+                return Err(MeshError::InvalidFace(f));
+            }
+            let face = (0..n)
+                .map(|i| {
+                    let (a, b) = (p[i], p[(i + 1) % n]);
+                    let vertices = [a.min(b), a.max(b)];
+                    let e = edges.entry(vertices).or_insert_with(|| {
+                        s.edge_faces.push(Vec::new());
+                        ORc::new(SharedEdge {
+                            id: s.edge_faces.len() - 1,
+                            vertices,
+                        })
+                    });
+                    s.edge_faces[e.id].push(f);
+                    let mut e = e.clone();
+                    e.set_bool(a > b);
+                    e
+                })
+                .collect();
+            s.faces.push(face);
+        }
+        Ok(s)
+    }
+    /// Get the number of faces.
+    pub fn num_faces(&self) -> usize {
+        self.faces.len()
+    }
+    /// Get the number of edges.
+    pub fn num_edges(&self) -> usize {
+        self.edge_faces.len()
+    }
+    /// Get the edge references of face `f`, in order.
+    pub fn face(&self, f: usize) -> &[ORc<SharedEdge>] {
+        &self.faces[f]
+    }
+    /// Get the faces having edge `e`.
+    pub fn edge_faces(&self, e: usize) -> &[usize] {
+        &self.edge_faces[e]
+    }
+    /// Get the vertices of face `f`, in order.
+    pub fn face_vertices(&self, f: usize) -> Vec<usize> {
+        self.faces[f]
+            .iter()
+            .map(|e| e.vertices[usize::from(e.o())])
+            .collect()
+    }
+    /// Get the vertices of all faces.
+    pub fn polygons(&self) -> Vec<Vec<usize>> {
+        (0..self.faces.len())
+            .map(|f| self.face_vertices(f))
+            .collect()
+    }
+    /// Reverse face `f`, flipping all its edge references.
+    pub fn flip_face(&mut self, f: usize) {
+        let face = &mut self.faces[f];
+        face.reverse();
+        face.iter_mut().for_each(ORc::flip);
+    }
+    /// Get whether face `f` runs along edge `e` backward, or `None` if it does not have it.
+    fn runs_backward(&self, f: usize, e: usize) -> Option<bool> {
+        self.faces[f].iter().find(|r| r.id == e).map(ORc::o)
+    }
+    /// Get whether faces `f` and `g` are consistently oriented along their first shared edge, or `None` if
+    /// they share none.
+    pub fn is_consistent(&self, f: usize, g: usize) -> Option<bool> {
+        self.faces[f].iter().find_map(|r| {
+            let b = self.runs_backward(g, r.id)?;
+            Some(r.o() != b)
+        })
+    }
+    /// Orient each connected component consistently, by flipping faces reached inconsistently from a face
+    /// kept as is, and return the components, with a cycle of faces reversing orientation for the
+    /// non-orientable ones, which are left consistent along a spanning tree only.
+    pub fn orient(&mut self) -> Vec<Component> {
+        let n = self.faces.len();
+        let mut parent = vec![None; n];
+        let mut depth = vec![usize::MAX; n];
+        let mut components = Vec::new();
+        for root in 0..n {
+            if depth[root] != usize::MAX {
+                continue;
+            }
+            depth[root] = 0;
+            let mut c = Component {
+                faces: vec![root],
+                cycle: None,
+            };
+            let mut queue = VecDeque::from([root]);
+            while let Some(f) = queue.pop_front() {
+                for i in 0..self.faces[f].len() {
+                    let (e, b) = (self.faces[f][i].id, self.faces[f][i].o());
+                    for j in 0..self.edge_faces[e].len() {
+                        let g = self.edge_faces[e][j];
+                        let inconsistent = g != f && self.runs_backward(g, e) == Some(b);
+                        if depth[g] == usize::MAX {
+                            if inconsistent {
+                                self.flip_face(g);
+                            }
+                            (parent[g], depth[g]) = (Some(f), depth[f] + 1);
+                            c.faces.push(g);
+                            queue.push_back(g);
+                        } else if inconsistent && c.cycle.is_none() {
+                            c.cycle = Some(Self::tree_cycle(&parent, &depth, f, g));
+                        }
+                    }
+                }
+            }
+            components.push(c);
+        }
+        components
+    }
+    /// Get the path in the spanning tree from face `f` up to the common ancestor and down to face `g`.
+    fn tree_cycle(parent: &[Option<usize>], depth: &[usize], f: usize, g: usize) -> Vec<usize> {
+        let (mut a, mut b) = (vec![f], vec![g]);
+        let (mut x, mut y) = (f, g);
+        while x != y {
+            if depth[x] >= depth[y] {
+                x = parent[x].unwrap();
+                a.push(x);
+            } else {
+                y = parent[y].unwrap();
+                b.push(y);
+            }
+        }
+        b.pop();
+        a.extend(b.into_iter().rev());
+        a
+    }
+}
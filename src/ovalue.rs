@@ -0,0 +1,254 @@
+//! This module defines `OValue`, a one-word value of a language runtime, either an inline fixnum or an `Rc` to a
+//! heap [`Object`], told apart by the lowest bit, with checked arithmetic promoting fixnums to boxed big integers.
+//!
+//! Fixnums are stored shifted left by [`OValue::TAG_BITS`] with the lowest bit `1`, while objects are stored as
+//! their address, aligned so that the lowest bit is `0`. Integers in the fixnum range are always fixnums, so
+//! that equal integers compare and hash the same.
+//!
+//! `OValue` is not defined by `define_enum_ointers!`, whose tags take high bits and whose inline payloads take
+//! at most 32 bits, while fixnums here take all bits but the lowest one. It reuses the word conversions of
+//! [`EnumOinterPayload`] and the `map_enum`/`map_enum_mut` dispatch of enum `ointer`s instead.
+//! ```
+//! use ointer::ovalue::{OValue, OValueRef, Object};
+//! let a = OValue::<()>::new_int(OValue::<()>::MAX_FIXNUM);
+//! let b = a.checked_add(&OValue::new_int(1)).unwrap();
+//! assert!(a.is_fixnum() && !b.is_fixnum());
+//! match b.view() {
+//!     OValueRef::Int(_) => unreachable!(),
+//!     OValueRef::Object(o) => assert_eq!(o, &Object::Big(OValue::<()>::MAX_FIXNUM as i128 + 1)),
+//! }
+//! let c = b.checked_sub(&OValue::new_int(1)).unwrap();
+//! assert_eq!(c.view(), OValueRef::Int(OValue::<()>::MAX_FIXNUM));
+//! ```
+
+use crate::ointer::*;
+use core::{marker::PhantomData, num::NonZeroUsize};
+use std::rc::Rc;
+
+/// Heap object pointed by an [`OValue`], either a big integer not fitting in a fixnum or a custom object `T`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Object<T> {
+    Big(i128),
+    Custom(T),
+}
+
+/// Borrowed view of an [`OValue`], to `match` on fixnum vs object.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum OValueRef<'a, T> {
+    Int(isize),
+    Object(&'a Object<T>),
+}
+
+impl<T> Clone for OValueRef<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for OValueRef<'_, T> {}
+
+/// One-word value, either an inline fixnum or an `Rc<Object<T>>`.
+/// ```
+/// use ointer::ovalue::{OValue, Object};
+/// use std::rc::Rc;
+/// let s = OValue::new_object("s");
+/// let t = s.clone();
+/// assert_eq!(t.map_enum(|_| 0, Rc::strong_count), 2);
+/// assert_eq!(s.get_usize(), OValue::<&str>::OBJECT);
+/// assert_eq!(s.checked_mul(&t), None);
+/// let mut i = OValue::<&str>::new_int(-4);
+/// i.map_enum_mut(|i| *i *= 2, |_| ());
+/// assert_eq!((i.as_fixnum(), i.get_usize()), (Some(-8), OValue::<&str>::INT));
+/// ```
+#[repr(transparent)]
+pub struct OValue<T = ()>(NonZeroUsize, PhantomData<Rc<Object<T>>>);
+
+impl<T> OValue<T> {
+    /// Number of low bits tagging fixnums.
+    pub const TAG_BITS: usize = 1;
+    /// Tag of objects.
+    pub const OBJECT: usize = 0;
+    /// Tag of fixnums.
+    pub const INT: usize = 1;
+    /// Smallest fixnum.
+    pub const MIN_FIXNUM: isize = isize::MIN >> Self::TAG_BITS;
+    /// Largest fixnum.
+    pub const MAX_FIXNUM: isize = isize::MAX >> Self::TAG_BITS;
+    const ALIGNED: () = assert!(
+        core::mem::align_of::<Object<T>>() > Self::INT,
+        "Objects must leave the lowest bit free"
+    );
+
+    fn from_word(u: usize) -> Self {
+        Self(NonZeroUsize::new(u).unwrap(), PhantomData)
+    }
+    /// Create a fixnum if `i` fits, or a boxed big integer otherwise.
+    pub fn new_int(i: isize) -> Self {
+        Self::from_int(i as i128)
+    }
+    fn fits(i: i128) -> bool {
+        (Self::MIN_FIXNUM as i128..=Self::MAX_FIXNUM as i128).contains(&i)
+    }
+    /// Create a fixnum if `i` fits, or a boxed big integer otherwise.
+    pub fn from_int(i: i128) -> Self {
+        if Self::fits(i) {
+            Self::from_word(((i as isize as usize) << Self::TAG_BITS) | Self::INT)
+        } else {
+            Self::from_rc(Rc::new(Object::Big(i)))
+        }
+    }
+    /// Create a boxed custom object.
+    pub fn new_object(x: T) -> Self {
+        Self::from_rc(Rc::new(Object::Custom(x)))
+    }
+    /// Create a value pointing to object `p`, or a fixnum if `p` is a big integer fitting in one.
+    pub fn from_rc(p: Rc<Object<T>>) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let _ = Self::ALIGNED;
+        match *p {
+            Object::Big(i) if Self::fits(i) => Self::from_int(i),
+            _ => Self::from_word(EnumOinterPayload::into_word(p)),
+        }
+    }
+    /// Get the tag, [`OValue::INT`] or [`OValue::OBJECT`].
+    pub fn get_usize(&self) -> usize {
+        self.0.get() & Self::INT
+    }
+    /// Whether `self` is a fixnum.
+    pub fn is_fixnum(&self) -> bool {
+        self.get_usize() == Self::INT
+    }
+    /// Get the fixnum, if any.
+    pub fn as_fixnum(&self) -> Option<isize> {
+        self.is_fixnum()
+            .then(|| self.0.get() as isize >> Self::TAG_BITS)
+    }
+    /// Get the integer held by a fixnum or a boxed big integer, if any.
+    pub fn as_int(&self) -> Option<i128> {
+        match self.view() {
+            OValueRef::Int(i) => Some(i as i128),
+            OValueRef::Object(Object::Big(i)) => Some(*i),
+            OValueRef::Object(Object::Custom(_)) => None,
+        }
+    }
+    /// Borrow `self` as an [`OValueRef`].
+    pub fn view(&self) -> OValueRef<'_, T> {
+        match self.as_fixnum() {
+            Some(i) => OValueRef::Int(i),
+            None => OValueRef::Object(unsafe { &*(self.0.get() as *const Object<T>) }),
+        }
+    }
+    /// Map the fixnum or the object of `self` by fn `int` or `object`.
+    pub fn map_enum<R>(
+        &self,
+        int: impl FnOnce(isize) -> R,
+        object: impl FnOnce(&Rc<Object<T>>) -> R,
+    ) -> R {
+        match self.as_fixnum() {
+            Some(i) => int(i),
+            None => unsafe { map_enum_payload(self.0.get(), object) },
+        }
+    }
+    /// Map the fixnum or the object of `self` mutably by fn `int` or `object`, then store changes back,
+    /// promoting a fixnum out of range to a big integer, and demoting a big integer in range to a fixnum.
+    pub fn map_enum_mut<R>(
+        &mut self,
+        int: impl FnOnce(&mut isize) -> R,
+        object: impl FnOnce(&mut Rc<Object<T>>) -> R,
+    ) -> R {
+        match self.as_fixnum() {
+            Some(mut i) => {
+                let r = int(&mut i);
+                *self = Self::new_int(i);
+                r
+            }
+            None => {
+                let mut u = self.0.get();
+                let r = unsafe { map_enum_payload_mut(&mut u, object) };
+                self.0 = NonZeroUsize::new(u).unwrap();
+                if let OValueRef::Object(&Object::Big(i)) = self.view() {
+                    if Self::fits(i) {
+                        *self = Self::from_int(i);
+                    }
+                }
+                r
+            }
+        }
+    }
+    fn checked_op(
+        &self,
+        rhs: &Self,
+        fixnum: impl FnOnce(isize, isize) -> Option<isize>,
+        big: impl FnOnce(i128, i128) -> Option<i128>,
+    ) -> Option<Self> {
+        match (self.as_fixnum(), rhs.as_fixnum()) {
+            (Some(a), Some(b)) => match fixnum(a, b) {
+                Some(c) => Some(Self::new_int(c)),
+                None => big(a as i128, b as i128).map(Self::from_int),
+            },
+            _ => big(self.as_int()?, rhs.as_int()?).map(Self::from_int),
+        }
+    }
+    /// Add integers, promoting to or demoting from a big integer, or return `None` on objects or overflow.
+    pub fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        self.checked_op(rhs, isize::checked_add, i128::checked_add)
+    }
+    /// Subtract integers, promoting to or demoting from a big integer, or return `None` on objects or overflow.
+    pub fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+        self.checked_op(rhs, isize::checked_sub, i128::checked_sub)
+    }
+    /// Multiply integers, promoting to or demoting from a big integer, or return `None` on objects or overflow.
+    pub fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        self.checked_op(rhs, isize::checked_mul, i128::checked_mul)
+    }
+    /// Negate an integer, promoting to or demoting from a big integer, or return `None` on objects or overflow.
+    pub fn checked_neg(&self) -> Option<Self> {
+        self.as_int()?.checked_neg().map(Self::from_int)
+    }
+}
+
+impl<T> From<isize> for OValue<T> {
+    fn from(i: isize) -> Self {
+        Self::new_int(i)
+    }
+}
+
+impl<T> Default for OValue<T> {
+    fn default() -> Self {
+        Self::new_int(0)
+    }
+}
+
+impl<T> Clone for OValue<T> {
+    fn clone(&self) -> Self {
+        self.map_enum(Self::new_int, |p| Self::from_rc(p.clone()))
+    }
+}
+
+impl<T> Drop for OValue<T> {
+    fn drop(&mut self) {
+        if !self.is_fixnum() {
+            let _ = unsafe { <Rc<Object<T>> as EnumOinterPayload>::from_word(self.0.get()) };
+        }
+    }
+}
+
+impl<T: core::fmt::Debug> core::fmt::Debug for OValue<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+        self.view().fmt(f)
+    }
+}
+
+impl<T: PartialEq> PartialEq for OValue<T> {
+    fn eq(&self, rhs: &Self) -> bool {
+        self.view() == rhs.view()
+    }
+}
+
+impl<T: Eq> Eq for OValue<T> {}
+
+impl<T: core::hash::Hash> core::hash::Hash for OValue<T> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.view().hash(state)
+    }
+}
@@ -0,0 +1,389 @@
+//! This module defines the quad-edge structure of Guibas and Stolfi, where an edge reference is an index of an
+//! edge record in an [`OArena`] with 16 bits stolen, 2 for the rotation, 1 for the flip and 13 for the generation
+//! of the record, and `Delaunay`, an incremental Delaunay triangulation built on it.
+//! ```
+//! use ointer::quadedge::QuadEdges;
+//! let mut q = QuadEdges::new();
+//! let a = q.make_edge('a', 'b');
+//! let b = q.make_edge('b', 'c');
+//! q.splice(a.sym(), b);
+//! let c = q.connect(b, a);
+//! assert_eq!((q.org(c), q.dest(c)), (Some(&'c'), Some(&'a')));
+//! assert_eq!((q.lnext(a), q.lnext(b), q.lnext(c)), (b, c, a));
+//! assert_eq!((a.rot().rot(), a.rot().flip().rot()), (a.sym(), a.flip()));
+//! ```
+
+use crate::{oindex::*, ointer::*};
+
+/// Number of stolen bits holding the generation of an edge record, above the rotation and the flip.
+const GENERATION_BITS: usize = 13;
+
+/// Reference to a directed, oriented edge: the index of its record, with its rotation, flip and the generation
+/// of its record in the stolen bits.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct QuadEdge(OIndex<(), { GENERATION_BITS + 3 }>);
+
+impl QuadEdge {
+    fn new(slot: OIndex<(), GENERATION_BITS>, r: usize, f: bool) -> Self {
+        let mut o = OIndex::new(slot.index());
+        o.set_usize(slot.get_usize() << 3 | usize::from(f) << 2 | r);
+        Self(o)
+    }
+    /// Get the index of the edge record.
+    pub fn index(&self) -> usize {
+        self.0.index()
+    }
+    fn slot<T>(&self) -> OIndex<T, GENERATION_BITS> {
+        let mut o = OIndex::new(self.index());
+        o.set_usize(self.0.get_usize() >> 3);
+        o
+    }
+    /// Get the rotation, from `0` to `3`, even for primal edges and odd for dual ones.
+    pub fn r(&self) -> usize {
+        self.0.get_usize() & 3
+    }
+    /// Whether the edge is flipped.
+    pub fn is_flipped(&self) -> bool {
+        self.0.get_usize() & 4 != 0
+    }
+    /// Rotate by a quarter turn counterclockwise, the dual edge from right to left.
+    pub fn rot(self) -> Self {
+        let f = self.is_flipped();
+        Self::new(self.slot(), (self.r() + 1 + 2 * usize::from(f)) & 3, f)
+    }
+    /// Rotate by a quarter turn clockwise.
+    pub fn rot_inv(self) -> Self {
+        let f = self.is_flipped();
+        Self::new(self.slot(), (self.r() + 3 + 2 * usize::from(f)) & 3, f)
+    }
+    /// Reverse the direction.
+    pub fn sym(self) -> Self {
+        Self::new(self.slot(), (self.r() + 2) & 3, self.is_flipped())
+    }
+    /// Swap left and right, keeping the direction.
+    pub fn flip(self) -> Self {
+        Self::new(self.slot(), self.r(), !self.is_flipped())
+    }
+}
+
+impl core::fmt::Debug for QuadEdge {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+        (self.index(), self.r(), self.is_flipped()).fmt(f)
+    }
+}
+
+/// Edge record: the `onext` of each unflipped rotation, and the data at its origin.
+struct Quad<T> {
+    next: [QuadEdge; 4],
+    data: [Option<T>; 4],
+}
+
+/// Quad-edge structure with data `T` at the origin of edges, vertices for primal edges and faces for dual ones.
+///
+/// Edges of a record removed by [`QuadEdges::delete_edge`] are stale: they make methods panic, even once the
+/// record is reused, unless `2^13` removals of that record wrapped its generation around.
+pub struct QuadEdges<T> {
+    quads: OArena<Quad<T>, GENERATION_BITS>,
+}
+
+impl<T> Default for QuadEdges<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> QuadEdges<T> {
+    /// Make an empty structure.
+    pub const fn new() -> Self {
+        Self {
+            quads: OArena::new(),
+        }
+    }
+    fn quad(&self, e: QuadEdge) -> &Quad<T> {
+        &self.quads[e.slot()]
+    }
+    fn quad_mut(&mut self, e: QuadEdge) -> &mut Quad<T> {
+        &mut self.quads[e.slot()]
+    }
+    /// Whether `e` is not stale.
+    pub fn contains(&self, e: QuadEdge) -> bool {
+        self.quads.contains(e.slot())
+    }
+    /// Get the number of edges.
+    pub fn len(&self) -> usize {
+        self.quads.len()
+    }
+    /// Whether there is no edge.
+    pub fn is_empty(&self) -> bool {
+        self.quads.is_empty()
+    }
+    /// Iterate over one primal edge per record.
+    pub fn edges(&self) -> impl Iterator<Item = QuadEdge> + '_ {
+        self.quads
+            .iter()
+            .map(|(i, _)| QuadEdge::new(i.cast(), 0, false))
+    }
+    /// Make an isolated edge from `org` to `dest`, its own `onext`, on a sphere with one face.
+    pub fn make_edge(&mut self, org: T, dest: T) -> QuadEdge {
+        let i = self.quads.insert(Quad {
+            next: [QuadEdge::new(OIndex::new(0), 0, false); 4],
+            data: [Some(org), None, Some(dest), None],
+        });
+        let e = |r| QuadEdge::new(i.cast(), r, false);
+        self.quads[i].next = [e(0), e(3), e(2), e(1)];
+        e(0)
+    }
+    /// Get the data at the origin of `e`.
+    pub fn org(&self, e: QuadEdge) -> Option<&T> {
+        self.quad(e).data[e.r()].as_ref()
+    }
+    /// Get the data at the destination of `e`.
+    pub fn dest(&self, e: QuadEdge) -> Option<&T> {
+        self.org(e.sym())
+    }
+    /// Set the data at the origin of `e`.
+    pub fn set_org(&mut self, e: QuadEdge, x: T) {
+        self.quad_mut(e).data[e.r()] = Some(x);
+    }
+    /// Set the data at the destination of `e`.
+    pub fn set_dest(&mut self, e: QuadEdge, x: T) {
+        self.set_org(e.sym(), x);
+    }
+    /// Get the next edge counterclockwise around the origin of `e`.
+    pub fn onext(&self, e: QuadEdge) -> QuadEdge {
+        let q = self.quad(e);
+        if e.is_flipped() {
+            q.next[(e.r() + 1) & 3].rot().flip()
+        } else {
+            q.next[e.r()]
+        }
+    }
+    /// Get the next edge clockwise around the origin of `e`.
+    pub fn oprev(&self, e: QuadEdge) -> QuadEdge {
+        self.onext(e.rot()).rot()
+    }
+    /// Get the next edge counterclockwise around the left face of `e`.
+    pub fn lnext(&self, e: QuadEdge) -> QuadEdge {
+        self.onext(e.rot_inv()).rot()
+    }
+    /// Get the next edge clockwise around the left face of `e`.
+    pub fn lprev(&self, e: QuadEdge) -> QuadEdge {
+        self.onext(e).sym()
+    }
+    /// Get the next edge counterclockwise around the right face of `e`.
+    pub fn rnext(&self, e: QuadEdge) -> QuadEdge {
+        self.onext(e.rot()).rot_inv()
+    }
+    /// Get the next edge clockwise around the right face of `e`.
+    pub fn rprev(&self, e: QuadEdge) -> QuadEdge {
+        self.onext(e.sym())
+    }
+    /// Get the next edge counterclockwise around the destination of `e`.
+    pub fn dnext(&self, e: QuadEdge) -> QuadEdge {
+        self.onext(e.sym()).sym()
+    }
+    /// Get the next edge clockwise around the destination of `e`.
+    pub fn dprev(&self, e: QuadEdge) -> QuadEdge {
+        self.onext(e.rot_inv()).rot_inv()
+    }
+    fn set_onext(&mut self, e: QuadEdge, n: QuadEdge) {
+        assert!(!e.is_flipped(), "Splicing flipped edges");
+        self.quad_mut(e).next[e.r()] = n;
+    }
+    /// Join the rings around the origins of `a` and `b` if they differ, or split them otherwise.
+    pub fn splice(&mut self, a: QuadEdge, b: QuadEdge) {
+        let alpha = self.onext(a).rot();
+        let beta = self.onext(b).rot();
+        let (an, bn) = (self.onext(a), self.onext(b));
+        let (alphan, betan) = (self.onext(alpha), self.onext(beta));
+        self.set_onext(a, bn);
+        self.set_onext(b, an);
+        self.set_onext(alpha, betan);
+        self.set_onext(beta, alphan);
+    }
+    /// Remove edge `e`, disconnecting it from the rest of the structure, and making it stale.
+    pub fn delete_edge(&mut self, e: QuadEdge) {
+        self.splice(e, self.oprev(e));
+        self.splice(e.sym(), self.oprev(e.sym()));
+        self.quads.remove(e.slot());
+    }
+}
+
+impl<T: Clone> QuadEdges<T> {
+    /// Add an edge from the destination of `a` to the origin of `b`, so that `a`, the new edge and `b`
+    /// share the same left face.
+    pub fn connect(&mut self, a: QuadEdge, b: QuadEdge) -> QuadEdge {
+        let org = self.dest(a).expect("No data at the destination").clone();
+        let dest = self.org(b).expect("No data at the origin").clone();
+        let e = self.make_edge(org, dest);
+        self.splice(e, self.lnext(a));
+        self.splice(e.sym(), b);
+        e
+    }
+    /// Turn edge `e` counterclockwise within the quadrilateral formed by its two faces.
+    pub fn swap(&mut self, e: QuadEdge) {
+        let a = self.oprev(e);
+        let b = self.oprev(e.sym());
+        self.splice(e, a);
+        self.splice(e.sym(), b);
+        self.splice(e, self.lnext(a));
+        self.splice(e.sym(), self.lnext(b));
+        let (org, dest) = (self.dest(a).cloned(), self.dest(b).cloned());
+        self.quad_mut(e).data[e.r()] = org;
+        self.quad_mut(e).data[e.sym().r()] = dest;
+    }
+}
+
+/// Incremental Delaunay triangulation of points in the plane, built on [`QuadEdges`] holding point indices.
+///
+/// Points are inserted within a bounding triangle, whose three corners are the points `0`, `1` and `2`.
+/// ```
+/// use ointer::quadedge::Delaunay;
+/// let mut d = Delaunay::new(10.0);
+/// for p in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.1)] {
+///     d.insert(p);
+/// }
+/// let mut t = d.triangles();
+/// t.sort();
+/// assert_eq!(t, [[3, 4, 5], [4, 6, 5]]);
+/// ```
+pub struct Delaunay {
+    edges: QuadEdges<usize>,
+    points: Vec<(f64, f64)>,
+    start: QuadEdge,
+}
+
+fn ccw(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> f64 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+fn in_circle(a: (f64, f64), b: (f64, f64), c: (f64, f64), d: (f64, f64)) -> bool {
+    let lift = |p: (f64, f64)| {
+        (
+            p.0 - d.0,
+            p.1 - d.1,
+            (p.0 - d.0).powi(2) + (p.1 - d.1).powi(2),
+        )
+    };
+    let (a, b, c) = (lift(a), lift(b), lift(c));
+    a.0 * (b.1 * c.2 - c.1 * b.2) - a.1 * (b.0 * c.2 - c.0 * b.2) + a.2 * (b.0 * c.1 - c.0 * b.1)
+        > 0.0
+}
+
+impl Delaunay {
+    /// Make a triangulation of the bounding triangle enclosing the disk of radius `r` around the origin.
+    pub fn new(r: f64) -> Self {
+        let r = r * 1e6;
+        let points = vec![(-r, -r), (r, -r), (0.0, r)];
+        let mut edges = QuadEdges::new();
+        let a = edges.make_edge(0, 1);
+        let b = edges.make_edge(1, 2);
+        edges.splice(a.sym(), b);
+        edges.connect(b, a);
+        Self {
+            edges,
+            points,
+            start: a,
+        }
+    }
+    /// Get the underlying quad-edge structure.
+    pub fn edges(&self) -> &QuadEdges<usize> {
+        &self.edges
+    }
+    /// Get the points, the corners of the bounding triangle first.
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+    fn org(&self, e: QuadEdge) -> (f64, f64) {
+        self.points[*self.edges.org(e).unwrap()]
+    }
+    fn dest(&self, e: QuadEdge) -> (f64, f64) {
+        self.points[*self.edges.dest(e).unwrap()]
+    }
+    fn right_of(&self, x: (f64, f64), e: QuadEdge) -> bool {
+        ccw(x, self.dest(e), self.org(e)) > 0.0
+    }
+    fn left_of(&self, x: (f64, f64), e: QuadEdge) -> bool {
+        ccw(x, self.org(e), self.dest(e)) > 0.0
+    }
+    /// Walk to an edge of the triangle containing `x`, or to an edge `x` is an end of.
+    fn locate(&self, x: (f64, f64)) -> QuadEdge {
+        let mut e = self.start;
+        loop {
+            if x == self.org(e) || x == self.dest(e) {
+                return e;
+            } else if self.right_of(x, e) {
+                e = e.sym();
+            } else if !self.right_of(x, self.edges.onext(e)) {
+                e = self.edges.onext(e);
+            } else if !self.right_of(x, self.edges.dprev(e)) {
+                e = self.edges.dprev(e);
+            } else {
+                return e;
+            }
+        }
+    }
+    /// Insert point `x`, strictly inside the bounding triangle, and return its index, or the index of the
+    /// point equal to it.
+    pub fn insert(&mut self, x: (f64, f64)) -> usize {
+        let mut e = self.locate(x);
+        if x == self.org(e) {
+            return *self.edges.org(e).unwrap();
+        } else if x == self.dest(e) {
+            return *self.edges.dest(e).unwrap();
+        }
+        let i = self.points.len();
+        self.points.push(x);
+        if ccw(self.org(e), x, self.dest(e)) == 0.0 {
+            // On edge `e`, which is replaced by the edges to `x`.
+            e = self.edges.oprev(e);
+            let d = self.edges.onext(e);
+            if d == self.start || d.sym() == self.start {
+                self.start = e;
+            }
+            self.edges.delete_edge(d);
+        }
+        let org = *self.edges.org(e).unwrap();
+        let mut base = self.edges.make_edge(org, i);
+        self.edges.splice(base, e);
+        let first = base;
+        loop {
+            base = self.edges.connect(e, base.sym());
+            e = self.edges.oprev(base);
+            if self.edges.lnext(e) == first {
+                break;
+            }
+        }
+        loop {
+            let t = self.edges.oprev(e);
+            if self.right_of(self.dest(t), e)
+                && in_circle(self.org(e), self.dest(t), self.dest(e), x)
+            {
+                self.edges.swap(e);
+                e = self.edges.oprev(e);
+            } else if self.edges.onext(e) == first {
+                break;
+            } else {
+                e = self.edges.lprev(self.edges.onext(e));
+            }
+        }
+        self.start = first;
+        i
+    }
+    /// Get the triangles of inserted points, counterclockwise, each starting from its smallest index.
+    pub fn triangles(&self) -> Vec<[usize; 3]> {
+        let mut t = Vec::new();
+        for e in self.edges.edges().flat_map(|e| [e, e.sym()]) {
+            let (a, b) = (self.edges.lnext(e), self.edges.lprev(e));
+            let v = [e, a, b].map(|e| *self.edges.org(e).unwrap());
+            if self.edges.lnext(a) == b
+                && v.iter().all(|&v| v > 2)
+                && v[0] < v[1].min(v[2])
+                && self.left_of(self.points[v[2]], e)
+            {
+                t.push(v);
+            }
+        }
+        t
+    }
+}
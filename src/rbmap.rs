@@ -0,0 +1,410 @@
+//! This module defines `RbMap`, a persistent ordered map as a left-leaning red-black tree, whose nodes are
+//! shared by `ORc`s with the colour of the node pointed in the stolen bit, and `ArcRbMap`, the same over `OArc`s
+//! to be `Send` and `Sync`.
+//!
+//! Cloning a map is `O(1)`, and a map modified afterwards copies only the path to the nodes it changes, while
+//! recolouring a child only changes the pointer to it in its parent.
+//! ```
+//! use ointer::rbmap::RbMap;
+//! let mut a: RbMap<i32, &str> = (0..5).map(|i| (i, "a")).collect();
+//! let b = a.clone();
+//! assert_eq!(a.insert(2, "b"), Some("a"));
+//! assert_eq!(a.remove(&0), Some("a"));
+//! assert_eq!(a.range(1..3).collect::<Vec<_>>(), [(&1, &"a"), (&2, &"b")]);
+//! assert_eq!(b.iter().map(|(_, v)| *v).collect::<String>(), "aaaaa");
+//! assert!(a.check() && b.check());
+//! ```
+
+use crate::{ointer::*, rc::ORc, sync::OArc};
+use core::{cmp::Ordering, ops::Bound, ops::RangeBounds};
+
+macro_rules! define_rb_map {
+    ($(#[$attr:meta])* $map:ident, $iter:ident, $node:ident, $ointer:ident) => {
+        #[derive(Clone)]
+        struct $node<K, V> {
+            key: K,
+            value: V,
+            left: Option<$ointer<$node<K, V>>>,
+            right: Option<$ointer<$node<K, V>>>,
+        }
+
+        type Link<K, V> = Option<$ointer<$node<K, V>>>;
+
+        $(#[$attr])*
+        pub struct $map<K, V> {
+            root: Link<K, V>,
+            len: usize,
+        }
+
+        impl<K, V> Clone for $map<K, V> {
+            fn clone(&self) -> Self {
+                Self {
+                    root: self.root.clone(),
+                    len: self.len,
+                }
+            }
+        }
+
+        impl<K, V> Default for $map<K, V> {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        fn is_red<K, V>(link: &Link<K, V>) -> bool {
+            link.as_ref().is_some_and(|n| n.o())
+        }
+
+        fn is_red_left<K, V>(link: &Link<K, V>) -> bool {
+            link.as_ref().is_some_and(|n| is_red(&n.left))
+        }
+
+        /// Get the node of `n` mutably, copying it first if shared.
+        fn node_mut<K: Clone, V: Clone>(n: &mut $ointer<$node<K, V>>) -> &mut $node<K, V> {
+            n.make_mut()
+        }
+
+        fn rotate_left<K: Clone, V: Clone>(mut h: $ointer<$node<K, V>>) -> $ointer<$node<K, V>> {
+            let mut x = node_mut(&mut h).right.take().unwrap();
+            node_mut(&mut h).right = node_mut(&mut x).left.take();
+            x.set_bool(h.o());
+            h.set_bool(true);
+            node_mut(&mut x).left = Some(h);
+            x
+        }
+
+        fn rotate_right<K: Clone, V: Clone>(mut h: $ointer<$node<K, V>>) -> $ointer<$node<K, V>> {
+            let mut x = node_mut(&mut h).left.take().unwrap();
+            node_mut(&mut h).left = node_mut(&mut x).right.take();
+            x.set_bool(h.o());
+            h.set_bool(true);
+            node_mut(&mut x).right = Some(h);
+            x
+        }
+
+        fn flip_colors<K: Clone, V: Clone>(h: &mut $ointer<$node<K, V>>) {
+            h.flip();
+            let n = node_mut(h);
+            n.left.as_mut().unwrap().flip();
+            n.right.as_mut().unwrap().flip();
+        }
+
+        fn fix_up<K: Clone, V: Clone>(mut h: $ointer<$node<K, V>>) -> $ointer<$node<K, V>> {
+            if is_red(&h.right) && !is_red(&h.left) {
+                h = rotate_left(h);
+            }
+            if is_red(&h.left) && is_red_left(&h.left) {
+                h = rotate_right(h);
+            }
+            if is_red(&h.left) && is_red(&h.right) {
+                flip_colors(&mut h);
+            }
+            h
+        }
+
+        fn move_red_left<K: Clone, V: Clone>(mut h: $ointer<$node<K, V>>) -> $ointer<$node<K, V>> {
+            flip_colors(&mut h);
+            if is_red_left(&h.right) {
+                let n = node_mut(&mut h);
+                n.right = Some(rotate_right(n.right.take().unwrap()));
+                h = rotate_left(h);
+                flip_colors(&mut h);
+            }
+            h
+        }
+
+        fn move_red_right<K: Clone, V: Clone>(mut h: $ointer<$node<K, V>>) -> $ointer<$node<K, V>> {
+            flip_colors(&mut h);
+            if is_red_left(&h.left) {
+                h = rotate_right(h);
+                flip_colors(&mut h);
+            }
+            h
+        }
+
+        fn insert<K: Ord + Clone, V: Clone>(h: Link<K, V>, key: K, value: V, old: &mut Option<V>) -> $ointer<$node<K, V>> {
+            let Some(mut h) = h else {
+                let mut n = $ointer::new($node {
+                    key,
+                    value,
+                    left: None,
+                    right: None,
+                });
+                n.set_bool(true);
+                return n;
+            };
+            let n = node_mut(&mut h);
+            match key.cmp(&n.key) {
+                Ordering::Less => n.left = Some(insert(n.left.take(), key, value, old)),
+                Ordering::Greater => n.right = Some(insert(n.right.take(), key, value, old)),
+                Ordering::Equal => *old = Some(core::mem::replace(&mut n.value, value)),
+            }
+            fix_up(h)
+        }
+
+        fn remove_min<K: Clone, V: Clone>(mut h: $ointer<$node<K, V>>) -> (Link<K, V>, K, V) {
+            if h.left.is_none() {
+                return (None, h.key.clone(), h.value.clone());
+            }
+            if !is_red(&h.left) && !is_red_left(&h.left) {
+                h = move_red_left(h);
+            }
+            let n = node_mut(&mut h);
+            let (left, key, value) = remove_min(n.left.take().unwrap());
+            n.left = left;
+            (Some(fix_up(h)), key, value)
+        }
+
+        /// Remove `key`, known to be in the subtree of `h`.
+        fn remove<K: Ord + Clone, V: Clone>(mut h: $ointer<$node<K, V>>, key: &K, old: &mut Option<V>) -> Link<K, V> {
+            if *key < h.key {
+                if !is_red(&h.left) && !is_red_left(&h.left) {
+                    h = move_red_left(h);
+                }
+                let n = node_mut(&mut h);
+                n.left = remove(n.left.take().unwrap(), key, old);
+            } else {
+                if is_red(&h.left) {
+                    h = rotate_right(h);
+                }
+                if *key == h.key && h.right.is_none() {
+                    *old = Some(h.value.clone());
+                    return None;
+                }
+                if !is_red(&h.right) && !is_red_left(&h.right) {
+                    h = move_red_right(h);
+                }
+                let n = node_mut(&mut h);
+                if *key == n.key {
+                    let (right, k, v) = remove_min(n.right.take().unwrap());
+                    n.key = k;
+                    *old = Some(core::mem::replace(&mut n.value, v));
+                    n.right = right;
+                } else {
+                    n.right = remove(n.right.take().unwrap(), key, old);
+                }
+            }
+            Some(fix_up(h))
+        }
+
+        /// Get the number of black links from `link` down to any leaf, or `None` if invalid.
+        fn check<K: Ord, V>(link: &Link<K, V>, lower: Option<&K>, upper: Option<&K>) -> Option<usize> {
+            let Some(n) = link else {
+                return Some(0);
+            };
+            let ordered = lower.is_none_or(|l| *l < n.key) && upper.is_none_or(|u| n.key < *u);
+            let leaning = !is_red(&n.right) && !(n.o() && is_red(&n.left));
+            let l = check(&n.left, lower, Some(&n.key))?;
+            let r = check(&n.right, Some(&n.key), upper)?;
+            (ordered && leaning && l == r).then_some(l + usize::from(!n.o()))
+        }
+
+        impl<K, V> $map<K, V> {
+            /// Size of a node, without the header of its allocation.
+            pub const NODE_SIZE: usize = core::mem::size_of::<$node<K, V>>();
+
+            /// Make an empty map.
+            pub const fn new() -> Self {
+                Self { root: None, len: 0 }
+            }
+            /// Get the number of entries.
+            pub fn len(&self) -> usize {
+                self.len
+            }
+            /// Whether there is no entry.
+            pub fn is_empty(&self) -> bool {
+                self.len == 0
+            }
+            /// Iterate over entries in order of keys.
+            pub fn iter(&self) -> $iter<'_, K, V> {
+                let mut it = $iter {
+                    stack: Vec::new(),
+                    end: None,
+                };
+                it.push_left(self.root.as_deref());
+                it
+            }
+        }
+
+        impl<K: Ord, V> $map<K, V> {
+            /// Get the value of `key`.
+            pub fn get(&self, key: &K) -> Option<&V> {
+                let mut link = &self.root;
+                while let Some(n) = link {
+                    link = match key.cmp(&n.key) {
+                        Ordering::Less => &n.left,
+                        Ordering::Greater => &n.right,
+                        Ordering::Equal => return Some(&n.value),
+                    };
+                }
+                None
+            }
+            /// Whether there is an entry for `key`.
+            pub fn contains_key(&self, key: &K) -> bool {
+                self.get(key).is_some()
+            }
+            /// Iterate over entries with keys in `range`, in order of keys.
+            ///
+            /// # Panics
+            /// Panics like `BTreeMap::range` if the start of `range` is after its end, or equal to it with
+            /// both excluded.
+            pub fn range<R: RangeBounds<K>>(&self, range: R) -> $iter<'_, K, V> {
+                match (range.start_bound(), range.end_bound()) {
+                    (Bound::Excluded(s), Bound::Excluded(e)) if s == e => {
+                        panic!("Range start and end are equal and excluded")
+                    }
+                    (
+                        Bound::Included(s) | Bound::Excluded(s),
+                        Bound::Included(e) | Bound::Excluded(e),
+                    ) if s > e => panic!("Range start is greater than range end"),
+                    _ => {}
+                }
+                let mut it = $iter {
+                    stack: Vec::new(),
+                    end: None,
+                };
+                let mut link = &self.root;
+                while let Some(n) = link {
+                    let after_start = match range.start_bound() {
+                        Bound::Included(k) => *k <= n.key,
+                        Bound::Excluded(k) => *k < n.key,
+                        Bound::Unbounded => true,
+                    };
+                    if after_start {
+                        it.stack.push(n);
+                        link = &n.left;
+                    } else {
+                        link = &n.right;
+                    }
+                }
+                // The first node after the end, where to stop.
+                let mut link = &self.root;
+                while let Some(n) = link {
+                    let after_end = match range.end_bound() {
+                        Bound::Included(k) => *k < n.key,
+                        Bound::Excluded(k) => *k <= n.key,
+                        Bound::Unbounded => false,
+                    };
+                    if after_end {
+                        it.end = Some(n);
+                        link = &n.left;
+                    } else {
+                        link = &n.right;
+                    }
+                }
+                it
+            }
+            /// Check the invariants of left-leaning red-black trees: ordered keys, no red right links, no two
+            /// red links in a row, a black root, and the same number of black links from the root to any leaf.
+            pub fn check(&self) -> bool {
+                !is_red(&self.root) && check(&self.root, None, None).is_some()
+            }
+        }
+
+        impl<K: Ord + Clone, V: Clone> $map<K, V> {
+            /// Insert `value` at `key`, returning the value replaced, copying the nodes on the path to it if shared.
+            pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+                let mut old = None;
+                let mut root = insert(self.root.take(), key, value, &mut old);
+                root.set_bool(false);
+                self.root = Some(root);
+                self.len += usize::from(old.is_none());
+                old
+            }
+            /// Remove the entry of `key`, returning its value, copying the nodes on the path to it if shared.
+            pub fn remove(&mut self, key: &K) -> Option<V> {
+                if !self.contains_key(key) {
+                    return None;
+                }
+                let mut root = self.root.take().unwrap();
+                if !is_red(&root.left) && !is_red(&root.right) {
+                    root.set_bool(true);
+                }
+                let mut old = None;
+                self.root = remove(root, key, &mut old);
+                if let Some(root) = &mut self.root {
+                    root.set_bool(false);
+                }
+                self.len -= 1;
+                old
+            }
+        }
+
+        impl<K: Ord + Clone, V: Clone> FromIterator<(K, V)> for $map<K, V> {
+            fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+                let mut m = Self::new();
+                for (k, v) in iter {
+                    m.insert(k, v);
+                }
+                m
+            }
+        }
+
+        impl<K: core::fmt::Debug, V: core::fmt::Debug> core::fmt::Debug for $map<K, V> {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+                f.debug_map().entries(self.iter()).finish()
+            }
+        }
+
+        impl<'a, K, V> IntoIterator for &'a $map<K, V> {
+            type Item = (&'a K, &'a V);
+            type IntoIter = $iter<'a, K, V>;
+            fn into_iter(self) -> $iter<'a, K, V> {
+                self.iter()
+            }
+        }
+
+        /// Iterator over entries of a map in order of keys.
+        pub struct $iter<'a, K, V> {
+            stack: Vec<&'a $node<K, V>>,
+            end: Option<&'a $node<K, V>>,
+        }
+
+        impl<'a, K, V> $iter<'a, K, V> {
+            fn push_left(&mut self, mut node: Option<&'a $node<K, V>>) {
+                while let Some(n) = node {
+                    self.stack.push(n);
+                    node = n.left.as_deref();
+                }
+            }
+        }
+
+        impl<'a, K, V> Iterator for $iter<'a, K, V> {
+            type Item = (&'a K, &'a V);
+            fn next(&mut self) -> Option<Self::Item> {
+                let n = self.stack.pop()?;
+                if self.end.is_some_and(|e| core::ptr::eq(e, n)) {
+                    self.stack.clear();
+                    return None;
+                }
+                self.push_left(n.right.as_deref());
+                Some((&n.key, &n.value))
+            }
+        }
+    };
+}
+
+mod rc_map {
+    use super::*;
+    define_rb_map!(
+        /// Persistent ordered map over `ORc`s.
+        RbMap,
+        Iter,
+        Node,
+        ORc
+    );
+}
+
+mod arc_map {
+    use super::*;
+    define_rb_map!(
+        /// Persistent ordered map over `OArc`s, `Send` and `Sync` when keys and values are.
+        ArcRbMap,
+        ArcIter,
+        Node,
+        OArc
+    );
+}
+
+pub use arc_map::{ArcIter, ArcRbMap};
+pub use rc_map::{Iter, RbMap};
@@ -0,0 +1,280 @@
+//! This module defines `Complex`, an abstract simplicial complex whose simplices keep their faces as `OArc`s, the
+//! stolen bit holding the sign of each face in the boundary operator, with boundary matrices and Betti numbers.
+//!
+//! A simplex is stored once, with its vertices sorted, and an `OArc` to it is an oriented simplex, reversed by
+//! `OArc::flip()`.
+//! ```
+//! use ointer::simplex::{boundary, Complex, Field};
+//! let mut c = Complex::new();
+//! let t = c.add(&[1, 0, 2]);
+//! assert_eq!((t.vertices(), t.o()), (&[0, 1, 2][..], true));
+//! let b: Vec<_> = boundary(&t).iter().map(|f| (f.vertices().to_vec(), f.o())).collect();
+//! assert_eq!(b, [(vec![1, 2], true), (vec![0, 2], false), (vec![0, 1], true)]);
+//! assert_eq!(c.boundary_matrix(1), [[-1, -1, 0], [1, 0, -1], [0, 1, 1]]);
+//! assert!(c.check_boundary());
+//! assert_eq!(c.betti_numbers(Field::Q), [1, 0, 0]);
+//! ```
+
+use crate::sync::OArc;
+use std::collections::HashMap;
+
+/// Simplex with its vertices sorted, and its faces oriented as in its boundary.
+pub struct Simplex {
+    vertices: Vec<usize>,
+    faces: Vec<OArc<Simplex>>,
+    index: usize,
+}
+
+impl Simplex {
+    /// Get the sorted vertices.
+    pub fn vertices(&self) -> &[usize] {
+        &self.vertices
+    }
+    /// Get the dimension, one less than the number of vertices.
+    pub fn dim(&self) -> usize {
+        self.vertices.len() - 1
+    }
+    /// Get the faces, face `i` missing vertex `i` and flipped if `i` is odd.
+    pub fn faces(&self) -> &[OArc<Simplex>] {
+        &self.faces
+    }
+    /// Get the index among the simplices of the same dimension of its complex.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl core::fmt::Debug for Simplex {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+        self.vertices.fmt(f)
+    }
+}
+
+/// Get the sign of oriented simplex `s`, `-1` if flipped, `1` otherwise.
+pub fn sign(s: &OArc<Simplex>) -> i64 {
+    if s.o() {
+        -1
+    } else {
+        1
+    }
+}
+
+/// Get the boundary of oriented simplex `s`, its faces flipped if `s` is.
+pub fn boundary(s: &OArc<Simplex>) -> Vec<OArc<Simplex>> {
+    s.faces
+        .iter()
+        .map(|f| {
+            let mut f = f.clone();
+            if s.o() {
+                f.flip();
+            }
+            f
+        })
+        .collect()
+}
+
+/// Field of coefficients of homology.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Field {
+    /// Integers modulo 2, blind to orientation.
+    Z2,
+    /// Rationals.
+    Q,
+}
+
+/// Abstract simplicial complex, closed under taking faces.
+#[derive(Default)]
+pub struct Complex {
+    simplices: Vec<Vec<OArc<Simplex>>>,
+    lookup: HashMap<Vec<usize>, OArc<Simplex>>,
+}
+
+/// Sort `v` and return whether an odd permutation did it.
+fn sort_with_parity(v: &mut [usize]) -> bool {
+    let mut odd = false;
+    for i in 1..v.len() {
+        let mut j = i;
+        while j > 0 && v[j - 1] > v[j] {
+            v.swap(j - 1, j);
+            odd = !odd;
+            j -= 1;
+        }
+    }
+    odd
+}
+
+impl Complex {
+    /// Make an empty complex.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Make the complex of simplices with vertices `simplices`, and all their faces.
+    pub fn from_simplices<S: AsRef<[usize]>>(simplices: impl IntoIterator<Item = S>) -> Self {
+        let mut c = Self::new();
+        for s in simplices {
+            c.add(s.as_ref());
+        }
+        c
+    }
+    /// Add the simplex with vertices `vertices` and all its faces, if not yet there, and return it oriented by
+    /// the order of `vertices`.
+    ///
+    /// # Panics
+    /// Panics if `vertices` is empty or has a repeated vertex.
+    pub fn add(&mut self, vertices: &[usize]) -> OArc<Simplex> {
+        let mut v = vertices.to_vec();
+        let odd = sort_with_parity(&mut v);
+        assert!(
+            !v.is_empty() && v.windows(2).all(|w| w[0] < w[1]),
+            "Empty simplex or repeated vertex"
+        );
+        let mut s = self.add_sorted(v);
+        if odd {
+            s.flip();
+        }
+        s
+    }
+    fn add_sorted(&mut self, v: Vec<usize>) -> OArc<Simplex> {
+        if let Some(s) = self.lookup.get(&v) {
+            return s.clone();
+        }
+        let faces = match v.len() {
+            1 => Vec::new(),
+            // Missing the last vertex first, so that simplices come in lexicographic order.
+            _ => {
+                let mut faces: Vec<_> = (0..v.len())
+                    .rev()
+                    .map(|i| {
+                        let mut w = v.clone();
+                        w.remove(i);
+                        let mut f = self.add_sorted(w);
+                        if i % 2 == 1 {
+                            f.flip();
+                        }
+                        f
+                    })
+                    .collect();
+                faces.reverse();
+                faces
+            }
+        };
+        let k = v.len() - 1;
+        if self.simplices.len() <= k {
+            self.simplices.resize_with(k + 1, Vec::new);
+        }
+        let s = OArc::new(Simplex {
+            vertices: v.clone(),
+            faces,
+            index: self.simplices[k].len(),
+        });
+        self.simplices[k].push(s.clone());
+        self.lookup.insert(v, s.clone());
+        s
+    }
+    /// Get the simplex with vertices `vertices`, oriented by their order, if any.
+    pub fn get(&self, vertices: &[usize]) -> Option<OArc<Simplex>> {
+        let mut v = vertices.to_vec();
+        let odd = sort_with_parity(&mut v);
+        let mut s = self.lookup.get(&v)?.clone();
+        if odd {
+            s.flip();
+        }
+        Some(s)
+    }
+    /// Get the dimension, the largest one of its simplices, or `None` if empty.
+    pub fn dim(&self) -> Option<usize> {
+        self.simplices.len().checked_sub(1)
+    }
+    /// Get the simplices of dimension `k`, all unflipped.
+    pub fn simplices(&self, k: usize) -> &[OArc<Simplex>] {
+        self.simplices.get(k).map_or(&[], Vec::as_slice)
+    }
+    /// Get the Euler characteristic, the alternating sum of the numbers of simplices.
+    pub fn euler_characteristic(&self) -> isize {
+        (0..self.simplices.len())
+            .map(|k| self.simplices(k).len() as isize * if k % 2 == 0 { 1 } else { -1 })
+            .sum()
+    }
+    /// Get the matrix of the boundary operator from the `k`-chains to the `k - 1`-chains, with a row per
+    /// simplex of dimension `k - 1` and a column per simplex of dimension `k`.
+    pub fn boundary_matrix(&self, k: usize) -> Vec<Vec<i64>> {
+        let rows = match k {
+            0 => 0,
+            _ => self.simplices(k - 1).len(),
+        };
+        let mut m = vec![vec![0; self.simplices(k).len()]; rows];
+        for (j, s) in self.simplices(k).iter().enumerate() {
+            for f in boundary(s) {
+                m[f.index][j] += sign(&f);
+            }
+        }
+        m
+    }
+    /// Check that the boundary of a boundary is zero in every dimension.
+    pub fn check_boundary(&self) -> bool {
+        (2..self.simplices.len()).all(|k| {
+            let (a, b) = (self.boundary_matrix(k - 1), self.boundary_matrix(k));
+            a.iter().all(|r| {
+                (0..self.simplices(k).len())
+                    .all(|j| r.iter().zip(&b).map(|(x, c)| x * c[j]).sum::<i64>() == 0)
+            })
+        })
+    }
+    /// Get the Betti numbers over field `field`, from dimension `0` up to the dimension of the complex.
+    pub fn betti_numbers(&self, field: Field) -> Vec<usize> {
+        let ranks: Vec<_> = (0..=self.simplices.len())
+            .map(|k| rank(self.boundary_matrix(k), field))
+            .collect();
+        (0..self.simplices.len())
+            .map(|k| self.simplices(k).len() - ranks[k] - ranks[k + 1])
+            .collect()
+    }
+}
+
+/// Get the rank of matrix `m` over field `field`, by Gaussian elimination, fraction free over the rationals.
+fn rank(m: Vec<Vec<i64>>, field: Field) -> usize {
+    let mut m: Vec<Vec<i128>> = m
+        .into_iter()
+        .map(|r| r.into_iter().map(i128::from).collect())
+        .collect();
+    if field == Field::Z2 {
+        m.iter_mut().flatten().for_each(|x| *x = x.rem_euclid(2));
+    }
+    let cols = m.first().map_or(0, Vec::len);
+    let mut r = 0;
+    for c in 0..cols {
+        let Some(p) = (r..m.len()).find(|&i| m[i][c] != 0) else {
+            continue;
+        };
+        m.swap(r, p);
+        let pivot = m[r].clone();
+        for row in m.iter_mut().skip(r + 1) {
+            let x = row[c];
+            if x == 0 {
+                continue;
+            }
+            for (y, &z) in row.iter_mut().zip(&pivot) {
+                *y = match field {
+                    Field::Z2 => (*y + z) % 2,
+                    Field::Q => *y * pivot[c] - z * x,
+                };
+            }
+            if field == Field::Q {
+                let g = row.iter().fold(0, |g, &y| gcd(g, y.abs()));
+                if g > 1 {
+                    row.iter_mut().for_each(|y| *y /= g);
+                }
+            }
+        }
+        r += 1;
+    }
+    r
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
@@ -0,0 +1,173 @@
+//! This module defines `Tagged`, a generic `ointer` that wraps any [`StealablePointer`] and steals its high `N` bits.
+
+use crate::ointer::*;
+use core::ops::{Deref, DerefMut};
+
+/// Generic `ointer` over any [`StealablePointer`] `P`, with high `N` bits stolen, and restored by
+/// sign extension if `S` is `true`.
+///
+/// Unlike the `ointer`s stamped out by `define_ointer!`, `Tagged` is not tied to a pointer taking a
+/// single type parameter, so custom handles or third-party pointers can be wrapped once they
+/// implement [`StealablePointer`].
+/// ```
+/// use ointer::{Ointer, Tagged};
+/// use std::rc::Rc;
+/// let mut o = Tagged::<_, 3>::new(Rc::new(5));
+/// o.set_usize(6);
+/// let p = o.clone();
+/// assert_eq!((*p, p.get_usize()), (5, 6));
+/// assert_eq!(o.map(|_: usize, p| Rc::strong_count(p)), 2);
+/// assert_eq!(*o.into_inner(), 5);
+/// ```
+/// The stolen bits are stored as [`TagStrategy::for_target`] chooses: in the free high bits of the
+/// pointer if there are enough of them, in its free low bits, or in the header of its allocation, and
+/// stealing more bits than any of them fails to compile:
+/// ```
+/// use ointer::{Ointer, TagStrategy, Tagged};
+/// assert_eq!(<Tagged<Box<u64>, 3> as Ointer<3>>::STRATEGY, TagStrategy::for_target::<Box<u64>>(0, 3));
+/// let mut o = Tagged::<_, 3>::new(Box::new(1u64));
+/// o.set_usize(7);
+/// *o += 1;
+/// assert_eq!((*o, o.get_usize()), (2, 7));
+/// ```
+/// ```compile_fail
+/// use ointer::Tagged;
+/// let o = Tagged::<_, 20>::new(Box::new(1u64));
+/// ```
+#[repr(transparent)]
+pub struct Tagged<P: StealablePointer, const N: usize, const S: bool = false>(P);
+
+unsafe impl<P: StealablePointer, const N: usize, const S: bool> Ointer<N> for Tagged<P, N, S> {
+    type Pointer = P;
+    const SIGN_EXTEND: bool = S;
+    const STRATEGY: TagStrategy = TagStrategy::for_target::<P>(0, N);
+    const OFFSET: usize = {
+        assert!(
+            core::mem::size_of::<P>() == core::mem::size_of::<usize>(),
+            "Stealable pointers must be a single word"
+        );
+        0
+    };
+}
+
+impl<P: StealablePointer, const N: usize, const S: bool> Tagged<P, N, S> {
+    /// Wrap pointer `p` with high `N` bits all `0`.
+    pub fn new(p: P) -> Self {
+        let mut s = Self(p);
+        s.steal();
+        s
+    }
+    /// Unwrap the pointer, dropping its stolen bits.
+    pub fn into_inner(mut self) -> P {
+        self.restore_ptr();
+        let s = core::mem::ManuallyDrop::new(self);
+        unsafe { core::ptr::read(&s.0) }
+    }
+}
+
+impl<P: StealablePointer, const N: usize, const S: bool> From<P> for Tagged<P, N, S> {
+    fn from(p: P) -> Self {
+        Self::new(p)
+    }
+}
+
+/// Only for pointers that can be dereferenced, and so are never dangling: the default `Weak` is a
+/// sentinel address with all bits set, which has no bits to steal.
+/// ```compile_fail
+/// use ointer::Tagged;
+/// let _ = Tagged::<std::rc::Weak<u8>, 3>::default();
+/// ```
+impl<P: StealablePointer + Default + Deref, const N: usize, const S: bool> Default
+    for Tagged<P, N, S>
+{
+    fn default() -> Self {
+        Self::new(P::default())
+    }
+}
+
+impl<P: StealablePointer + Clone, const N: usize, const S: bool> Clone for Tagged<P, N, S> {
+    fn clone(&self) -> Self {
+        self.map(|u: usize, p| {
+            let mut o = Self::new(p.clone());
+            o.set_usize(u);
+            o
+        })
+    }
+}
+
+impl<P: StealablePointer + core::fmt::Debug, const N: usize, const S: bool> core::fmt::Debug
+    for Tagged<P, N, S>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
+        self.map(|u: usize, p| (u, p).fmt(f))
+    }
+}
+
+impl<P: StealablePointer, const N: usize, const S: bool> Drop for Tagged<P, N, S> {
+    fn drop(&mut self) {
+        self.restore_ptr();
+    }
+}
+
+impl<P: StealablePointer + core::hash::Hash, const N: usize, const S: bool> core::hash::Hash
+    for Tagged<P, N, S>
+{
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.map(|u: usize, p| (u, p).hash(state))
+    }
+}
+
+impl<P: StealablePointer + PartialEq, const N: usize, const S: bool> PartialEq for Tagged<P, N, S> {
+    fn eq(&self, rhs: &Self) -> bool {
+        self.map(|u: usize, p| rhs.map(|c, q| (u, p).eq(&(c, q))))
+    }
+}
+
+impl<P: StealablePointer + Eq, const N: usize, const S: bool> Eq for Tagged<P, N, S> {}
+
+impl<P: StealablePointer + PartialOrd, const N: usize, const S: bool> PartialOrd
+    for Tagged<P, N, S>
+{
+    fn partial_cmp(&self, rhs: &Self) -> Option<core::cmp::Ordering> {
+        self.map(|u: usize, p| rhs.map(|c, q| (u, p).partial_cmp(&(c, q))))
+    }
+}
+
+impl<P: StealablePointer + Ord, const N: usize, const S: bool> Ord for Tagged<P, N, S> {
+    fn cmp(&self, rhs: &Self) -> core::cmp::Ordering {
+        self.map(|u: usize, p| rhs.map(|c, q| (u, p).cmp(&(c, q))))
+    }
+}
+
+impl<P: StealablePointer + Deref, const N: usize, const S: bool> Deref for Tagged<P, N, S> {
+    type Target = P::Target;
+    fn deref(&self) -> &P::Target {
+        self.map(|_: usize, p| unsafe { &*(p.deref() as *const P::Target) })
+    }
+}
+
+impl<P: StealablePointer + DerefMut, const N: usize, const S: bool> DerefMut for Tagged<P, N, S> {
+    fn deref_mut(&mut self) -> &mut P::Target {
+        self.map_mut(|_: &mut usize, p| unsafe { &mut *(p.deref_mut() as *mut P::Target) })
+    }
+}
+
+impl<P: StealablePointer, const S: bool> Tagged<P, 1, S> {
+    /// Get first bit and cast as bool.
+    pub fn o(&self) -> bool {
+        self.get_bool()
+    }
+    /// Flip first bit.
+    pub fn flip(&mut self) {
+        self.set_bool(!self.o());
+    }
+    /// Clone and flip.
+    pub fn clone_and_flip(&self) -> Self
+    where
+        Self: Clone,
+    {
+        let mut o = self.clone();
+        o.flip();
+        o
+    }
+}